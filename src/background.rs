@@ -0,0 +1,193 @@
+use crate::vector::*;
+
+/// ## SUN_ANGULAR_RADIUS
+/// Angular radius of the sun disk in radians as seen from the ground
+pub const SUN_ANGULAR_RADIUS: f32 = 0.00465;
+
+/// ## STAR_CELLS
+/// Number of cells along each axis the directions of a star field are divided into
+const STAR_CELLS: f32 = 400.0;
+
+/// ## Background
+/// Representation of what a ray that doesn't hit anything in the scene sees.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Background {
+    /// The same color in every direction, like a studio backdrop or black for a scene lit only by lights
+    SolidColor(Color),
+    /// Blend from the bottom color straight down to the top color straight up
+    Gradient { bottom: Color, top: Color },
+    /// Daylight sky with a bright sun disk in the direction of sun_dir.
+    /// A turbidity of 1.0 is a perfectly clear sky, higher values give a hazier sky.
+    PhysicalSky { sun_dir: Vector3, turbidity: f32 },
+    /// Black space with white stars in about the fraction density of all directions.
+    /// The same seed always gives the same stars.
+    Starfield { density: f32, seed: u64 },
+}
+
+impl Background {
+    /// ## value
+    /// Returns the Color seen when looking in the given direction
+    pub fn value(&self, direction: Vector3) -> Color {
+        let unit_dir: Vector3 = match direction.try_unit_vec() {
+            Some(unit_dir) => unit_dir,
+            None => return match *self {
+                // A solid color doesn't depend on the direction, so even a ray without one sees it
+                Background::SolidColor(color) => color,
+                _ => Color::new(0.0, 0.0, 0.0), // A ray without a direction doesn't see anything else
+            },
+        };
+        match *self {
+            Background::SolidColor(color) => color,
+            Background::Gradient { bottom, top } => {
+                let t: f32 = 0.5*(unit_dir.y + 1.0);
+                bottom.lerp(top, t)
+            }
+            Background::PhysicalSky { sun_dir, turbidity } => {
+                let haze: f32 = ((turbidity - 1.0) / 9.0).clamp(0.0, 1.0);
+                let zenith: Color = Color::new(0.25, 0.45, 0.9).lerp(Color::new(0.75, 0.75, 0.78), haze);
+                let horizon: Color = Color::new(0.85, 0.9, 1.0);
+                let ground: Color = Color::new(0.35, 0.32, 0.3);
+                let sky: Color = if unit_dir.y >= 0.0 {
+                    horizon.lerp(zenith, unit_dir.y)
+                } else {
+                    horizon.lerp(ground, -unit_dir.y)
+                };
+
+                // The sun gets dimmer and its glow wider the hazier the sky is
+                let sun_color: Color = Color::new(1.0, 0.9, 0.7);
+                let cos_angle: f32 = sun_dir.try_unit_vec().map_or(-1.0, |sun_dir| unit_dir.dot(sun_dir));
+                if cos_angle >= SUN_ANGULAR_RADIUS.cos() {
+                    sky + sun_color * (100.0 / turbidity.max(1.0))
+                } else {
+                    let glow: f32 = cos_angle.max(0.0).powf(256.0 / (1.0 + 3.0*haze));
+                    sky + sun_color * glow
+                }
+            }
+            Background::Starfield { density, seed } => {
+                // Nearby directions share a cell, which is a star or not depending on its hash
+                let cell: Vector3 = unit_dir * STAR_CELLS;
+                let hash: u64 = star_hash(seed, [cell.x.floor() as i64, cell.y.floor() as i64, cell.z.floor() as i64]);
+                if ((hash >> 40) as f32 / (1u64 << 24) as f32) < density {
+                    Color::new(1.0, 1.0, 1.0)
+                } else {
+                    Color::new(0.0, 0.0, 0.0)
+                }
+            }
+        }
+    }
+}
+
+impl Default for Background {
+    /// ## default
+    /// Returns the standard white to light blue sky gradient
+    fn default() -> Background {
+        Background::Gradient {
+            bottom: Color::new(1.0, 1.0, 1.0),
+            top: Color::new(0.5, 0.7, 1.0),
+        }
+    }
+}
+
+/// ## star_hash
+/// Returns a well mixed hash of a star field cell and seed, using the SplitMix64 finalizer
+fn star_hash(seed: u64, cell: [i64; 3]) -> u64 {
+    let mut hash: u64 = seed;
+    for coordinate in cell {
+        hash = (hash ^ coordinate as u64).wrapping_add(0x9e3779b97f4a7c15);
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+        hash ^= hash >> 31;
+    }
+    hash
+}
+
+/// Tests for Background enum
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn background_gradient() {
+        let background = Background::default();
+
+        assert_eq!(background.value(Vector3::new(0.0, 1.0, 0.0)), Color::new(0.5, 0.7, 1.0));
+        assert_eq!(background.value(Vector3::new(0.0, -1.0, 0.0)), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn background_solid_color() {
+        let background = Background::SolidColor(Color::new(0.2, 0.3, 0.4));
+
+        for direction in [Vector3::new(0.0, 1.0, 0.0), Vector3::new(1.0, -2.0, 3.0), Vector3::new(0.0, 0.0, 0.0)] {
+            assert_eq!(background.value(direction), Color::new(0.2, 0.3, 0.4));
+        }
+    }
+
+    #[test]
+    fn background_physical_sky_sun_disk() {
+        let sun_dir = Vector3::new(0.0, 1.0, -1.0);
+        let background = Background::PhysicalSky { sun_dir, turbidity: 2.0 };
+
+        // Tilt the direction half the angular radius away from the sun center
+        let offset = Vector3::new(1.0, 0.0, 0.0) * (0.5 * SUN_ANGULAR_RADIUS * sun_dir.normal());
+        let in_sun = background.value(sun_dir + offset);
+        let away = background.value(Vector3::new(0.0, 1.0, 1.0));
+
+        assert!(in_sun.x > 10.0 * away.x);
+        assert!(in_sun.y > 10.0 * away.y);
+        assert!(in_sun.z > 10.0 * away.z);
+    }
+
+    #[test]
+    fn background_physical_sky_finite() {
+        let background = Background::PhysicalSky { sun_dir: Vector3::new(0.3, 0.5, -1.0), turbidity: 6.0 };
+
+        for _ in 0..1000 {
+            let color = background.value(Vector3::random_in_unit());
+            assert!(color.x.is_finite() && color.y.is_finite() && color.z.is_finite());
+            assert!(color.x >= 0.0 && color.y >= 0.0 && color.z >= 0.0);
+        }
+    }
+
+    #[test]
+    fn background_starfield_deterministic() {
+        let background = Background::Starfield { density: 0.1, seed: 42 };
+        let mut rng = StdRng::seed_from_u64(477);
+
+        for _ in 0..1000 {
+            let direction = Vector3::random_on_sphere_with(1.0, &mut rng);
+            assert_eq!(background.value(direction), background.value(direction));
+            assert_eq!(background.value(direction), Background::Starfield { density: 0.1, seed: 42 }.value(direction));
+        }
+    }
+
+    #[test]
+    fn background_starfield_density() {
+        let background = Background::Starfield { density: 0.05, seed: 7 };
+        let samples = 20000;
+        let mut rng = StdRng::seed_from_u64(477);
+        let mut stars = 0;
+        for _ in 0..samples {
+            if background.value(Vector3::random_on_sphere_with(1.0, &mut rng)) == Color::new(1.0, 1.0, 1.0) {
+                stars += 1;
+            }
+        }
+        let fraction = stars as f32 / samples as f32;
+
+        assert!((fraction - 0.05).abs() < 0.01, "fraction {}", fraction);
+    }
+
+    #[test]
+    fn background_starfield_seed() {
+        let a = Background::Starfield { density: 0.5, seed: 1 };
+        let b = Background::Starfield { density: 0.5, seed: 2 };
+        let mut rng = StdRng::seed_from_u64(477);
+        let differing = (0..1000)
+            .map(|_| Vector3::random_on_sphere_with(1.0, &mut rng))
+            .filter(|d| a.value(*d) != b.value(*d))
+            .count();
+
+        assert!(differing > 100);
+    }
+}
@@ -1,29 +1,325 @@
-use crate::{vector::*, ray::Ray};
+use crate::{vector::*, ray::Ray, content_hash::ContentHasher, hitables::aabb::Aabb};
+
+use rand::Rng;
 
 /// ## Camera
 /// Representation of a camera containing information about what is captured in the scene.
+/// u and v are unit vectors pointing right and up as seen from the camera.
+/// Rays start at random points on a lens of radius lens_radius around the origin, so only things
+/// at the focus distance are sharp. A radius of zero is a pinhole camera where everything is sharp.
+/// Rays are sent off at random times while the shutter is open from time0 to time1, which blurs moving objects.
 pub struct Camera {
     low_left_corner: Vector3,
     horizontal: Vector3,
     vertical: Vector3,
     origin: Vector3,
+    u: Vector3,
+    v: Vector3,
+    lens_radius: f32,
+    time0: f32,
+    time1: f32,
 }
 
-impl Camera {
-    /// ## new
-    /// Returns a new Camera with standard values
-    pub fn new() -> Camera {
+impl Default for Camera {
+    /// ## default
+    /// Returns a Camera at the origin looking down -z with a 4 by 2 viewport one unit away
+    fn default() -> Camera {
         Camera {
             low_left_corner: Vector3::new(-2.0, -1.0, -1.0),
             horizontal: Vector3::new(4.0, 0.0, 0.0),
             vertical: Vector3::new(0.0, 2.0, 0.0),
             origin: Vector3::new(0.0, 0.0, 0.0),
+            u: Vector3::new(1.0, 0.0, 0.0),
+            v: Vector3::new(0.0, 1.0, 0.0),
+            lens_radius: 0.0,
+            time0: 0.0,
+            time1: 0.0,
+        }
+    }
+}
+
+impl Camera {
+    /// ## new
+    /// Returns a Camera at lookfrom looking towards lookat, turned so vup points up in the image.
+    /// vfov_degrees is the vertical field of view and aspect_ratio the width of the image divided by its height.
+    /// aperture is the diameter of the lens and focus_distance how far from lookfrom things are sharp.
+    pub fn new(lookfrom: Vector3, lookat: Vector3, vup: Vector3, vfov_degrees: f32, aspect_ratio: f32, aperture: f32, focus_distance: f32) -> Camera {
+        let half_height: f32 = (vfov_degrees.to_radians() / 2.0).tan();
+        let half_width: f32 = aspect_ratio * half_height;
+        let w: Vector3 = (lookfrom - lookat).unit_vec();
+        let u: Vector3 = vup.cross(w).unit_vec();
+        let v: Vector3 = w.cross(u);
+        Camera {
+            low_left_corner: lookfrom - (u * half_width + v * half_height + w) * focus_distance,
+            horizontal: u * (2.0 * half_width * focus_distance),
+            vertical: v * (2.0 * half_height * focus_distance),
+            origin: lookfrom,
+            u,
+            v,
+            lens_radius: aperture / 2.0,
+            time0: 0.0,
+            time1: 0.0,
+        }
+    }
+
+    /// ## with_shutter
+    /// Returns the camera with its shutter open from time0 to time1 instead of only at time 0
+    pub fn with_shutter(self, time0: f32, time1: f32) -> Camera {
+        Camera {
+            time0,
+            time1,
+            ..self
         }
     }
 
     /// ## get_ray
-    /// Returns a ray from the origin towards a direction given by how much moved in horizontal and vertical given with u respective v
+    /// Returns a ray from the origin towards a direction given by how much moved in horizontal and vertical given with u respective v.
+    /// With a lens the ray starts at a random point on it, aimed at the same point on the focus plane.
+    /// The lens point and time come from the thread's own generator and aren't repeatable, see get_ray_with.
     pub fn get_ray(&self, u: f32, v: f32) -> Ray {
-        Ray::new(self.origin, self.low_left_corner + self.horizontal * u + self.vertical * v - self.origin)
+        self.get_ray_with(u, v, &mut rand::thread_rng())
+    }
+
+    /// ## get_ray_with
+    /// Returns a ray like get_ray, picking the point on the lens, the time and the random number of the ray
+    /// with the given random number generator
+    pub fn get_ray_with<R: Rng + ?Sized>(&self, u: f32, v: f32, rng: &mut R) -> Ray {
+        let target: Vector3 = self.low_left_corner + self.horizontal * u + self.vertical * v;
+        let origin: Vector3 = if self.lens_radius > 0.0 {
+            let disk: Vector3 = Vector3::random_in_unit_disk_with(rng) * self.lens_radius;
+            self.origin + self.u * disk.x + self.v * disk.y
+        } else {
+            self.origin
+        };
+        let time: f32 = if self.time1 > self.time0 { rng.gen_range(self.time0..self.time1) } else { self.time0 };
+        let mut ray: Ray = Ray::new_at(origin, target - origin, time);
+        ray.random = rng.gen_range(0.0..1.0);
+        ray
+    }
+
+    /// ## get_center_ray
+    /// Returns the ray get_ray would return through the center of the lens at the start of the shutter.
+    /// It uses no random numbers, so it is the same every time even with a lens.
+    pub fn get_center_ray(&self, u: f32, v: f32) -> Ray {
+        let target: Vector3 = self.low_left_corner + self.horizontal * u + self.vertical * v;
+        Ray::new_at(self.origin, target - self.origin, self.time0)
     }
-}
\ No newline at end of file
+
+    /// ## frustum
+    /// Returns the frustum containing every ray get_ray returns for u between u_min and u_max
+    /// and v between v_min and v_max. None for a camera with a lens, where the rays don't all
+    /// start in the same point.
+    pub fn frustum(&self, u_min: f32, v_min: f32, u_max: f32, v_max: f32) -> Option<Frustum> {
+        if self.lens_radius > 0.0 {
+            return None;
+        }
+        let corner = |u: f32, v: f32| self.low_left_corner + self.horizontal * u + self.vertical * v - self.origin;
+        let corners: [Vector3; 4] = [corner(u_min, v_min), corner(u_max, v_min), corner(u_max, v_max), corner(u_min, v_max)];
+        let center: Vector3 = corner((u_min + u_max) / 2.0, (v_min + v_max) / 2.0);
+        let planes: [Vector3; 4] = std::array::from_fn(|i| {
+            let normal: Vector3 = corners[i].cross(corners[(i + 1) % 4]);
+            if normal.dot(center) < 0.0 { -normal } else { normal }
+        });
+        Some(Frustum {
+            apex: self.origin,
+            planes,
+        })
+    }
+
+    /// ## get_ray_with_footprint
+    /// Returns the same ray as get_ray together with the approximate size of a pixel at the focus plane,
+    /// where du and dv are the width and height of a pixel in u and v.
+    /// The size is the side of a square with the same area as the pixel.
+    pub fn get_ray_with_footprint(&self, u: f32, v: f32, du: f32, dv: f32) -> (Ray, f32) {
+        self.get_ray_with_footprint_with(u, v, du, dv, &mut rand::thread_rng())
+    }
+
+    /// ## get_ray_with_footprint_with
+    /// Returns the same ray and size as get_ray_with_footprint, picking the ray like get_ray_with
+    /// with the given random number generator
+    pub fn get_ray_with_footprint_with<R: Rng + ?Sized>(&self, u: f32, v: f32, du: f32, dv: f32, rng: &mut R) -> (Ray, f32) {
+        let area: f32 = (self.horizontal * du).cross(self.vertical * dv).normal();
+        (self.get_ray_with(u, v, rng), area.sqrt())
+    }
+
+    /// ## content_hash
+    /// Returns a hash of the position and viewport of the camera.
+    /// Cameras set up the same way always get the same hash, also between runs.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher: ContentHasher = ContentHasher::new();
+        hasher.write_str("Camera");
+        hasher.write_vector(self.low_left_corner);
+        hasher.write_vector(self.horizontal);
+        hasher.write_vector(self.vertical);
+        hasher.write_vector(self.origin);
+        hasher.write_f32(self.lens_radius);
+        hasher.write_f32(self.time0);
+        hasher.write_f32(self.time1);
+        hasher.finish()
+    }
+}
+
+/// ## Frustum
+/// The pyramid starting at apex that a bundle of camera rays goes through,
+/// bounded by four planes through the apex with normals pointing inwards.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Frustum {
+    apex: Vector3,
+    planes: [Vector3; 4],
+}
+
+impl Frustum {
+    /// ## may_contain
+    /// Returns false if the box is entirely outside one of the planes, so no ray in the frustum can hit it.
+    /// Boxes outside the frustum near its edges may still give true.
+    pub fn may_contain(&self, bbox: &Aabb) -> bool {
+        self.planes.iter().all(|normal| {
+            (0..8).any(|corner| {
+                let point: Vector3 = Vector3::new(
+                    if corner & 1 == 0 { bbox.min.x } else { bbox.max.x },
+                    if corner & 2 == 0 { bbox.min.y } else { bbox.max.y },
+                    if corner & 4 == 0 { bbox.min.z } else { bbox.max.z },
+                );
+                normal.dot(point - self.apex) >= 0.0
+            })
+        })
+    }
+}
+
+/// Tests for Camera struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn camera_new_viewport() {
+        let cam = Camera::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0), 90.0, 1.0, 0.0, 1.0);
+
+        assert!((cam.vertical.normal() - 2.0).abs() < 1e-5);
+        assert!((cam.horizontal.normal() - 2.0).abs() < 1e-5);
+        assert!((cam.get_ray(0.5, 0.5).direction - Vector3::new(0.0, 0.0, -1.0)).normal() < 1e-5);
+    }
+
+    #[test]
+    fn camera_default_matches_new() {
+        // The default pinhole camera is a 90 degree view with aspect ratio 2 down -z
+        let default = Camera::default();
+        let cam = Camera::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0), 90.0, 2.0, 0.0, 1.0);
+
+        assert!((cam.low_left_corner - default.low_left_corner).normal() < 1e-5);
+        assert!((cam.horizontal - default.horizontal).normal() < 1e-5);
+        assert!((cam.vertical - default.vertical).normal() < 1e-5);
+        assert_eq!(cam.origin, default.origin);
+        assert_eq!(cam.lens_radius, default.lens_radius);
+    }
+
+    #[test]
+    fn camera_new_looks_at_target() {
+        let lookfrom = Vector3::new(3.0, 2.0, 1.0);
+        let lookat = Vector3::new(0.0, 0.0, -1.0);
+        let cam = Camera::new(lookfrom, lookat, Vector3::new(0.0, 1.0, 0.0), 40.0, 2.0, 0.0, 1.0);
+        let ray = cam.get_ray(0.5, 0.5);
+
+        assert_eq!(ray.origin, lookfrom);
+        assert!((ray.direction.unit_vec() - (lookat - lookfrom).unit_vec()).normal() < 1e-5);
+        assert!(cam.vertical.cross(cam.horizontal).dot(ray.direction) > 0.0);
+    }
+
+    #[test]
+    fn camera_zero_aperture_is_pinhole() {
+        let lookfrom = Vector3::new(1.0, 2.0, 3.0);
+        let cam = Camera::new(lookfrom, Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0), 60.0, 1.5, 0.0, 4.0);
+
+        for i in 0..100 {
+            let s = i as f32 / 100.0;
+            assert_eq!(cam.get_ray(s, 1.0 - s).origin, lookfrom);
+        }
+    }
+
+    #[test]
+    fn camera_aperture_focus_plane() {
+        let lookfrom = Vector3::new(0.0, 0.0, 0.0);
+        let cam = Camera::new(lookfrom, Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0), 60.0, 1.5, 0.5, 4.0);
+        let focus_point = cam.low_left_corner + cam.horizontal * 0.3 + cam.vertical * 0.6;
+
+        for _ in 0..100 {
+            let ray = cam.get_ray(0.3, 0.6);
+            // Rays start on the lens and all pass through the same point on the focus plane
+            assert!((ray.origin - lookfrom).normal() <= 0.25);
+            assert!(ray.origin.z.abs() < 1e-6);
+            let t = (focus_point.z - ray.origin.z) / ray.direction.z;
+            assert!((ray.point_at(t) - focus_point).normal() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn camera_center_ray() {
+        let camera = Camera::new(Vector3::new(1.0, 0.0, 0.0), Vector3::new(1.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0), 60.0, 1.5, 0.5, 4.0)
+            .with_shutter(0.5, 1.0);
+        let ray = camera.get_center_ray(0.3, 0.6);
+
+        assert_eq!(ray.origin, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(ray.time, 0.5);
+        assert_eq!(ray.direction, camera.low_left_corner + camera.horizontal * 0.3 + camera.vertical * 0.6 - ray.origin);
+    }
+
+    #[test]
+    fn camera_shutter_times() {
+        let camera = Camera::default().with_shutter(1.0, 1.5);
+        let times: Vec<f32> = (0..1000).map(|_| camera.get_ray(0.5, 0.5).time).collect();
+
+        assert!(times.iter().all(|time| (1.0..1.5).contains(time)));
+        assert!(times.iter().any(|time| *time < 1.1) && times.iter().any(|time| *time > 1.4));
+        assert_eq!(Camera::default().get_ray(0.5, 0.5).time, 0.0);
+        assert_ne!(camera.content_hash(), Camera::default().content_hash());
+    }
+
+    #[test]
+    fn camera_frustum() {
+        let cam = Camera::default();
+        let frustum = cam.frustum(0.0, 0.5, 0.5, 1.0).unwrap();
+        let small = |center: Vector3| Aabb::new(center - Vector3::new(0.1, 0.1, 0.1), center + Vector3::new(0.1, 0.1, 0.1));
+
+        // The top left quarter of the image
+        assert!(frustum.may_contain(&small(Vector3::new(-1.0, 0.5, -1.0))));
+        assert!(!frustum.may_contain(&small(Vector3::new(1.0, 0.5, -1.0))));
+        assert!(!frustum.may_contain(&small(Vector3::new(-1.0, -0.5, -1.0))));
+        // Straddling the edge between the quarters
+        assert!(frustum.may_contain(&small(Vector3::new(0.0, 0.5, -1.0))));
+
+        let lens = Camera::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0), 90.0, 2.0, 0.1, 1.0);
+        assert_eq!(lens.frustum(0.0, 0.5, 0.5, 1.0), None);
+    }
+
+    #[test]
+    fn camera_get_ray_with_footprint_ray() {
+        let cam = Camera::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0), 90.0, 2.0, 0.1, 1.0)
+            .with_shutter(0.0, 1.0);
+        let (ray, _) = cam.get_ray_with_footprint_with(0.25, 0.75, 0.01, 0.02, &mut StdRng::seed_from_u64(470));
+        let plain = cam.get_ray_with(0.25, 0.75, &mut StdRng::seed_from_u64(470));
+
+        assert_eq!(ray, plain);
+    }
+
+    #[test]
+    fn camera_get_ray_with_footprint_size() {
+        let cam = Camera::default();
+        // A pixel of 1/400 of the 4 wide viewport and 1/200 of the 2 high viewport is 0.01 on each side
+        let (_, small) = cam.get_ray_with_footprint(0.5, 0.5, 1.0 / 400.0, 1.0 / 200.0);
+        let (_, large) = cam.get_ray_with_footprint(0.5, 0.5, 3.0 / 400.0, 3.0 / 200.0);
+
+        assert!((small - 0.01).abs() < 1e-6);
+        assert!((large - 3.0 * small).abs() < 1e-6);
+    }
+
+    #[test]
+    fn camera_content_hash() {
+        let a = Camera::default();
+        let mut b = Camera::default();
+        assert_eq!(a.content_hash(), b.content_hash());
+
+        b.origin = Vector3::new(0.0, 0.1, 0.0);
+        assert_ne!(a.content_hash(), b.content_hash());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::render::validate_gamma;
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// ## RenderArgs
+/// Image size, quality and gamma given on the command line with --width, --height, --samples, --depth and --gamma
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct RenderArgs {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: usize,
+    pub max_depth: usize,
+    pub gamma: f32,
+}
+
+impl Default for RenderArgs {
+    /// ## default
+    /// Returns the size and quality used when nothing else is given, the 1000x500 image
+    /// main rendered before it read any arguments
+    fn default() -> RenderArgs {
+        RenderArgs {
+            width: 1000,
+            height: 500,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            gamma: 2.0,
+        }
+    }
+}
+
+impl RenderArgs {
+    /// ## parse
+    /// Reads the arguments after the program name, given as "--width 400" or "--width=400".
+    /// Missing values keep their default, and invalid values or unknown arguments print a warning
+    /// and are ignored. A value starting with -- is read as the next argument instead, so
+    /// "--width --height 5" warns about the missing width and still reads the height.
+    /// Returns an error if the width or height is zero, since there is nothing to render,
+    /// or if the gamma isn't above zero.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<RenderArgs, String> {
+        let mut parsed: RenderArgs = RenderArgs::default();
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            let (name, inline_value): (String, Option<String>) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            if !["--width", "--height", "--samples", "--depth", "--gamma"].contains(&name.as_str()) {
+                eprintln!("Warning: ignoring unknown argument {}", name);
+                continue;
+            }
+            let value: Option<String> = inline_value.or_else(|| args.next_if(|next| !next.starts_with("--")));
+            match name.as_str() {
+                "--width" => parse_value(&name, value, &mut parsed.width),
+                "--height" => parse_value(&name, value, &mut parsed.height),
+                "--samples" => parse_value(&name, value, &mut parsed.samples_per_pixel),
+                "--depth" => parse_value(&name, value, &mut parsed.max_depth),
+                _ => parse_value(&name, value, &mut parsed.gamma),
+            }
+        }
+
+        if parsed.width == 0 || parsed.height == 0 {
+            return Err(format!("Can't render an image of {}x{} pixels, width and height must be at least 1",
+                               parsed.width, parsed.height));
+        }
+        validate_gamma(parsed.gamma)?;
+        Ok(parsed)
+    }
+}
+
+/// ## parse_value
+/// Stores the value given for the argument name in target, or prints a warning and keeps target
+/// as it is if the value is missing or invalid
+fn parse_value<T: FromStr + Display>(name: &str, value: Option<String>, target: &mut T) {
+    match value {
+        Some(value) => match value.parse::<T>() {
+            Ok(value) => *target = value,
+            Err(_) => eprintln!("Warning: invalid value {} for {}, using {}", value, name, target),
+        },
+        None => eprintln!("Warning: missing value for {}, using {}", name, target),
+    }
+}
+
+/// Tests for command line arguments
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn render_args_defaults() {
+        assert_eq!(RenderArgs::parse(args(&[])), Ok(RenderArgs::default()));
+    }
+
+    #[test]
+    fn render_args_parse() {
+        let parsed = RenderArgs::parse(args(&["--width", "400", "--height=200", "--samples", "8", "--depth=5", "--gamma", "2.2"])).unwrap();
+
+        assert_eq!(parsed, RenderArgs { width: 400, height: 200, samples_per_pixel: 8, max_depth: 5, gamma: 2.2 });
+    }
+
+    #[test]
+    fn render_args_invalid_values_keep_defaults() {
+        let parsed = RenderArgs::parse(args(&["--width", "wide", "--samples=-3", "--gamma=dark", "--verbose", "--depth"])).unwrap();
+
+        assert_eq!(parsed, RenderArgs::default());
+    }
+
+    #[test]
+    fn render_args_missing_value_before_flag() {
+        let parsed = RenderArgs::parse(args(&["--width", "--height", "5", "--samples", "--depth=7"])).unwrap();
+
+        assert_eq!(parsed, RenderArgs { height: 5, max_depth: 7, ..RenderArgs::default() });
+        // Negative numbers are still taken as values and then rejected
+        assert!(RenderArgs::parse(args(&["--gamma", "-2"])).is_err());
+    }
+
+    #[test]
+    fn render_args_zero_size() {
+        assert!(RenderArgs::parse(args(&["--width", "0"])).unwrap_err().contains("0x500"));
+        assert!(RenderArgs::parse(args(&["--height=0"])).is_err());
+        assert!(RenderArgs::parse(args(&["--samples", "0"])).is_ok());
+    }
+
+    #[test]
+    fn render_args_gamma() {
+        assert_eq!(RenderArgs::parse(args(&["--gamma", "1"])).unwrap().gamma, 1.0);
+        assert!(RenderArgs::parse(args(&["--gamma", "0"])).unwrap_err().contains("Gamma"));
+        assert!(RenderArgs::parse(args(&["--gamma=-2.2"])).is_err());
+        assert!(RenderArgs::parse(args(&["--gamma=NaN"])).is_err());
+    }
+}
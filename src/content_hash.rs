@@ -0,0 +1,95 @@
+use crate::vector::Vector3;
+
+/// ## ContentHasher
+/// A 64 bit FNV-1a hasher for describing the contents of scenes and cameras.
+/// Unlike the hashers of the standard library the result is guaranteed to stay the same
+/// between runs, platforms and compiler versions, so it can be used in file names.
+pub struct ContentHasher {
+    state: u64,
+}
+
+impl Default for ContentHasher {
+    /// ## default
+    /// Returns a ContentHasher that hasn't been given any data
+    fn default() -> ContentHasher {
+        ContentHasher {
+            state: 0xcbf29ce484222325,
+        }
+    }
+}
+
+impl ContentHasher {
+    /// ## new
+    /// Returns the same as default
+    pub fn new() -> ContentHasher {
+        ContentHasher::default()
+    }
+
+    /// ## write_bytes
+    /// Adds raw bytes to the hash
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= *byte as u64;
+            self.state = self.state.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    /// ## write_str
+    /// Adds a string to the hash, such as the name of a type
+    pub fn write_str(&mut self, s: &str) {
+        self.write_u64(s.len() as u64);
+        self.write_bytes(s.as_bytes());
+    }
+
+    /// ## write_u64
+    /// Adds an integer to the hash
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// ## write_f32
+    /// Adds a float to the hash using its exact bits
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_bytes(&value.to_bits().to_le_bytes());
+    }
+
+    /// ## write_vector
+    /// Adds the x, y and z of a Vector3 to the hash
+    pub fn write_vector(&mut self, v: Vector3) {
+        self.write_f32(v.x);
+        self.write_f32(v.y);
+        self.write_f32(v.z);
+    }
+
+    /// ## finish
+    /// Returns the hash of everything written so far
+    pub fn finish(&self) -> u64 {
+        self.state
+    }
+}
+
+/// Tests for ContentHasher struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_hasher_known_value() {
+        // FNV-1a of "a"
+        let mut hasher = ContentHasher::new();
+        hasher.write_bytes(b"a");
+        assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn content_hasher_order_matters() {
+        let mut a = ContentHasher::new();
+        a.write_f32(1.0);
+        a.write_f32(2.0);
+        let mut b = ContentHasher::new();
+        b.write_f32(2.0);
+        b.write_f32(1.0);
+
+        assert_ne!(a.finish(), b.finish());
+    }
+}
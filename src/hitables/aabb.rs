@@ -0,0 +1,98 @@
+use crate::ray::Ray;
+use crate::vector::Vector3;
+
+/// ## Aabb
+/// An axis aligned bounding box spanning from the corner min to the corner max.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Aabb {
+    pub min: Vector3,
+    pub max: Vector3,
+}
+
+impl Aabb {
+    /// ## new
+    /// Returns an Aabb with the corners min and max given as arguments
+    pub fn new(min: Vector3, max: Vector3) -> Aabb {
+        Aabb {
+            min,
+            max,
+        }
+    }
+
+    /// ## centroid
+    /// Returns the point in the middle of the box
+    pub fn centroid(&self) -> Vector3 {
+        self.min.midpoint(self.max)
+    }
+
+    /// ## hit
+    /// Checks whether a given Ray passes through the box somewhere between t_min and t_max.
+    /// Uses the slab method, intersecting the ray with the pair of planes of each axis in turn.
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        let mut t_min: f32 = t_min;
+        let mut t_max: f32 = t_max;
+        for axis in 0..3 {
+            let inv_d: f32 = 1.0 / ray.direction[axis];
+            let mut t0: f32 = (self.min[axis] - ray.origin[axis]) * inv_d;
+            let mut t1: f32 = (self.max[axis] - ray.origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // max/min ignore the NaN a ray lying exactly in a slab plane gives
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max <= t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// ## surrounding_box
+/// Returns the smallest Aabb containing both of the given boxes
+pub fn surrounding_box(a: Aabb, b: Aabb) -> Aabb {
+    Aabb {
+        min: Vector3::new(a.min.x.min(b.min.x), a.min.y.min(b.min.y), a.min.z.min(b.min.z)),
+        max: Vector3::new(a.max.x.max(b.max.x), a.max.y.max(b.max.y), a.max.z.max(b.max.z)),
+    }
+}
+
+/// Tests for Aabb struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aabb_hit() {
+        let aabb = Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert!(aabb.hit(&ray, 0.001, f32::MAX));
+    }
+
+    #[test]
+    fn aabb_hit_beside() {
+        let aabb = Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+        let ray = Ray::new(Vector3::new(2.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert!(!aabb.hit(&ray, 0.001, f32::MAX));
+    }
+
+    #[test]
+    fn aabb_hit_out_of_range() {
+        let aabb = Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert!(!aabb.hit(&ray, 0.001, 3.0));
+    }
+
+    #[test]
+    fn aabb_surrounding_box() {
+        let a = Aabb::new(Vector3::new(-1.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let b = Aabb::new(Vector3::new(0.0, -2.0, 0.5), Vector3::new(0.5, 0.5, 3.0));
+        let c = Aabb::new(Vector3::new(-1.0, -2.0, 0.0), Vector3::new(1.0, 1.0, 3.0));
+
+        assert_eq!(c, surrounding_box(a, b));
+    }
+}
@@ -0,0 +1,272 @@
+use super::*;
+use super::aabb::{Aabb, surrounding_box};
+
+/// ## BvhNode
+/// A node in a bounding volume hierarchy.
+/// Each node holds up to two children and a box surrounding both of them,
+/// so a ray missing the box can skip everything inside it.
+pub struct BvhNode {
+    left: Box<dyn Hitable>,
+    right: Option<Box<dyn Hitable>>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    /// ## new
+    /// Builds a hierarchy over the given objects.
+    /// Objects are sorted by the centroid of their bounding box along the axis the centroids are
+    /// spread out the most and split in half, recursively.
+    /// Panics if the list is empty or contains an object without a bounding box.
+    pub fn new(objects: Vec<Box<dyn Hitable>>) -> BvhNode {
+        assert!(!objects.is_empty(), "Can't build a BvhNode without objects");
+        let mut boxed: Vec<(Aabb, Box<dyn Hitable>)> = objects.into_iter()
+            .map(|object| (object.bounding_box().expect("Object in BvhNode has no bounding box"), object))
+            .collect();
+
+        if boxed.len() <= 2 {
+            let (left_box, left) = boxed.remove(0);
+            return match boxed.pop() {
+                Some((right_box, right)) => BvhNode {
+                    left,
+                    right: Some(right),
+                    bbox: surrounding_box(left_box, right_box),
+                },
+                None => BvhNode {
+                    left,
+                    right: None,
+                    bbox: left_box,
+                },
+            };
+        }
+
+        let axis: usize = split_axis(boxed.iter().map(|(bbox, _)| *bbox));
+        boxed.sort_by(|(a, _), (b, _)| a.centroid()[axis].total_cmp(&b.centroid()[axis]));
+        let upper: Vec<Box<dyn Hitable>> = boxed.split_off(boxed.len() / 2).into_iter().map(|(_, object)| object).collect();
+        let lower: Vec<Box<dyn Hitable>> = boxed.into_iter().map(|(_, object)| object).collect();
+
+        let left: BvhNode = BvhNode::new(lower);
+        let right: BvhNode = BvhNode::new(upper);
+        BvhNode {
+            bbox: surrounding_box(left.bbox, right.bbox),
+            left: Box::new(left),
+            right: Some(Box::new(right)),
+        }
+    }
+}
+
+/// ## split_axis
+/// Returns the axis (0, 1 or 2 for x, y or z) along which the centroids of the boxes are spread out the most
+fn split_axis<I: Iterator<Item = Aabb>>(boxes: I) -> usize {
+    let centroid_bounds: Aabb = boxes
+        .map(|bbox| Aabb::new(bbox.centroid(), bbox.centroid()))
+        .reduce(surrounding_box)
+        .expect("List of boxes is not empty");
+    let extent: Vector3 = centroid_bounds.max - centroid_bounds.min;
+    if extent.x >= extent.y && extent.x >= extent.z {
+        0
+    } else if extent.y >= extent.z {
+        1
+    } else {
+        2
+    }
+}
+
+impl Hitable for BvhNode {
+    /// ## hit_record
+    /// Checks whether a given Ray hits any object in the hierarchy.
+    /// Children are only tested if the ray passes through the box surrounding them.
+    /// If it hits return information regarding the closest hit.
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        if !self.bbox.hit(ray, t_min, t_max) {
+            return None;
+        }
+        let left: Option<HitRecord> = self.left.hit_record(ray, t_min, t_max);
+        let closest_yet: f32 = left.map_or(t_max, |record| record.t);
+        self.right.as_ref()
+            .and_then(|right| right.hit_record(ray, t_min, closest_yet))
+            .or(left)
+    }
+
+    /// ## bounding_box
+    /// Returns the box surrounding every object in the hierarchy
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bbox)
+    }
+
+    /// ## hash_content
+    /// Adds the children to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("BvhNode");
+        self.left.hash_content(hasher);
+        if let Some(right) = &self.right {
+            right.hash_content(hasher);
+        }
+    }
+
+    /// ## tessellate
+    /// Returns the triangles of both children, None if a child can't be made into triangles
+    fn tessellate(&self, subdivisions: usize) -> Option<Vec<Triangle>> {
+        let mut triangles: Vec<Triangle> = self.left.tessellate(subdivisions)?;
+        if let Some(right) = &self.right {
+            triangles.extend(right.tessellate(subdivisions)?);
+        }
+        Some(triangles)
+    }
+}
+
+/// ## BvhIndex
+/// A bounding volume hierarchy over a list of objects that holds indices into the list
+/// instead of the objects themselves, so the list can stay where it is.
+/// Built the same way as a BvhNode, and stored as a flat list of nodes with the root last.
+pub struct BvhIndex {
+    nodes: Vec<BvhIndexNode>,
+}
+
+/// ## BvhIndexNode
+/// A node of a BvhIndex, either an object or two child nodes, with the box surrounding them
+enum BvhIndexNode {
+    Leaf { bbox: Aabb, index: usize },
+    Branch { bbox: Aabb, left: usize, right: usize },
+}
+
+impl BvhIndex {
+    /// ## new
+    /// Builds a hierarchy over the given objects.
+    /// None if there are no objects or an object has no bounding box.
+    pub fn new(objects: &[Box<dyn Hitable>]) -> Option<BvhIndex> {
+        if objects.is_empty() {
+            return None;
+        }
+        let mut boxed: Vec<(Aabb, usize)> = objects.iter().enumerate()
+            .map(|(index, object)| Some((object.bounding_box()?, index)))
+            .collect::<Option<Vec<(Aabb, usize)>>>()?;
+        let mut nodes: Vec<BvhIndexNode> = Vec::with_capacity(2 * objects.len());
+        BvhIndex::build(&mut boxed, &mut nodes);
+        Some(BvhIndex {
+            nodes,
+        })
+    }
+
+    /// ## build
+    /// Adds the nodes of a hierarchy over the boxed objects to nodes and returns the index of its root
+    fn build(boxed: &mut [(Aabb, usize)], nodes: &mut Vec<BvhIndexNode>) -> usize {
+        if let [(bbox, index)] = boxed {
+            nodes.push(BvhIndexNode::Leaf { bbox: *bbox, index: *index });
+            return nodes.len() - 1;
+        }
+        let axis: usize = split_axis(boxed.iter().map(|(bbox, _)| *bbox));
+        boxed.sort_by(|(a, _), (b, _)| a.centroid()[axis].total_cmp(&b.centroid()[axis]));
+        let (lower, upper) = boxed.split_at_mut(boxed.len() / 2);
+        let left: usize = BvhIndex::build(lower, nodes);
+        let right: usize = BvhIndex::build(upper, nodes);
+        let bbox: Aabb = surrounding_box(nodes[left].bbox(), nodes[right].bbox());
+        nodes.push(BvhIndexNode::Branch { bbox, left, right });
+        nodes.len() - 1
+    }
+
+    /// ## hit
+    /// Checks whether a given Ray hits any of the objects, which have to be the ones the hierarchy
+    /// was built over. Returns the index of the closest object hit and stores the hit in the HitRecord.
+    pub fn hit<'a>(&self, objects: &'a [Box<dyn Hitable>], ray: &Ray, t_min: f32, t_max: f32, hit_rec: &mut HitRecord<'a>) -> Option<usize> {
+        let mut temp_rec: HitRecord = HitRecord::new();
+        let mut hit_index: Option<usize> = None;
+        let mut closest_yet: f32 = t_max;
+        let mut stack: Vec<usize> = vec![self.nodes.len() - 1];
+        while let Some(node) = stack.pop() {
+            match self.nodes[node] {
+                BvhIndexNode::Leaf { bbox, index } => {
+                    if bbox.hit(ray, t_min, closest_yet) && objects[index].hit(ray, t_min, closest_yet, &mut temp_rec) {
+                        hit_index = Some(index);
+                        closest_yet = temp_rec.t;
+                        *hit_rec = temp_rec;
+                    }
+                }
+                BvhIndexNode::Branch { bbox, left, right } => {
+                    if bbox.hit(ray, t_min, closest_yet) {
+                        stack.push(right);
+                        stack.push(left);
+                    }
+                }
+            }
+        }
+        hit_index
+    }
+}
+
+impl BvhIndexNode {
+    /// ## bbox
+    /// Returns the box surrounding everything in the node
+    fn bbox(&self) -> Aabb {
+        match self {
+            BvhIndexNode::Leaf { bbox, .. } => *bbox,
+            BvhIndexNode::Branch { bbox, .. } => *bbox,
+        }
+    }
+}
+
+/// Tests for BvhNode struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::objects::Sphere;
+
+    #[test]
+    fn bvh_node_bounding_box() {
+        let bvh = BvhNode::new(vec![
+            Box::new(Sphere::new(Vector3::new(0.0, 0.0, 0.0), 1.0)),
+            Box::new(Sphere::new(Vector3::new(4.0, 0.0, 0.0), 1.0)),
+            Box::new(Sphere::new(Vector3::new(0.0, 0.0, -4.0), 2.0)),
+        ]);
+        let expected = Aabb::new(Vector3::new(-2.0, -2.0, -6.0), Vector3::new(5.0, 2.0, 1.0));
+
+        assert_eq!(Some(expected), bvh.bounding_box());
+    }
+
+    #[test]
+    fn bvh_node_matches_linear_scene() {
+        use super::scene::Scene;
+        use super::objects::Triangle;
+        use crate::camera::Camera;
+        use rand::{Rng, SeedableRng, rngs::StdRng};
+
+        let objects = || -> Vec<Box<dyn Hitable>> {
+            let mut rng = StdRng::seed_from_u64(7);
+            (0..60).map(|i| {
+                let center = Vector3::new(rng.gen_range(-3.0..3.0), rng.gen_range(-2.0..2.0), rng.gen_range(-6.0..-1.5));
+                if i % 3 == 0 {
+                    Box::new(Triangle::new(center, center + Vector3::new(0.4, 0.0, 0.1), center + Vector3::new(0.0, 0.4, -0.1))) as Box<dyn Hitable>
+                } else {
+                    Box::new(Sphere::new(center, rng.gen_range(0.05..0.4))) as Box<dyn Hitable>
+                }
+            }).collect()
+        };
+        let bvh = BvhNode::new(objects());
+        let mut scene = Scene::from_objects(objects());
+        scene.bvh_threshold = usize::MAX;
+
+        let cam = Camera::default();
+        let (width, height) = (40, 20);
+        let mut hits: usize = 0;
+        for row in 0..height {
+            for col in 0..width {
+                let ray = cam.get_ray((col as f32 + 0.5) / width as f32, (row as f32 + 0.5) / height as f32);
+                let bvh_rec = bvh.hit_record(&ray, 0.001, f32::MAX);
+                let scene_rec = scene.hit_record(&ray, 0.001, f32::MAX);
+                assert_eq!(bvh_rec.is_some(), scene_rec.is_some());
+                if let (Some(bvh_rec), Some(scene_rec)) = (bvh_rec, scene_rec) {
+                    assert_eq!(bvh_rec.t, scene_rec.t);
+                    assert_eq!(bvh_rec.p, scene_rec.p);
+                    assert_eq!(bvh_rec.normal, scene_rec.normal);
+                    hits += 1;
+                }
+            }
+        }
+        assert!(hits > 50);
+    }
+
+    #[test]
+    fn bvh_node_empty() {
+        let result = std::panic::catch_unwind(|| BvhNode::new(vec![]));
+        assert!(result.is_err());
+    }
+}
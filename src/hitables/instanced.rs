@@ -0,0 +1,140 @@
+use super::*;
+use super::aabb::surrounding_box;
+use super::objects::hit_sphere;
+use super::materials::{Lambertian, DEFAULT_ALBEDO};
+
+/// ## InstancedSpheres
+/// Many spheres sharing one radius and material, only differing in center.
+/// Stores just a Vector3 per sphere, which suits particle like scenes with thousands of spheres.
+#[derive(Debug)]
+pub struct InstancedSpheres {
+    pub centers: Vec<Vector3>,
+    pub radius: f32,
+    pub material: Box<dyn Material>,
+}
+
+impl InstancedSpheres {
+    /// ## new
+    /// Returns spheres at the given centers with the given radius, made of the standard gray diffuse material
+    pub fn new(centers: Vec<Vector3>, radius: f32) -> InstancedSpheres {
+        InstancedSpheres::with_material(centers, radius, Box::new(Lambertian::new(DEFAULT_ALBEDO)))
+    }
+
+    /// ## with_material
+    /// Returns spheres at the given centers with the given radius and material
+    pub fn with_material(centers: Vec<Vector3>, radius: f32, material: Box<dyn Material>) -> InstancedSpheres {
+        InstancedSpheres {
+            centers,
+            radius,
+            material,
+        }
+    }
+
+    /// ## hit_instance
+    /// Checks whether a given Ray hits any of the spheres.
+    /// If it hits return the index of the closest sphere hit together with information regarding the hit.
+    pub fn hit_instance<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(usize, HitRecord<'a>)> {
+        let mut closest: Option<(usize, HitRecord<'a>)> = None;
+        let mut closest_t: f32 = t_max;
+        for (index, center) in self.centers.iter().enumerate() {
+            // Only hits closer than the closest so far count, so the record is made for few of the spheres
+            if let Some(hit_rec) = hit_sphere(*center, self.radius, self.material.as_ref(), ray, t_min, closest_t) {
+                closest_t = hit_rec.t;
+                closest = Some((index, hit_rec));
+            }
+        }
+        closest
+    }
+}
+
+impl Hitable for InstancedSpheres {
+    /// ## hit_record
+    /// Checks whether a given Ray hits any of the spheres.
+    /// If it hits return information regarding the closest hit.
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        self.hit_instance(ray, t_min, t_max).map(|(_, hit_rec)| hit_rec)
+    }
+
+    /// ## bounding_box
+    /// Returns the box surrounding all spheres, None if there are no spheres
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r: Vector3 = Vector3::new(self.radius, self.radius, self.radius);
+        self.centers.iter()
+            .map(|center| Aabb::new(*center - r, *center + r))
+            .reduce(surrounding_box)
+    }
+
+    /// ## hash_content
+    /// Adds the centers, radius and material to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("InstancedSpheres");
+        hasher.write_u64(self.centers.len() as u64);
+        for center in &self.centers {
+            hasher.write_vector(*center);
+        }
+        hasher.write_f32(self.radius);
+        self.material.hash_content(hasher);
+    }
+
+    /// ## tessellate
+    /// Returns the triangles of every sphere tessellated like a single Sphere
+    fn tessellate(&self, subdivisions: usize) -> Option<Vec<Triangle>> {
+        let mut triangles: Vec<Triangle> = Vec::new();
+        for center in &self.centers {
+            triangles.extend(Sphere::new(*center, self.radius).tessellate(subdivisions)?);
+        }
+        Some(triangles)
+    }
+}
+
+/// Tests for InstancedSpheres struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::scene::Scene;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn instanced_spheres_match_spheres() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let centers: Vec<Vector3> = (0..200)
+            .map(|_| Vector3::new(rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0), rng.gen_range(-15.0..-5.0)))
+            .collect();
+        let instanced = InstancedSpheres::new(centers.clone(), 0.3);
+        let scene = Scene::from_objects(centers.iter()
+            .map(|center| Box::new(Sphere::new(*center, 0.3)) as Box<dyn Hitable>)
+            .collect());
+
+        let mut hits: usize = 0;
+        for _ in 0..2000 {
+            let origin = Vector3::new(rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0), rng.gen_range(-1.0..1.0));
+            let direction = Vector3::new(rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5), -1.0);
+            let ray = Ray::new(origin, direction);
+
+            let mut scene_rec = HitRecord::new();
+            let scene_hit = scene.hit(&ray, 0.001, f32::MAX, &mut scene_rec);
+            match instanced.hit_instance(&ray, 0.001, f32::MAX) {
+                Some((index, hit_rec)) => {
+                    assert!(scene_hit);
+                    assert_eq!(hit_rec.t, scene_rec.t);
+                    assert_eq!(hit_rec.p, scene_rec.p);
+                    assert_eq!(hit_rec.normal, scene_rec.normal);
+                    assert!(((hit_rec.p - centers[index]).normal() - 0.3).abs() < 1e-3);
+                    hits += 1;
+                }
+                None => assert!(!scene_hit),
+            }
+        }
+        assert!(hits > 100);
+    }
+
+    #[test]
+    fn instanced_spheres_bounding_box() {
+        let instanced = InstancedSpheres::new(vec![Vector3::new(1.0, 0.0, 0.0), Vector3::new(-2.0, 3.0, 1.0)], 0.5);
+        let bbox = instanced.bounding_box().unwrap();
+
+        assert_eq!(bbox.min, Vector3::new(-2.5, -0.5, -0.5));
+        assert_eq!(bbox.max, Vector3::new(1.5, 3.5, 1.5));
+        assert!(InstancedSpheres::new(Vec::new(), 1.0).bounding_box().is_none());
+    }
+}
@@ -0,0 +1,689 @@
+use super::*;
+use super::objects::orthonormal_basis;
+use crate::vector::Color;
+use crate::texture::{Texture, SolidColor};
+use rand::{Rng, RngCore};
+use std::fmt::Debug;
+use std::sync::LazyLock;
+
+/// ## Material
+/// Describes how light interacts with the surface of an object.
+pub trait Material: Debug + Send + Sync {
+    /// Returns true if the incoming ray is scattered, with the scattered ray and how much it is
+    /// attenuated stored in scattered and attenuation. Returns false if the ray is absorbed.
+    /// Random choices are made with rng, so a seeded generator makes the scattering repeatable.
+    fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray, rng: &mut dyn RngCore) -> bool;
+    /// Returns the light given off by the surface, black for materials that don't glow
+    fn emitted(&self) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+    /// Adds the type and parameters of the material to a hash describing the scene
+    fn hash_content(&self, hasher: &mut ContentHasher);
+    /// Returns false if rays scattered by the material shouldn't use up the max depth of a path,
+    /// like for glass where light bounces many times inside an object
+    fn counts_towards_depth(&self) -> bool {
+        true
+    }
+    /// Returns true for materials scattering light in or close to a single direction, like mirrors and glass
+    fn is_specular(&self) -> bool {
+        false
+    }
+    /// Scatters like scatter, but at least as rough as min_roughness between 0 and 1.
+    /// Used for path regularization, materials without a roughness scatter as usual.
+    fn scatter_regularized(&self, ray_in: &Ray, hit_rec: &HitRecord, _min_roughness: f32, attenuation: &mut Color, scattered: &mut Ray, rng: &mut dyn RngCore) -> bool {
+        self.scatter(ray_in, hit_rec, attenuation, scattered, rng)
+    }
+}
+
+/// ## DEFAULT_ALBEDO
+/// Gray albedo of the material used for surfaces without a material of their own
+pub const DEFAULT_ALBEDO: Color = Vector3 { x: 0.5, y: 0.5, z: 0.5 };
+
+/// ## DEFAULT_MATERIAL
+/// Material used for surfaces without a material of their own
+pub static DEFAULT_MATERIAL: LazyLock<Lambertian> = LazyLock::new(|| Lambertian::new(DEFAULT_ALBEDO));
+
+/// ## Lambertian
+/// A matte surface scattering light diffusely in all directions.
+/// The albedo is a texture, so the color may change over the surface.
+#[derive(Debug)]
+pub struct Lambertian {
+    pub albedo: Box<dyn Texture>,
+}
+
+impl Lambertian {
+    /// ## new
+    /// Returns a Lambertian with the same albedo all over
+    pub fn new(albedo: Color) -> Lambertian {
+        Lambertian::with_texture(Box::new(SolidColor::new(albedo)))
+    }
+
+    /// ## with_texture
+    /// Returns a Lambertian with its albedo given by a texture
+    pub fn with_texture(albedo: Box<dyn Texture>) -> Lambertian {
+        Lambertian {
+            albedo,
+        }
+    }
+}
+
+impl Material for Lambertian {
+    /// ## scatter
+    /// Scatters the ray towards a random point on a unit sphere touching the surface at the hit point,
+    /// which spreads the rays with Lambert's cosine law. If that point is too close to the hit point
+    /// to give a direction the ray scatters along the normal.
+    fn scatter(&self, _ray_in: &Ray, hit_rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray, rng: &mut dyn RngCore) -> bool {
+        let direction: Vector3 = hit_rec.normal + Vector3::random_unit_vector_with(rng);
+        let direction: Vector3 = if direction.near_zero() { hit_rec.normal } else { direction };
+        *scattered = Ray::new(hit_rec.p, direction);
+        *attenuation = self.albedo.value(hit_rec.u, hit_rec.v, hit_rec.p);
+        true
+    }
+
+    /// ## hash_content
+    /// Adds the albedo to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("Lambertian");
+        self.albedo.hash_content(hasher);
+    }
+}
+
+/// ## Isotropic
+/// Scatters light equally in every direction, like the particles of fog and smoke.
+/// Meant for volumes such as ConstantMedium, where the ray scatters inside and not off a surface.
+#[derive(Debug)]
+pub struct Isotropic {
+    pub albedo: Box<dyn Texture>,
+}
+
+impl Isotropic {
+    /// ## new
+    /// Returns an Isotropic with the same albedo all over
+    pub fn new(albedo: Color) -> Isotropic {
+        Isotropic {
+            albedo: Box::new(SolidColor::new(albedo)),
+        }
+    }
+}
+
+impl Material for Isotropic {
+    /// ## scatter
+    /// Scatters the ray in a random direction from the hit point
+    fn scatter(&self, _ray_in: &Ray, hit_rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray, rng: &mut dyn RngCore) -> bool {
+        *scattered = Ray::new(hit_rec.p, Vector3::random_unit_vector_with(rng));
+        *attenuation = self.albedo.value(hit_rec.u, hit_rec.v, hit_rec.p);
+        true
+    }
+
+    /// ## hash_content
+    /// Adds the albedo to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("Isotropic");
+        self.albedo.hash_content(hasher);
+    }
+}
+
+/// ## DiffuseLight
+/// A glowing surface giving off the same light in all directions, like a lamp.
+/// Rays hitting it stop there and see its emitted color.
+#[derive(Debug)]
+pub struct DiffuseLight {
+    pub emit: Color,
+}
+
+impl DiffuseLight {
+    /// ## new
+    /// Returns a DiffuseLight giving off the given color
+    pub fn new(emit: Color) -> DiffuseLight {
+        DiffuseLight {
+            emit,
+        }
+    }
+}
+
+impl Material for DiffuseLight {
+    /// ## scatter
+    /// Lights absorb every ray, so nothing is scattered
+    fn scatter(&self, _ray_in: &Ray, _hit_rec: &HitRecord, _attenuation: &mut Color, _scattered: &mut Ray, _rng: &mut dyn RngCore) -> bool {
+        false
+    }
+
+    /// ## emitted
+    /// Returns the emitted color
+    fn emitted(&self) -> Color {
+        self.emit
+    }
+
+    /// ## hash_content
+    /// Adds the emitted color to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("DiffuseLight");
+        hasher.write_vector(self.emit);
+    }
+}
+
+/// ## Dielectric
+/// A clear material like glass or water which refracts light passing through it.
+/// refraction_index is the index for yellow light at 587.6 nm. cauchy_b is the second coefficient
+/// of Cauchy's equation in square micrometers and decides how much the index changes with the
+/// wavelength. Zero means no dispersion.
+#[derive(Debug)]
+pub struct Dielectric {
+    pub refraction_index: f32,
+    pub cauchy_b: f32,
+}
+
+impl Dielectric {
+    /// ## new
+    /// Returns a Dielectric with the given refraction index and no dispersion
+    pub fn new(refraction_index: f32) -> Dielectric {
+        Dielectric {
+            refraction_index,
+            cauchy_b: 0.0,
+        }
+    }
+
+    /// ## with_dispersion
+    /// Returns a Dielectric with the given refraction index where the index varies with the
+    /// wavelength according to cauchy_b. Crown glass has a cauchy_b of about 0.0042.
+    pub fn with_dispersion(refraction_index: f32, cauchy_b: f32) -> Dielectric {
+        Dielectric {
+            refraction_index,
+            cauchy_b,
+        }
+    }
+
+    /// ## refraction_index_at
+    /// Returns the refraction index for light of the given wavelength in nanometers.
+    /// Without a wavelength the refraction index of yellow light is used.
+    pub fn refraction_index_at(&self, wavelength: Option<f32>) -> f32 {
+        match wavelength {
+            Some(nm) => {
+                let micrometers: f32 = nm / 1000.0;
+                self.refraction_index + self.cauchy_b * (1.0 / (micrometers*micrometers) - 1.0 / (0.5876*0.5876))
+            }
+            None => self.refraction_index,
+        }
+    }
+}
+
+impl Material for Dielectric {
+    /// ## scatter
+    /// Refracts the ray through the surface using Snell's law. The ray is reflected instead if it
+    /// can't be refracted, or randomly with the probability given by Schlick's approximation.
+    fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray, rng: &mut dyn RngCore) -> bool {
+        let index: f32 = self.refraction_index_at(ray_in.wavelength);
+        let unit_dir: Vector3 = match ray_in.direction.try_unit_vec() {
+            Some(unit_dir) => unit_dir,
+            None => return false,
+        };
+        // The normal points against the ray, and a ray hitting the back face is leaving the object
+        let normal: Vector3 = hit_rec.normal;
+        let ratio: f32 = if hit_rec.front_face { 1.0 / index } else { index };
+
+        let cos_theta: f32 = (-unit_dir).dot(normal).min(1.0);
+        let sin_theta: f32 = (1.0 - cos_theta*cos_theta).sqrt();
+        let cannot_refract: bool = ratio * sin_theta > 1.0;
+        let direction: Vector3 = if cannot_refract || reflectance(cos_theta, ratio) > rng.gen_range(0.0..1.0) {
+            unit_dir.reflect(normal)
+        } else {
+            refract(unit_dir, normal, ratio)
+        };
+        debug_assert!(scatter_is_valid(ray_in, direction, None), "Dielectric scattered a ray in an invalid direction");
+
+        *attenuation = Color::new(1.0, 1.0, 1.0);
+        *scattered = Ray::new(hit_rec.p, direction);
+        true
+    }
+
+    /// ## hash_content
+    /// Adds the refraction index and dispersion to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("Dielectric");
+        hasher.write_f32(self.refraction_index);
+        hasher.write_f32(self.cauchy_b);
+    }
+
+    /// ## counts_towards_depth
+    /// Light passes through glass with a bounce on the way in and one on the way out,
+    /// so these bounces don't count towards the max depth
+    fn counts_towards_depth(&self) -> bool {
+        false
+    }
+
+    /// ## is_specular
+    /// Glass reflects and refracts in single directions
+    fn is_specular(&self) -> bool {
+        true
+    }
+}
+
+/// ## RoughnessModel
+/// How the roughness of a Metal spreads out its reflections.
+/// Fuzz moves the reflected direction by a random point in a sphere with the roughness as radius.
+/// Ggx reflects in a microfacet normal sampled from the GGX distribution, which gives highlights
+/// with a sharp peak and long tails like those of real metals.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum RoughnessModel {
+    Fuzz,
+    Ggx,
+}
+
+/// ## Metal
+/// A shiny surface reflecting light in the mirror direction, spread out by its fuzz.
+#[derive(Debug)]
+pub struct Metal {
+    pub albedo: Color,
+    /// Roughness between 0 (a perfect mirror) and 1
+    fuzz: f32,
+    pub model: RoughnessModel,
+}
+
+impl Metal {
+    /// ## new
+    /// Returns a Metal with the given albedo and fuzz using the simple fuzz model.
+    /// Fuzz above 1 is clamped to 1.
+    pub fn new(albedo: Color, fuzz: f32) -> Metal {
+        Metal::with_model(albedo, fuzz, RoughnessModel::Fuzz)
+    }
+
+    /// ## with_model
+    /// Returns a Metal with the given albedo and roughness using the given roughness model.
+    /// Roughness above 1 is clamped to 1.
+    pub fn with_model(albedo: Color, roughness: f32, model: RoughnessModel) -> Metal {
+        Metal {
+            albedo,
+            fuzz: roughness.min(1.0),
+            model,
+        }
+    }
+
+    /// ## fuzz
+    /// Returns the roughness of the metal, at most 1
+    pub fn fuzz(&self) -> f32 {
+        self.fuzz
+    }
+
+    /// ## set_fuzz
+    /// Sets the roughness of the metal. Fuzz above 1 is clamped to 1 like in new.
+    pub fn set_fuzz(&mut self, fuzz: f32) {
+        self.fuzz = fuzz.min(1.0);
+    }
+
+    /// ## scatter_with_roughness
+    /// Reflects the ray in the surface, spread out by the given roughness according to the roughness model.
+    /// Rays spread to below the surface are absorbed.
+    fn scatter_with_roughness(&self, ray_in: &Ray, hit_rec: &HitRecord, roughness: f32, attenuation: &mut Color, scattered: &mut Ray, rng: &mut dyn RngCore) -> bool {
+        let unit_dir: Vector3 = match ray_in.direction.try_unit_vec() {
+            Some(unit_dir) => unit_dir,
+            None => return false,
+        };
+        // Seen from the ray the surface faces against it, also when hit from inside
+        let normal: Vector3 = if unit_dir.dot(hit_rec.normal) > 0.0 { -hit_rec.normal } else { hit_rec.normal };
+        // Roughness may spread the ray below the surface, but the mirror direction has to be above it
+        debug_assert!(scatter_is_valid(ray_in, unit_dir.reflect(normal), Some(normal)), "Metal reflected a ray into the surface");
+        let direction: Vector3 = match self.model {
+            RoughnessModel::Fuzz => unit_dir.reflect(normal) + Vector3::random_in_unit_with(rng) * roughness,
+            RoughnessModel::Ggx => unit_dir.reflect(sample_ggx(normal, roughness * roughness, rng)),
+        };
+        debug_assert!(scatter_is_valid(ray_in, direction, None), "Metal scattered a ray in an invalid direction");
+        *attenuation = self.albedo;
+        *scattered = Ray::new(hit_rec.p, direction);
+        direction.dot(normal) > 0.0
+    }
+}
+
+impl Material for Metal {
+    /// ## scatter
+    /// Reflects the ray in the surface, spread out according to the roughness model.
+    /// Rays spread to below the surface are absorbed.
+    fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray, rng: &mut dyn RngCore) -> bool {
+        self.scatter_with_roughness(ray_in, hit_rec, self.fuzz, attenuation, scattered, rng)
+    }
+
+    /// ## is_specular
+    /// Metal reflects around the mirror direction
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    /// ## scatter_regularized
+    /// Reflects the ray like scatter, with the roughness raised to at least min_roughness
+    fn scatter_regularized(&self, ray_in: &Ray, hit_rec: &HitRecord, min_roughness: f32, attenuation: &mut Color, scattered: &mut Ray, rng: &mut dyn RngCore) -> bool {
+        self.scatter_with_roughness(ray_in, hit_rec, self.fuzz.max(min_roughness.min(1.0)), attenuation, scattered, rng)
+    }
+
+    /// ## hash_content
+    /// Adds the albedo and roughness to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("Metal");
+        hasher.write_vector(self.albedo);
+        hasher.write_f32(self.fuzz);
+        hasher.write_str(match self.model {
+            RoughnessModel::Fuzz => "Fuzz",
+            RoughnessModel::Ggx => "Ggx",
+        });
+    }
+}
+
+/// ## sample_ggx
+/// Returns a microfacet normal around the unit normal n sampled from the GGX distribution
+/// with the width alpha. An alpha of zero always gives n.
+fn sample_ggx(n: Vector3, alpha: f32, rng: &mut dyn RngCore) -> Vector3 {
+    let r1: f32 = rng.gen_range(0.0..1.0);
+    let r2: f32 = rng.gen_range(0.0..1.0);
+    let phi: f32 = 2.0 * std::f32::consts::PI * r1;
+    let cos_theta: f32 = ((1.0 - r2) / (1.0 + (alpha*alpha - 1.0) * r2)).sqrt();
+    let sin_theta: f32 = (1.0 - cos_theta*cos_theta).max(0.0).sqrt();
+    let (u, v) = orthonormal_basis(n);
+    u * (sin_theta * phi.cos()) + v * (sin_theta * phi.sin()) + n * cos_theta
+}
+
+/// ## scatter_is_valid
+/// Checks that a scattered direction is finite and, if a normal is given, not below the surface.
+/// Prints the incoming ray and the direction if it isn't. Used in debug assertions in scatter,
+/// so release builds don't pay for the checks.
+fn scatter_is_valid(ray_in: &Ray, direction: Vector3, normal: Option<Vector3>) -> bool {
+    let finite: bool = direction.x.is_finite() && direction.y.is_finite() && direction.z.is_finite();
+    let above: bool = normal.is_none_or(|n| direction.dot(n) >= 0.0);
+    if !(finite && above) {
+        eprintln!("Invalid scattered direction {:?} for ray {:?}", direction, ray_in);
+    }
+    finite && above
+}
+
+/// ## refract
+/// Returns the direction the unit vector uv continues in after passing through a surface with
+/// the unit normal n, where ratio is the refraction index it leaves divided by the one it enters
+fn refract(uv: Vector3, n: Vector3, ratio: f32) -> Vector3 {
+    let cos_theta: f32 = (-uv).dot(n).min(1.0);
+    let perpendicular: Vector3 = (uv + n * cos_theta) * ratio;
+    let parallel: Vector3 = n * -(1.0 - perpendicular.dot(perpendicular)).abs().sqrt();
+    perpendicular + parallel
+}
+
+/// ## reflectance
+/// Returns Schlick's approximation of how much light is reflected at the given angle
+fn reflectance(cosine: f32, ratio: f32) -> f32 {
+    let r0: f32 = ((1.0 - ratio) / (1.0 + ratio)).powf(2.0);
+    r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
+}
+
+/// ## FreeMirror
+/// A mirror that wrongly claims its bounces are free, so only the bounce limit can stop its paths.
+/// Used by tests of the bounce limit.
+#[cfg(test)]
+#[derive(Debug)]
+pub(crate) struct FreeMirror(pub Metal);
+
+#[cfg(test)]
+impl Material for FreeMirror {
+    fn scatter(&self, ray_in: &Ray, hit_rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray, rng: &mut dyn RngCore) -> bool {
+        self.0.scatter(ray_in, hit_rec, attenuation, scattered, rng)
+    }
+    fn hash_content(&self, hasher: &mut crate::content_hash::ContentHasher) {
+        self.0.hash_content(hasher);
+    }
+    fn counts_towards_depth(&self) -> bool {
+        false
+    }
+}
+
+/// Tests for materials
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    /// Returns the direction a ray refracted through the material goes in, ignoring reflections
+    fn refracted_direction(material: &Dielectric, ray: &Ray, hit_rec: &HitRecord) -> Vector3 {
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+        loop {
+            assert!(material.scatter(ray, hit_rec, &mut attenuation, &mut scattered, &mut rand::thread_rng()));
+            if scattered.direction.dot(hit_rec.normal) < 0.0 {
+                return scattered.direction;
+            }
+        }
+    }
+
+    /// Returns a HitRecord for a hit by ray on a surface facing up at the origin
+    fn upward_hit(ray: &Ray) -> HitRecord<'static> {
+        let mut hit_rec = HitRecord::new();
+        hit_rec.set_face_normal(ray, Vector3::new(0.0, 1.0, 0.0));
+        hit_rec
+    }
+
+    #[test]
+    fn lambertian_scatter() {
+        let material = Lambertian::new(Color::new(0.1, 0.2, 0.3));
+        let ray = Ray::new(Vector3::new(0.0, 1.0, 1.0), Vector3::new(0.0, -1.0, -1.0));
+        let hit_rec = upward_hit(&ray);
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+
+        for _ in 0..100 {
+            assert!(material.scatter(&ray, &hit_rec, &mut attenuation, &mut scattered, &mut rand::thread_rng()));
+            assert_eq!(attenuation, Color::new(0.1, 0.2, 0.3));
+            assert!(scattered.direction.dot(hit_rec.normal) >= 0.0);
+        }
+    }
+
+    #[test]
+    fn lambertian_scatter_texture() {
+        use crate::texture::CheckerTexture;
+
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let material = Lambertian::with_texture(Box::new(CheckerTexture::new(black, white, 1.0)));
+        let ray = Ray::new(Vector3::new(1.0, 2.0, 1.0), Vector3::new(0.0, -1.0, 0.0));
+        let mut hit_rec = upward_hit(&ray);
+        let mut attenuation = Color::new(0.5, 0.5, 0.5);
+        let mut scattered = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+
+        hit_rec.p = Vector3::new(1.0, 1.0, 1.0);
+        assert!(material.scatter(&ray, &hit_rec, &mut attenuation, &mut scattered, &mut rand::thread_rng()));
+        assert_eq!(attenuation, white);
+        hit_rec.p = Vector3::new(-1.0, 1.0, 1.0);
+        assert!(material.scatter(&ray, &hit_rec, &mut attenuation, &mut scattered, &mut rand::thread_rng()));
+        assert_eq!(attenuation, black);
+    }
+
+    #[test]
+    fn lambertian_scatter_cosine_law() {
+        let material = Lambertian::new(Color::new(0.5, 0.5, 0.5));
+        let ray = Ray::new(Vector3::new(0.0, 1.0, 1.0), Vector3::new(0.0, -1.0, -1.0));
+        let hit_rec = upward_hit(&ray);
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+
+        // Directions spread with the cosine law have an average cosine to the normal of 2/3
+        let samples: usize = 20000;
+        let mut sum: f32 = 0.0;
+        let mut rng = StdRng::seed_from_u64(499);
+        for _ in 0..samples {
+            assert!(material.scatter(&ray, &hit_rec, &mut attenuation, &mut scattered, &mut rng));
+            sum += scattered.direction.unit_vec().dot(hit_rec.normal);
+        }
+        assert!((sum / samples as f32 - 2.0 / 3.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn diffuse_light_emits_without_scattering() {
+        let light = DiffuseLight::new(Color::new(2.0, 1.0, 0.5));
+        let ray = Ray::new(Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit_rec = upward_hit(&ray);
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+
+        assert!(!light.scatter(&ray, &hit_rec, &mut attenuation, &mut scattered, &mut rand::thread_rng()));
+        assert_eq!(light.emitted(), Color::new(2.0, 1.0, 0.5));
+        assert_eq!(DEFAULT_MATERIAL.emitted(), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn dielectric_refracts_towards_normal() {
+        let material = Dielectric::new(1.5);
+        let ray = Ray::new(Vector3::new(-1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        let hit_rec = upward_hit(&ray);
+        let direction = refracted_direction(&material, &ray, &hit_rec).unit_vec();
+
+        // sin of the refracted angle is sin(45 degrees) / 1.5
+        let expected_sin = (0.5_f32).sqrt() / 1.5;
+        assert!((direction.x - expected_sin).abs() < 1e-5);
+    }
+
+    #[test]
+    fn dielectric_total_internal_reflection() {
+        let material = Dielectric::new(1.5);
+        // Leaving the glass at a grazing angle can't refract
+        let ray = Ray::new(Vector3::new(-1.0, -0.2, 0.0), Vector3::new(1.0, 0.2, 0.0));
+        let hit_rec = upward_hit(&ray);
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+
+        for _ in 0..100 {
+            assert!(material.scatter(&ray, &hit_rec, &mut attenuation, &mut scattered, &mut rand::thread_rng()));
+            assert!(scattered.direction.y < 0.0);
+        }
+    }
+
+    #[test]
+    fn dielectric_dispersion_separates_wavelengths() {
+        let material = Dielectric::with_dispersion(1.5, 0.05);
+        let hit_rec = upward_hit(&Ray::new(Vector3::new(-1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0)));
+        let mut blue = Ray::new(Vector3::new(-1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        blue.wavelength = Some(450.0);
+        let mut red = Ray::new(Vector3::new(-1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        red.wavelength = Some(650.0);
+
+        let blue_dir = refracted_direction(&material, &blue, &hit_rec).unit_vec();
+        let red_dir = refracted_direction(&material, &red, &hit_rec).unit_vec();
+
+        // Blue light has the higher index and is bent more towards the normal
+        assert!(material.refraction_index_at(Some(450.0)) > material.refraction_index_at(Some(650.0)));
+        assert!(blue_dir.x < red_dir.x - 0.01);
+    }
+
+    #[test]
+    fn dielectric_dispersion_without_wavelength() {
+        let dispersive = Dielectric::with_dispersion(1.5, 0.05);
+        let plain = Dielectric::new(1.5);
+        let ray = Ray::new(Vector3::new(-1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        let hit_rec = upward_hit(&ray);
+
+        assert_eq!(dispersive.refraction_index_at(None), 1.5);
+        assert_eq!(refracted_direction(&dispersive, &ray, &hit_rec), refracted_direction(&plain, &ray, &hit_rec));
+    }
+
+    /// Returns the average angle in radians between the scattered rays of a metal hit at 45 degrees and the mirror direction
+    fn metal_spread(material: &Metal, rng: &mut StdRng) -> f32 {
+        let ray = Ray::new(Vector3::new(-1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        let hit_rec = upward_hit(&ray);
+        let mirror = Vector3::new(1.0, 1.0, 0.0).unit_vec();
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+        let mut sum: f32 = 0.0;
+        let mut count: usize = 0;
+        for _ in 0..5000 {
+            if material.scatter(&ray, &hit_rec, &mut attenuation, &mut scattered, rng) {
+                sum += scattered.direction.unit_vec().dot(mirror).clamp(-1.0, 1.0).acos();
+                count += 1;
+            }
+        }
+        sum / count as f32
+    }
+
+    #[test]
+    fn metal_zero_roughness_is_mirror() {
+        let ray = Ray::new(Vector3::new(-1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        let hit_rec = upward_hit(&ray);
+        let mirror = Vector3::new(1.0, 1.0, 0.0).unit_vec();
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+
+        for model in [RoughnessModel::Fuzz, RoughnessModel::Ggx] {
+            let material = Metal::with_model(Color::new(0.8, 0.6, 0.2), 0.0, model);
+            for _ in 0..100 {
+                assert!(material.scatter(&ray, &hit_rec, &mut attenuation, &mut scattered, &mut rand::thread_rng()));
+                assert!((scattered.direction.unit_vec() - mirror).normal() < 1e-5);
+                assert_eq!(attenuation, Color::new(0.8, 0.6, 0.2));
+            }
+        }
+    }
+
+    #[test]
+    fn metal_large_fuzz_stays_above_surface() {
+        // A grazing ray, so a lot of the fuzzed reflections end up below the surface
+        let ray = Ray::new(Vector3::new(-1.0, 0.2, 0.0), Vector3::new(1.0, -0.2, 0.0));
+        let hit_rec = upward_hit(&ray);
+        let material = Metal::new(Color::new(1.0, 1.0, 1.0), 5.0);
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+        let mut absorbed = 0;
+        let mut rng = StdRng::seed_from_u64(503);
+
+        assert_eq!(material.fuzz(), 1.0);
+        for _ in 0..1000 {
+            if material.scatter(&ray, &hit_rec, &mut attenuation, &mut scattered, &mut rng) {
+                assert!(scattered.direction.dot(hit_rec.normal) > 0.0);
+            } else {
+                absorbed += 1;
+            }
+        }
+        assert!(absorbed > 0);
+    }
+
+    #[test]
+    fn metal_ggx_roughness_widens() {
+        let mut rng = StdRng::seed_from_u64(474);
+        let smooth = metal_spread(&Metal::with_model(Color::new(1.0, 1.0, 1.0), 0.1, RoughnessModel::Ggx), &mut rng);
+        let medium = metal_spread(&Metal::with_model(Color::new(1.0, 1.0, 1.0), 0.4, RoughnessModel::Ggx), &mut rng);
+        let rough = metal_spread(&Metal::with_model(Color::new(1.0, 1.0, 1.0), 0.8, RoughnessModel::Ggx), &mut rng);
+
+        assert!(smooth < medium);
+        assert!(medium < rough);
+    }
+
+    #[test]
+    fn metal_default_model_is_fuzz() {
+        let material = Metal::new(Color::new(1.0, 1.0, 1.0), 3.0);
+
+        assert_eq!(material.model, RoughnessModel::Fuzz);
+        assert_eq!(material.fuzz(), 1.0);
+    }
+
+    #[test]
+    fn metal_set_fuzz_clamps() {
+        let mut material = Metal::new(Color::new(1.0, 1.0, 1.0), 0.2);
+        material.set_fuzz(0.5);
+        assert_eq!(material.fuzz(), 0.5);
+        material.set_fuzz(4.0);
+        assert_eq!(material.fuzz(), 1.0);
+    }
+
+    #[test]
+    fn scatter_is_valid_catches_bad_directions() {
+        let ray = Ray::new(Vector3::new(-1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        let up = Vector3::new(0.0, 1.0, 0.0);
+
+        assert!(scatter_is_valid(&ray, ray.direction.reflect(up), Some(up)));
+        // Reflecting with the sign of the normal term flipped sends the ray into the surface
+        assert!(!scatter_is_valid(&ray, ray.direction + up * ray.direction.dot(up) * 2.0, Some(up)));
+        assert!(!scatter_is_valid(&ray, Vector3::new(f32::NAN, 1.0, 0.0), None));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "Metal")]
+    fn metal_scatter_bad_reflection_panics() {
+        let material = Metal::new(Color::new(1.0, 1.0, 1.0), 0.0);
+        let ray = Ray::new(Vector3::new(-1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        let mut hit_rec = upward_hit(&ray);
+        hit_rec.normal = Vector3::new(f32::NAN, 1.0, 0.0);
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+
+        material.scatter(&ray, &hit_rec, &mut attenuation, &mut scattered, &mut rand::thread_rng());
+    }
+}
@@ -0,0 +1,174 @@
+use super::*;
+use super::materials::Isotropic;
+use crate::vector::Color;
+
+/// ## ConstantMedium
+/// Fog or smoke of the same density everywhere inside a closed boundary object.
+/// Rays going through it scatter at random points, the denser the sooner, and the medium
+/// scatters them in any direction with the isotropic phase function of the given color.
+/// Where a ray scatters is picked with the random number of the ray, which the render draws from
+/// its seeded generator, so a seeded render with fog is the same every time.
+pub struct ConstantMedium {
+    pub boundary: Box<dyn Hitable>,
+    density: f32,
+    pub phase_function: Isotropic,
+}
+
+impl ConstantMedium {
+    /// ## new
+    /// Returns a medium of the given density filling boundary, scattering light of phase_color,
+    /// or an error if the density isn't above zero, which would scatter rays before they enter the medium.
+    pub fn new(boundary: Box<dyn Hitable>, density: f32, phase_color: Color) -> Result<ConstantMedium, String> {
+        if density.is_nan() || density <= 0.0 {
+            return Err(format!("Density of a medium must be above zero, got {}", density));
+        }
+        Ok(ConstantMedium {
+            boundary,
+            density,
+            phase_function: Isotropic::new(phase_color),
+        })
+    }
+
+    /// ## density
+    /// Returns how dense the medium is, which is always above zero
+    pub fn density(&self) -> f32 {
+        self.density
+    }
+}
+
+impl Hitable for ConstantMedium {
+    /// ## hit_record
+    /// Finds where the ray enters and leaves the boundary and picks a random distance to scatter at,
+    /// -ln(random) / density, with the random number of the ray.
+    /// It's a hit if that is before the ray leaves the medium.
+    /// A ray starting inside the boundary enters it where it starts, and only the part of the ray
+    /// between t_min and t_max counts. The hit has no real surface, so its normal is arbitrary.
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        let enter: f32 = self.boundary.hit_record(ray, f32::NEG_INFINITY, f32::INFINITY)?.t;
+        let leave: f32 = self.boundary.hit_record(ray, enter + 1e-4, f32::INFINITY)?.t;
+        let t_enter: f32 = enter.max(t_min).max(0.0);
+        let t_leave: f32 = leave.min(t_max);
+        if t_enter >= t_leave {
+            return None;
+        }
+
+        let ray_length: f32 = ray.direction.normal();
+        let distance_inside: f32 = (t_leave - t_enter) * ray_length;
+        // 1 - random is in (0, 1], so the logarithm is never infinite
+        let hit_distance: f32 = -(1.0 - ray.random).ln() / self.density;
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let mut hit_rec: HitRecord = HitRecord::new();
+        hit_rec.t = t_enter + hit_distance / ray_length;
+        hit_rec.p = ray.point_at(hit_rec.t);
+        hit_rec.normal = Vector3::new(1.0, 0.0, 0.0);
+        hit_rec.front_face = true;
+        hit_rec.material = Some(&self.phase_function);
+        Some(hit_rec)
+    }
+
+    /// ## bounding_box
+    /// Returns the box of the boundary
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+
+    /// ## hash_content
+    /// Adds the density, phase function and boundary to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("ConstantMedium");
+        hasher.write_f32(self.density);
+        self.phase_function.hash_content(hasher);
+        self.boundary.hash_content(hasher);
+    }
+}
+
+/// Tests for ConstantMedium struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    /// 1000 rays from the origin straight down -z with seeded random numbers
+    fn rays() -> impl Iterator<Item = Ray> {
+        let mut rng = StdRng::seed_from_u64(540);
+        (0..1000).map(move |_| {
+            let mut ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+            ray.random = rng.gen_range(0.0..1.0);
+            ray
+        })
+    }
+
+    /// Returns a medium of the given density in a unit sphere around center
+    fn fog(center: Vector3, density: f32, color: Color) -> ConstantMedium {
+        ConstantMedium::new(Box::new(Sphere::new(center, 1.0)), density, color).unwrap()
+    }
+
+    /// Number of 1000 rays straight through a medium in a unit sphere that scatter in it
+    fn scatter_count(density: f32) -> usize {
+        let fog = fog(Vector3::new(0.0, 0.0, -3.0), density, Color::new(1.0, 1.0, 1.0));
+        rays().filter(|ray| fog.hit_record(ray, 0.001, f32::MAX).is_some()).count()
+    }
+
+    #[test]
+    fn constant_medium_density() {
+        assert!(scatter_count(1000.0) >= 999);
+        assert!(scatter_count(1e-4) <= 5);
+        let medium = scatter_count(0.5);
+        // A path of 2 through density 0.5 scatters with probability 1 - 1/e
+        assert!((550..=720).contains(&medium), "{}", medium);
+    }
+
+    #[test]
+    fn constant_medium_hit_inside_span() {
+        let fog = fog(Vector3::new(0.0, 0.0, -3.0), 0.5, Color::new(0.2, 0.4, 0.6));
+        for ray in rays() {
+            let ray = Ray::new(ray.origin, 2.0 * ray.direction);
+            if let Some(hit_rec) = fog.hit_record(&ray, 0.001, f32::MAX) {
+                assert!((1.0..=2.0).contains(&hit_rec.t));
+                assert_eq!(hit_rec.p, ray.point_at(hit_rec.t));
+                assert!(hit_rec.material.is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn constant_medium_ray_inside() {
+        let fog = fog(Vector3::new(0.0, 0.0, 0.0), 1000.0, Color::new(1.0, 1.0, 1.0));
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        // Dense fog around the ray's origin scatters it right away, never behind it
+        for ray in rays() {
+            let hit_rec = fog.hit_record(&ray, 0.001, f32::MAX).unwrap();
+            assert!(0.001 <= hit_rec.t && hit_rec.t < 0.1);
+        }
+        // Nothing scatters when the part of the ray between t_min and t_max has no length or is outside the fog
+        assert!(fog.hit_record(&ray, 0.001, 0.001).is_none());
+        assert!(fog.hit_record(&ray, 2.0, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn constant_medium_random_picks_distance() {
+        let fog = fog(Vector3::new(0.0, 0.0, -3.0), 0.5, Color::new(1.0, 1.0, 1.0));
+        let mut ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        // The same random number scatters at the same point, and a larger one further in
+        ray.random = 0.3;
+        let near = fog.hit_record(&ray, 0.001, f32::MAX).unwrap().t;
+        assert_eq!(fog.hit_record(&ray, 0.001, f32::MAX).unwrap().t, near);
+        ray.random = 0.5;
+        assert!(fog.hit_record(&ray, 0.001, f32::MAX).unwrap().t > near);
+        assert!((near - (2.0 - 0.7f32.ln() / 0.5)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn constant_medium_invalid_density() {
+        for density in [0.0, -0.5, f32::NAN] {
+            let medium = ConstantMedium::new(Box::new(Sphere::new(Vector3::new(0.0, 0.0, -3.0), 1.0)), density, Color::new(1.0, 1.0, 1.0));
+            assert!(medium.is_err_and(|error| error.contains("Density")));
+        }
+        assert_eq!(fog(Vector3::new(0.0, 0.0, 0.0), 0.25, Color::new(1.0, 1.0, 1.0)).density(), 0.25);
+    }
+}
@@ -0,0 +1,235 @@
+use super::*;
+use super::objects::Triangle;
+use super::bvh::BvhNode;
+
+use std::fs;
+use std::io;
+
+/// ## Mesh
+/// A representation of a triangle mesh.
+/// The triangles are put in a bounding volume hierarchy when the mesh is created,
+/// so a ray only has to be tested against the triangles near it.
+pub struct Mesh {
+    bvh: Option<BvhNode>,
+    triangle_count: usize,
+}
+
+impl Mesh {
+    /// ## new
+    /// Returns a Mesh made up of the given triangles
+    pub fn new(triangles: Vec<Triangle>) -> Mesh {
+        let triangle_count: usize = triangles.len();
+        let bvh: Option<BvhNode> = if triangles.is_empty() {
+            None
+        } else {
+            let objects: Vec<Box<dyn Hitable>> = triangles.into_iter()
+                .map(|triangle| Box::new(triangle) as Box<dyn Hitable>)
+                .collect();
+            Some(BvhNode::new(objects))
+        };
+        Mesh {
+            bvh,
+            triangle_count,
+        }
+    }
+
+    /// ## with_backface_culling
+    /// Returns a Mesh made up of the given triangles where rays hitting the back of a triangle
+    /// pass through it if cull_backfaces is true. Only suitable for closed meshes seen from outside.
+    pub fn with_backface_culling(triangles: Vec<Triangle>, cull_backfaces: bool) -> Mesh {
+        Mesh::new(triangles.into_iter()
+            .map(|triangle| Triangle { cull_backfaces, ..triangle })
+            .collect())
+    }
+
+    /// ## load_obj
+    /// Returns a Mesh with the triangles of the Wavefront OBJ file at path
+    pub fn load_obj(path: &str) -> io::Result<Mesh> {
+        Mesh::from_obj_str(&fs::read_to_string(path)?)
+    }
+
+    /// ## from_obj_str
+    /// Returns a Mesh with the triangles described by a string in the Wavefront OBJ format.
+    /// Only vertices (v) and faces (f) are read, faces with more than three corners are split into triangles.
+    pub fn from_obj_str(obj: &str) -> io::Result<Mesh> {
+        let mut vertices: Vec<Vector3> = Vec::new();
+        let mut triangles: Vec<Triangle> = Vec::new();
+
+        for (number, line) in obj.lines().enumerate() {
+            let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Line {}: {}", number + 1, message));
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("v") => {
+                    let coordinates: Vec<f32> = words.take(3)
+                        .map(|word| word.parse::<f32>().map_err(|_| invalid("Invalid vertex coordinate")))
+                        .collect::<io::Result<Vec<f32>>>()?;
+                    if coordinates.len() != 3 {
+                        return Err(invalid("Vertex needs three coordinates"));
+                    }
+                    vertices.push(Vector3::new(coordinates[0], coordinates[1], coordinates[2]));
+                }
+                Some("f") => {
+                    // Indices start at 1, negative indices count backwards from the latest vertex
+                    let corners: Vec<Vector3> = words
+                        .map(|word| {
+                            let index: i64 = word.split('/').next().unwrap_or("")
+                                .parse().map_err(|_| invalid("Invalid face index"))?;
+                            let resolved: i64 = if index < 0 { vertices.len() as i64 + index } else { index - 1 };
+                            usize::try_from(resolved).ok()
+                                .and_then(|i| vertices.get(i).copied())
+                                .ok_or_else(|| invalid("Face index out of range"))
+                        })
+                        .collect::<io::Result<Vec<Vector3>>>()?;
+                    if corners.len() < 3 {
+                        return Err(invalid("Face needs at least three corners"));
+                    }
+                    for i in 1..corners.len() - 1 {
+                        triangles.push(Triangle::new(corners[0], corners[i], corners[i + 1]));
+                    }
+                }
+                _ => (), // Comments, normals, texture coordinates and so on are ignored
+            }
+        }
+        Ok(Mesh::new(triangles))
+    }
+
+    /// ## triangle_count
+    /// Returns the number of triangles in the mesh
+    pub fn triangle_count(&self) -> usize {
+        self.triangle_count
+    }
+}
+
+impl Hitable for Mesh {
+    /// ## hit_record
+    /// Checks whether a given Ray hits any triangle of the mesh.
+    /// If it hits return information regarding the closest hit.
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        self.bvh.as_ref().and_then(|bvh| bvh.hit_record(ray, t_min, t_max))
+    }
+
+    /// ## bounding_box
+    /// Returns the box surrounding all triangles, None for an empty mesh
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.as_ref().and_then(|bvh| bvh.bounding_box())
+    }
+
+    /// ## hash_content
+    /// Adds the triangles to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("Mesh");
+        hasher.write_u64(self.triangle_count as u64);
+        if let Some(bvh) = &self.bvh {
+            bvh.hash_content(hasher);
+        }
+    }
+
+    /// ## tessellate
+    /// Returns the triangles of the mesh
+    fn tessellate(&self, subdivisions: usize) -> Option<Vec<Triangle>> {
+        match &self.bvh {
+            Some(bvh) => bvh.tessellate(subdivisions),
+            None => Some(Vec::new()),
+        }
+    }
+}
+
+/// Tests for Mesh struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::scene::Scene;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    /// Returns a grid of triangles over a bumpy surface
+    fn bumpy_grid(size: usize) -> Vec<Triangle> {
+        let height = |x: usize, z: usize| ((x as f32 * 0.7).sin() + (z as f32 * 0.3).cos()) * 0.5;
+        let point = |x: usize, z: usize| Vector3::new(x as f32 - size as f32 / 2.0, height(x, z), -(z as f32));
+        let mut triangles = Vec::new();
+        for x in 0..size {
+            for z in 0..size {
+                triangles.push(Triangle::new(point(x, z), point(x + 1, z), point(x, z + 1)));
+                triangles.push(Triangle::new(point(x + 1, z), point(x + 1, z + 1), point(x, z + 1)));
+            }
+        }
+        triangles
+    }
+
+    #[test]
+    fn mesh_matches_linear_scene() {
+        let triangles = bumpy_grid(20);
+        let mesh = Mesh::new(triangles.clone());
+        let scene = Scene::from_objects(triangles.into_iter().map(|t| Box::new(t) as Box<dyn Hitable>).collect());
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut hits: usize = 0;
+        for _ in 0..2000 {
+            let origin = Vector3::new(rng.gen_range(-12.0..12.0), rng.gen_range(2.0..6.0), rng.gen_range(-22.0..2.0));
+            let direction = Vector3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..-0.1), rng.gen_range(-1.0..1.0));
+            let ray = Ray::new(origin, direction);
+
+            let mut mesh_rec = HitRecord::new();
+            let mut scene_rec = HitRecord::new();
+            let mesh_hit = mesh.hit(&ray, 0.001, f32::MAX, &mut mesh_rec);
+            let scene_hit = scene.hit(&ray, 0.001, f32::MAX, &mut scene_rec);
+
+            assert_eq!(mesh_hit, scene_hit);
+            if mesh_hit {
+                assert_eq!(mesh_rec.t, scene_rec.t);
+                assert_eq!(mesh_rec.p, scene_rec.p);
+                hits += 1;
+            }
+        }
+        assert!(hits > 100);
+    }
+
+    #[test]
+    fn mesh_builds_large() {
+        let mesh = Mesh::new(bumpy_grid(40));
+
+        assert_eq!(mesh.triangle_count(), 3200);
+        assert!(mesh.bounding_box().is_some());
+    }
+
+    #[test]
+    fn mesh_from_obj_str() {
+        let obj = "# A unit square\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n";
+        let mesh = Mesh::from_obj_str(obj).unwrap();
+        let ray = Ray::new(Vector3::new(0.25, 0.75, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let mut hit_rec = HitRecord::new();
+
+        assert_eq!(mesh.triangle_count(), 2);
+        assert!(mesh.hit(&ray, 0.001, f32::MAX, &mut hit_rec));
+        assert_eq!(hit_rec.p, Vector3::new(0.25, 0.75, 0.0));
+    }
+
+    #[test]
+    fn mesh_from_obj_str_invalid_index() {
+        let obj = "v 0 0 0\nv 1 0 0\nf 1 2 3\n";
+
+        assert!(Mesh::from_obj_str(obj).is_err());
+    }
+
+    #[test]
+    fn mesh_empty() {
+        let mesh = Mesh::new(vec![]);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert!(!mesh.hit(&ray, 0.001, f32::MAX, &mut HitRecord::new()));
+        assert_eq!(mesh.bounding_box(), None);
+    }
+
+    #[test]
+    fn mesh_backface_culling() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n";
+        let triangles: Vec<Triangle> = vec![
+            Triangle::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 0.0)),
+            Triangle::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 0.0), Vector3::new(0.0, 1.0, 0.0)),
+        ];
+        let back = Ray::new(Vector3::new(0.25, 0.75, -1.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(Mesh::from_obj_str(obj).unwrap().hit(&back, 0.001, f32::MAX, &mut HitRecord::new()));
+        assert!(Mesh::with_backface_culling(triangles.clone(), false).hit(&back, 0.001, f32::MAX, &mut HitRecord::new()));
+        assert!(!Mesh::with_backface_culling(triangles, true).hit(&back, 0.001, f32::MAX, &mut HitRecord::new()));
+    }
+}
@@ -1,27 +1,151 @@
 use crate::ray::Ray;
 use crate::vector::Vector3;
+use crate::content_hash::ContentHasher;
 
 pub mod objects;
-use objects::Sphere;
+use objects::{Sphere, Triangle};
 pub mod scene;
+pub mod aabb;
+use aabb::Aabb;
+pub mod bvh;
+pub mod mesh;
+pub mod instanced;
+pub mod transform;
+pub mod medium;
+pub mod scene_json;
+pub mod materials;
+use materials::Material;
 
-#[derive(Clone, Copy)]
-pub struct HitRecord {
+#[derive(Debug, Clone, Copy)]
+pub struct HitRecord<'a> {
     pub t: f32,
     pub p: Vector3,
+    /// Normal of the surface at p, always pointing against the ray
     pub normal: Vector3,
+    /// Whether the ray hit the outside of the surface, the side the outward normal points to
+    pub front_face: bool,
+    /// Surface coordinates of p between 0 and 1, where textures are looked up
+    pub u: f32,
+    pub v: f32,
+    /// Material of the surface that was hit, None if the object has no material of its own
+    pub material: Option<&'a dyn Material>,
 }
 
-impl HitRecord {
-    pub fn new() -> HitRecord {
+impl Default for HitRecord<'_> {
+    /// ## default
+    /// Returns a HitRecord at t 0 with every vector zeroed and no material
+    fn default() -> Self {
         HitRecord {
             t: 0.0,
             p: Vector3::new(0.0, 0.0, 0.0),
             normal: Vector3::new(0.0, 0.0, 0.0),
+            front_face: true,
+            u: 0.0,
+            v: 0.0,
+            material: None,
         }
     }
 }
 
-pub trait Hitable {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, hit_rec: &mut HitRecord) -> bool;
+impl<'a> HitRecord<'a> {
+    /// ## new
+    /// Returns the same as default
+    pub fn new() -> HitRecord<'a> {
+        HitRecord::default()
+    }
+
+    /// ## set_face_normal
+    /// Stores which side of the surface the ray hit, and the normal flipped to point against the ray
+    pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: Vector3) {
+        self.front_face = ray.direction.dot(outward_normal) <= 0.0;
+        self.normal = if self.front_face { outward_normal } else { -outward_normal };
+    }
+}
+
+pub trait Hitable: Send + Sync {
+    /// Returns information about where the ray hits the object with t between t_min and t_max,
+    /// the closest hit if there are several. None if the ray misses.
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>>;
+    /// Checks whether the ray hits the object like hit_record, storing the hit in hit_rec if it does
+    fn hit<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32, hit_rec: &mut HitRecord<'a>) -> bool {
+        match self.hit_record(ray, t_min, t_max) {
+            Some(record) => {
+                *hit_rec = record;
+                true
+            }
+            None => false,
+        }
+    }
+    /// Returns a box enclosing the object, or None if the object is unbounded
+    fn bounding_box(&self) -> Option<Aabb>;
+    /// Adds the type and parameters of the object to a hash describing the scene
+    fn hash_content(&self, hasher: &mut ContentHasher);
+    /// Returns triangles approximating the surface of the object, where curved surfaces are split up
+    /// more finely the higher subdivisions is. None if the object can't be made into triangles.
+    fn tessellate(&self, _subdivisions: usize) -> Option<Vec<Triangle>> {
+        None
+    }
+}
+
+/// Tests for Hitable trait
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A plane at z = 0 that only implements hit_record
+    struct ZPlane;
+    impl Hitable for ZPlane {
+        fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+            let t: f32 = -ray.origin.z / ray.direction.z;
+            (t_min < t && t < t_max).then(|| {
+                let mut hit_rec: HitRecord = HitRecord::new();
+                hit_rec.t = t;
+                hit_rec.p = ray.point_at(t);
+                hit_rec.set_face_normal(ray, Vector3::new(0.0, 0.0, 1.0));
+                hit_rec
+            })
+        }
+        fn bounding_box(&self) -> Option<Aabb> {
+            None
+        }
+        fn hash_content(&self, hasher: &mut ContentHasher) {
+            hasher.write_str("ZPlane");
+        }
+    }
+
+    #[test]
+    fn hit_record_default() {
+        let hit_rec = HitRecord::default();
+
+        assert_eq!(hit_rec.t, 0.0);
+        assert_eq!(hit_rec.p, Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(hit_rec.normal, Vector3::new(0.0, 0.0, 0.0));
+        assert!(hit_rec.material.is_none());
+        assert_eq!(HitRecord::new().t, hit_rec.t);
+    }
+
+    #[test]
+    fn hit_record_set_face_normal() {
+        let outward = Vector3::new(0.0, 1.0, 0.0);
+        let mut hit_rec = HitRecord::new();
+
+        hit_rec.set_face_normal(&Ray::new(Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0)), outward);
+        assert!(hit_rec.front_face);
+        assert_eq!(hit_rec.normal, outward);
+
+        hit_rec.set_face_normal(&Ray::new(Vector3::new(0.0, -1.0, 0.0), Vector3::new(0.0, 1.0, 0.0)), outward);
+        assert!(!hit_rec.front_face);
+        assert_eq!(hit_rec.normal, Vector3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn hitable_hit_from_hit_record() {
+        let ray = Ray::new(Vector3::new(1.0, 2.0, 3.0), Vector3::new(0.0, 0.0, -1.0));
+        let mut hit_rec = HitRecord::new();
+
+        assert!(ZPlane.hit(&ray, 0.001, f32::MAX, &mut hit_rec));
+        assert_eq!(hit_rec.t, 3.0);
+        assert_eq!(hit_rec.p, Vector3::new(1.0, 2.0, 0.0));
+        assert!(!ZPlane.hit(&ray, 0.001, 2.0, &mut HitRecord::new()));
+    }
 }
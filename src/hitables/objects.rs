@@ -1,51 +1,1257 @@
 use super::*;
+use super::materials::{Material, Lambertian, DEFAULT_ALBEDO};
+use super::aabb::surrounding_box;
+use rand::Rng;
+use std::f32::consts::PI;
 
 /// ## Sphere
 /// A representation of a Sphere with a center in a position given by a Vector3 and a radius given as a f32.
+/// The material decides how light scatters off the sphere.
 #[derive(Debug)]
 pub struct Sphere {
     pub center: Vector3,
     pub radius: f32,
+    pub material: Box<dyn Material>,
 }
 
 impl Sphere {
     /// ## new
-    /// Return a Sphere where it's center and radius is given
+    /// Return a Sphere where it's center and radius is given, made of the standard gray diffuse material
     pub fn new(center: Vector3, radius: f32) -> Sphere {
+        Sphere::with_material(center, radius, Box::new(Lambertian::new(DEFAULT_ALBEDO)))
+    }
+
+    /// ## with_material
+    /// Return a Sphere where it's center, radius and material is given
+    pub fn with_material(center: Vector3, radius: f32, material: Box<dyn Material>) -> Sphere {
         Sphere {
-            center: center,
-            radius: radius,
+            center,
+            radius,
+            material,
         }
     }
-}
 
-impl Hitable for Sphere {
-    /// ## hit
-    /// Checks wheter a given Ray hits the sphere.
-    /// If it hits store information regarding that in the HitRecord.
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, hit_rec: &mut HitRecord) -> bool {
+    /// ## hit_both
+    /// Returns where the Ray enters and leaves the sphere as the pair of t values (entry, exit).
+    /// Only values between t_min and t_max are used, if just one of them is in range, like for a ray
+    /// starting inside the sphere, both values of the pair are that one. A ray just touching the
+    /// sphere counts as a miss, like in hit. None if the ray doesn't hit the sphere in range.
+    pub fn hit_both(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
         let oc: Vector3 = ray.origin - self.center;
-        let a: f32 = ray.direction.dot(ray.direction);
+        let a: f32 = ray.direction.length_squared();
         let b: f32 = oc.dot(ray.direction);
-        let c: f32 = oc.dot(oc) - self.radius*self.radius;
+        let c: f32 = oc.length_squared() - self.radius*self.radius;
         let discriminant: f32 = b*b - a*c;
+        if discriminant <= 0.0 {
+            return None;
+        }
+
+        let in_range = |t: f32| t_min < t && t < t_max;
+        let entry: f32 = (-b - discriminant.sqrt()) / a;
+        let exit: f32 = (-b + discriminant.sqrt()) / a;
+        match (in_range(entry), in_range(exit)) {
+            (true, true) => Some((entry, exit)),
+            (true, false) => Some((entry, entry)),
+            (false, true) => Some((exit, exit)),
+            (false, false) => None,
+        }
+    }
+
+    /// ## pdf_value
+    /// Returns the probability density of sampling the given direction from origin
+    /// when directions are picked uniformly from the cone the sphere subtends.
+    /// Directions outside the cone have density zero. The cone is tested directly instead of
+    /// hitting the sphere, since rays grazing its edge may miss it by rounding.
+    pub fn pdf_value(&self, origin: Vector3, direction: Vector3) -> f32 {
+        let oc: Vector3 = self.center - origin;
+        let distance_squared: f32 = oc.length_squared();
+        if distance_squared <= self.radius*self.radius {
+            return 1.0 / (4.0 * PI); // Origin inside the sphere sees it in every direction
+        }
+        let cos_theta_max: f32 = (1.0 - self.radius*self.radius / distance_squared).sqrt();
+        if direction.unit_vec().dot(oc.unit_vec()) < cos_theta_max {
+            return 0.0;
+        }
+        let solid_angle: f32 = 2.0 * PI * (1.0 - cos_theta_max);
+        1.0 / solid_angle
+    }
+
+    /// ## random
+    /// Returns a random unit direction from origin towards the sphere.
+    /// Directions are uniformly distributed over the solid angle subtended by the sphere.
+    pub fn random(&self, origin: Vector3) -> Vector3 {
+        self.random_with(origin, &mut rand::thread_rng())
+    }
+
+    /// ## random_with
+    /// Returns the same as random with the random numbers taken from rng
+    pub fn random_with<R: Rng + ?Sized>(&self, origin: Vector3, rng: &mut R) -> Vector3 {
+        let oc: Vector3 = self.center - origin;
+        let distance_squared: f32 = oc.length_squared();
+        let cos_theta_max: f32 = if distance_squared <= self.radius*self.radius {
+            -1.0 // Origin inside the sphere, sample the whole sphere of directions
+        } else {
+            (1.0 - self.radius*self.radius / distance_squared).sqrt()
+        };
+
+        let r1: f32 = rng.gen_range(0.0..1.0);
+        let r2: f32 = rng.gen_range(0.0..1.0);
+        let z: f32 = 1.0 + r2 * (cos_theta_max - 1.0);
+        let phi: f32 = 2.0 * PI * r1;
+        let sin_theta: f32 = (1.0 - z*z).max(0.0).sqrt();
+
+        // From the center every direction is as good as any other to build the basis around
+        let w: Vector3 = oc.try_unit_vec().unwrap_or(Vector3::new(0.0, 0.0, 1.0));
+        let (u, v) = orthonormal_basis(w);
+        u * (phi.cos() * sin_theta) + v * (phi.sin() * sin_theta) + w * z
+    }
+}
+
+/// ## sphere_uv
+/// Returns the surface coordinates (u, v) of a point p on the unit sphere around the origin.
+/// u goes around the y axis from x = -1 and v from the bottom at y = -1 to the top at y = 1.
+pub fn sphere_uv(p: Vector3) -> (f32, f32) {
+    let theta: f32 = (-p.y).clamp(-1.0, 1.0).acos();
+    let phi: f32 = (-p.z).atan2(p.x) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
+/// ## hit_sphere
+/// Checks whether a given Ray hits the sphere with the given center and radius between t_min and t_max.
+/// If it hits return information regarding the closest hit, with the given material.
+/// Shared by all kinds of spheres so they find the same hits. A ray just touching the sphere counts as a miss.
+pub fn hit_sphere<'a>(center: Vector3, radius: f32, material: &'a dyn Material, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+    let oc: Vector3 = ray.origin - center;
+    let a: f32 = ray.direction.length_squared();
+    let b: f32 = oc.dot(ray.direction);
+    let c: f32 = oc.length_squared() - radius*radius;
+    let discriminant: f32 = b*b - a*c;
+    if discriminant <= 0.0 {
+        return None;
+    }
+
+    let t: f32 = [(-b - discriminant.sqrt()) / a, (-b + discriminant.sqrt()) / a]
+        .into_iter()
+        .find(|t| t_min < *t && *t < t_max)?;
+    let mut hit_rec: HitRecord = HitRecord::new();
+    hit_rec.t = t;
+    hit_rec.p = ray.point_at(t);
+    let outward_normal: Vector3 = (hit_rec.p - center) / radius;
+    hit_rec.set_face_normal(ray, outward_normal);
+    (hit_rec.u, hit_rec.v) = sphere_uv(outward_normal);
+    hit_rec.material = Some(material);
+    Some(hit_rec)
+}
+
+/// ## orthonormal_basis
+/// Returns two unit vectors u and v which together with the unit vector w form an orthonormal basis
+pub fn orthonormal_basis(w: Vector3) -> (Vector3, Vector3) {
+    let a: Vector3 = if w.x.abs() > 0.9 {
+        Vector3::new(0.0, 1.0, 0.0)
+    } else {
+        Vector3::new(1.0, 0.0, 0.0)
+    };
+    let v: Vector3 = w.cross(a).unit_vec();
+    let u: Vector3 = w.cross(v);
+    (u, v)
+}
+
+impl Hitable for Sphere {
+    /// ## hit_record
+    /// Checks wheter a given Ray hits the sphere.
+    /// If it hits return information regarding the closest hit.
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        hit_sphere(self.center, self.radius, self.material.as_ref(), ray, t_min, t_max)
+    }
+
+    /// ## bounding_box
+    /// Returns the box from center - radius to center + radius on every axis
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r: Vector3 = Vector3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
+
+    /// ## hash_content
+    /// Adds the center, radius and material to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("Sphere");
+        hasher.write_vector(self.center);
+        hasher.write_f32(self.radius);
+        self.material.hash_content(hasher);
+    }
+
+    /// ## tessellate
+    /// Returns a UV sphere with subdivisions rings from pole to pole and twice as many segments around,
+    /// 4 * subdivisions * (subdivisions - 1) triangles facing out. At least two rings are used.
+    fn tessellate(&self, subdivisions: usize) -> Option<Vec<Triangle>> {
+        let stacks: usize = subdivisions.max(2);
+        let slices: usize = 2 * stacks;
+        let point = |stack: usize, slice: usize| {
+            let theta: f32 = PI * stack as f32 / stacks as f32;
+            let phi: f32 = 2.0 * PI * slice as f32 / slices as f32;
+            self.center + Vector3::new(theta.sin() * phi.cos(), theta.cos(), -theta.sin() * phi.sin()) * self.radius
+        };
+        let mut triangles: Vec<Triangle> = Vec::with_capacity(2 * slices * (stacks - 1));
+        for stack in 0..stacks {
+            for slice in 0..slices {
+                let (top_left, top_right) = (point(stack, slice), point(stack, slice + 1));
+                let (bottom_left, bottom_right) = (point(stack + 1, slice), point(stack + 1, slice + 1));
+                // The rings at the poles shrink to a point, so they only need one triangle per segment
+                if stack != 0 {
+                    triangles.push(Triangle::new(top_left, bottom_left, top_right));
+                }
+                if stack != stacks - 1 {
+                    triangles.push(Triangle::new(top_right, bottom_left, bottom_right));
+                }
+            }
+        }
+        Some(triangles)
+    }
+}
+
+/// ## MovingSphere
+/// A sphere moving in a straight line from center0 at time0 to center1 at time1.
+/// Where a ray hits it depends on the time of the ray.
+#[derive(Debug)]
+pub struct MovingSphere {
+    pub center0: Vector3,
+    pub center1: Vector3,
+    pub time0: f32,
+    pub time1: f32,
+    pub radius: f32,
+    pub material: Box<dyn Material>,
+}
+
+impl MovingSphere {
+    /// ## new
+    /// Returns a MovingSphere going from center0 at time0 to center1 at time1, made of the standard gray diffuse material
+    pub fn new(center0: Vector3, center1: Vector3, time0: f32, time1: f32, radius: f32) -> MovingSphere {
+        MovingSphere::with_material(center0, center1, time0, time1, radius, Box::new(Lambertian::new(DEFAULT_ALBEDO)))
+    }
+
+    /// ## with_material
+    /// Returns a MovingSphere going from center0 at time0 to center1 at time1 with the given material
+    pub fn with_material(center0: Vector3, center1: Vector3, time0: f32, time1: f32, radius: f32, material: Box<dyn Material>) -> MovingSphere {
+        MovingSphere {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material,
+        }
+    }
+
+    /// ## center
+    /// Returns the center at the given time. The sphere keeps moving the same way before time0 and after time1,
+    /// and stays at center0 if time0 and time1 are the same.
+    pub fn center(&self, time: f32) -> Vector3 {
+        if self.time1 == self.time0 {
+            return self.center0;
+        }
+        self.center0.lerp(self.center1, (time - self.time0) / (self.time1 - self.time0))
+    }
+}
+
+impl Hitable for MovingSphere {
+    /// ## hit_record
+    /// Checks whether a given Ray hits the sphere where it is at the time of the ray.
+    /// If it hits return information regarding the closest hit.
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        hit_sphere(self.center(ray.time), self.radius, self.material.as_ref(), ray, t_min, t_max)
+    }
+
+    /// ## bounding_box
+    /// Returns the box surrounding the sphere at both time0 and time1, which holds it in between
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r: Vector3 = Vector3::new(self.radius, self.radius, self.radius);
+        Some(surrounding_box(
+            Aabb::new(self.center0 - r, self.center0 + r),
+            Aabb::new(self.center1 - r, self.center1 + r)))
+    }
+
+    /// ## hash_content
+    /// Adds the centers, times, radius and material to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("MovingSphere");
+        hasher.write_vector(self.center0);
+        hasher.write_vector(self.center1);
+        hasher.write_f32(self.time0);
+        hasher.write_f32(self.time1);
+        hasher.write_f32(self.radius);
+        self.material.hash_content(hasher);
+    }
+}
+
+/// ## Triangle
+/// A representation of a Triangle with the corners a, b and c.
+/// The front of the triangle is the side from which the corners are in counter clockwise order.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Triangle {
+    pub a: Vector3,
+    pub b: Vector3,
+    pub c: Vector3,
+    /// Let rays hitting the back of the triangle pass through, which saves work inside closed meshes
+    pub cull_backfaces: bool,
+}
+
+impl Triangle {
+    /// ## new
+    /// Returns a two sided Triangle with the corners given as arguments
+    pub fn new(a: Vector3, b: Vector3, c: Vector3) -> Triangle {
+        Triangle {
+            a,
+            b,
+            c,
+            cull_backfaces: false,
+        }
+    }
+}
+
+impl Hitable for Triangle {
+    /// ## hit_record
+    /// Checks whether a given Ray hits the triangle using the Möller–Trumbore algorithm.
+    /// If it hits return information regarding the hit.
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        let edge1: Vector3 = self.b - self.a;
+        let edge2: Vector3 = self.c - self.a;
+        let pvec: Vector3 = ray.direction.cross(edge2);
+        let det: f32 = edge1.dot(pvec);
+        if det.abs() < 1e-8 {
+            return None; // Ray is parallel to the triangle or the triangle is degenerate
+        }
+        if self.cull_backfaces && det < 0.0 {
+            return None; // Ray goes the same way as the normal, so it hits the back
+        }
+        let inv_det: f32 = 1.0 / det;
+
+        let tvec: Vector3 = ray.origin - self.a;
+        let u: f32 = tvec.dot(pvec) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let qvec: Vector3 = tvec.cross(edge1);
+        let v: f32 = ray.direction.dot(qvec) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t: f32 = edge2.dot(qvec) * inv_det;
+        if t_min < t && t < t_max {
+            let mut hit_rec: HitRecord = HitRecord::new();
+            hit_rec.t = t;
+            hit_rec.p = ray.point_at(t);
+            hit_rec.set_face_normal(ray, edge1.cross(edge2).try_unit_vec()?);
+            return Some(hit_rec);
+        }
+        None
+    }
+
+    /// ## tessellate
+    /// Returns the triangle itself
+    fn tessellate(&self, _subdivisions: usize) -> Option<Vec<Triangle>> {
+        Some(vec![*self])
+    }
+
+    /// ## bounding_box
+    /// Returns the box enclosing the corners.
+    /// The box is padded slightly so it has a thickness even if the triangle is axis aligned.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let padding: Vector3 = Vector3::new(1e-4, 1e-4, 1e-4);
+        let min: Vector3 = Vector3::new(self.a.x.min(self.b.x).min(self.c.x),
+                                        self.a.y.min(self.b.y).min(self.c.y),
+                                        self.a.z.min(self.b.z).min(self.c.z));
+        let max: Vector3 = Vector3::new(self.a.x.max(self.b.x).max(self.c.x),
+                                        self.a.y.max(self.b.y).max(self.c.y),
+                                        self.a.z.max(self.b.z).max(self.c.z));
+        Some(Aabb::new(min - padding, max + padding))
+    }
 
-        if discriminant > 0.0 {
-            let mut temp: f32 = (-b - discriminant.sqrt()) / a;
-            if t_min < temp && temp < t_max {
-                hit_rec.t = temp;
-                hit_rec.p = ray.point_at(temp);
-                hit_rec.normal = (hit_rec.p - self.center) / self.radius;
-                return true;
+    /// ## hash_content
+    /// Adds the corners to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("Triangle");
+        hasher.write_vector(self.a);
+        hasher.write_vector(self.b);
+        hasher.write_vector(self.c);
+        hasher.write_u64(self.cull_backfaces as u64);
+    }
+}
+
+/// ## Plane
+/// A representation of an infinite Plane through point, facing the way of the unit vector normal.
+/// The material decides how light scatters off the plane.
+#[derive(Debug)]
+pub struct Plane {
+    pub point: Vector3,
+    pub normal: Vector3,
+    pub material: Box<dyn Material>,
+}
+
+impl Plane {
+    /// ## new
+    /// Returns a Plane through point with the given normal, made of the standard gray diffuse material
+    pub fn new(point: Vector3, normal: Vector3) -> Plane {
+        Plane::with_material(point, normal, Box::new(Lambertian::new(DEFAULT_ALBEDO)))
+    }
+
+    /// ## with_material
+    /// Returns a Plane through point with the given normal and material. The normal doesn't have to be
+    /// a unit vector, but panics if it is zero.
+    pub fn with_material(point: Vector3, normal: Vector3, material: Box<dyn Material>) -> Plane {
+        Plane {
+            point,
+            normal: normal.unit_vec(),
+            material,
+        }
+    }
+}
+
+impl Hitable for Plane {
+    /// ## hit_record
+    /// Checks whether a given Ray hits the plane.
+    /// A ray parallel to the plane misses it, also when it lies in the plane.
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        let denominator: f32 = ray.direction.dot(self.normal);
+        if denominator.abs() < 1e-8 {
+            return None;
+        }
+        let t: f32 = (self.point - ray.origin).dot(self.normal) / denominator;
+        if t_min < t && t < t_max {
+            let mut hit_rec: HitRecord = HitRecord::new();
+            hit_rec.t = t;
+            hit_rec.p = ray.point_at(t);
+            hit_rec.set_face_normal(ray, self.normal);
+            hit_rec.material = Some(self.material.as_ref());
+            return Some(hit_rec);
+        }
+        None
+    }
+
+    /// ## bounding_box
+    /// Returns None since the plane is unbounded
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+
+    /// ## hash_content
+    /// Adds the point, normal and material to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("Plane");
+        hasher.write_vector(self.point);
+        hasher.write_vector(self.normal);
+        self.material.hash_content(hasher);
+    }
+}
+
+/// ## AABox
+/// A representation of a solid box with its sides along the axes, spanning from the corner min to the corner max.
+/// The material decides how light scatters off the box.
+#[derive(Debug)]
+pub struct AABox {
+    pub min: Vector3,
+    pub max: Vector3,
+    pub material: Box<dyn Material>,
+}
+
+impl AABox {
+    /// ## new
+    /// Returns an AABox between the corners min and max, made of the standard gray diffuse material
+    pub fn new(min: Vector3, max: Vector3) -> AABox {
+        AABox::with_material(min, max, Box::new(Lambertian::new(DEFAULT_ALBEDO)))
+    }
+
+    /// ## with_material
+    /// Returns an AABox between the corners min and max made of the given material
+    pub fn with_material(min: Vector3, max: Vector3, material: Box<dyn Material>) -> AABox {
+        AABox {
+            min,
+            max,
+            material,
+        }
+    }
+
+    /// ## corner
+    /// Returns the corner where bit 0, 1 and 2 of index pick the max instead of the min for x, y and z
+    fn corner(&self, index: usize) -> Vector3 {
+        let mut corner: Vector3 = self.min;
+        for axis in 0..3 {
+            if index & (1 << axis) != 0 {
+                corner[axis] = self.max[axis];
             }
-            temp = (-b + discriminant.sqrt()) / a;
-            if t_min < temp && temp < t_max {
-                hit_rec.t = temp;
-                hit_rec.p = ray.point_at(temp);
-                hit_rec.normal = (hit_rec.p - self.center) / self.radius;
-                return true;
+        }
+        corner
+    }
+}
+
+impl Hitable for AABox {
+    /// ## hit_record
+    /// Checks whether a given Ray hits the box using the slab method, like Aabb::hit.
+    /// The face hit is the one of the axis where the ray entered, or left for a ray starting inside the box.
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        let (mut t_enter, mut enter_axis): (f32, usize) = (f32::NEG_INFINITY, 0);
+        let (mut t_exit, mut exit_axis): (f32, usize) = (f32::INFINITY, 0);
+        for axis in 0..3 {
+            let inv_d: f32 = 1.0 / ray.direction[axis];
+            let mut t0: f32 = (self.min[axis] - ray.origin[axis]) * inv_d;
+            let mut t1: f32 = (self.max[axis] - ray.origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // A ray parallel to the slab gives infinite values, or NaN on a slab plane, which the comparisons skip
+            if t0 > t_enter {
+                (t_enter, enter_axis) = (t0, axis);
             }
+            if t1 < t_exit {
+                (t_exit, exit_axis) = (t1, axis);
+            }
+        }
+        if t_exit <= t_enter {
+            return None;
+        }
+
+        let (t, axis, sign): (f32, usize, f32) = if t_min < t_enter && t_enter < t_max {
+            (t_enter, enter_axis, -ray.direction[enter_axis].signum())
+        } else if t_min < t_exit && t_exit < t_max {
+            (t_exit, exit_axis, ray.direction[exit_axis].signum())
+        } else {
+            return None;
+        };
+        let mut outward_normal: Vector3 = Vector3::new(0.0, 0.0, 0.0);
+        outward_normal[axis] = sign;
+
+        let mut hit_rec: HitRecord = HitRecord::new();
+        hit_rec.t = t;
+        hit_rec.p = ray.point_at(t);
+        hit_rec.set_face_normal(ray, outward_normal);
+        hit_rec.material = Some(self.material.as_ref());
+        Some(hit_rec)
+    }
+
+    /// ## bounding_box
+    /// Returns the box itself
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
+    }
+
+    /// ## hash_content
+    /// Adds the corners and material to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("AABox");
+        hasher.write_vector(self.min);
+        hasher.write_vector(self.max);
+        self.material.hash_content(hasher);
+    }
+
+    /// ## tessellate
+    /// Returns the six faces of the box as two triangles each, facing out
+    fn tessellate(&self, _subdivisions: usize) -> Option<Vec<Triangle>> {
+        // Corners of each face in counter clockwise order seen from outside, see corner
+        const FACES: [[usize; 4]; 6] = [[0, 4, 6, 2], [1, 3, 7, 5], [0, 1, 5, 4], [2, 6, 7, 3], [0, 2, 3, 1], [4, 5, 7, 6]];
+        let mut triangles: Vec<Triangle> = Vec::with_capacity(12);
+        for [a, b, c, d] in FACES {
+            triangles.push(Triangle::new(self.corner(a), self.corner(b), self.corner(c)));
+            triangles.push(Triangle::new(self.corner(a), self.corner(c), self.corner(d)));
         }
-        false
+        Some(triangles)
+    }
+}
+
+/// ## XyRect
+/// A flat rectangle in the plane z = k, spanning from x0 to x1 and y0 to y1, with its outward normal along +z.
+/// The material decides how light scatters off it, which makes it handy for walls and area lights.
+#[derive(Debug)]
+pub struct XyRect {
+    pub x0: f32,
+    pub x1: f32,
+    pub y0: f32,
+    pub y1: f32,
+    pub k: f32,
+    pub material: Box<dyn Material>,
+}
+
+impl XyRect {
+    /// ## new
+    /// Returns a XyRect in the plane z = k, made of the standard gray diffuse material
+    pub fn new(x0: f32, x1: f32, y0: f32, y1: f32, k: f32) -> XyRect {
+        XyRect::with_material(x0, x1, y0, y1, k, Box::new(Lambertian::new(DEFAULT_ALBEDO)))
+    }
+
+    /// ## with_material
+    /// Returns a XyRect in the plane z = k made of the given material
+    pub fn with_material(x0: f32, x1: f32, y0: f32, y1: f32, k: f32, material: Box<dyn Material>) -> XyRect {
+        XyRect {
+            x0,
+            x1,
+            y0,
+            y1,
+            k,
+            material,
+        }
+    }
+}
+
+impl Hitable for XyRect {
+    /// ## hit_record
+    /// Checks whether a given Ray hits the rectangle, see rect_hit
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        rect_hit(ray, t_min, t_max, [0, 1, 2], [self.x0, self.x1, self.y0, self.y1], self.k, self.material.as_ref())
+    }
+
+    /// ## bounding_box
+    /// Returns the rectangle padded a little along z, see rect_bounding_box
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(rect_bounding_box([0, 1, 2], [self.x0, self.x1, self.y0, self.y1], self.k))
+    }
+
+    /// ## hash_content
+    /// Adds the bounds, plane offset and material to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("XyRect");
+        for value in [self.x0, self.x1, self.y0, self.y1, self.k] {
+            hasher.write_f32(value);
+        }
+        self.material.hash_content(hasher);
+    }
+
+    /// ## tessellate
+    /// Returns the rectangle as two triangles facing +z
+    fn tessellate(&self, _subdivisions: usize) -> Option<Vec<Triangle>> {
+        Some(rect_triangles([0, 1, 2], [self.x0, self.x1, self.y0, self.y1], self.k))
+    }
+}
+
+/// ## XzRect
+/// A flat rectangle in the plane y = k, spanning from x0 to x1 and z0 to z1, with its outward normal along +y.
+/// Works like XyRect, only in another plane.
+#[derive(Debug)]
+pub struct XzRect {
+    pub x0: f32,
+    pub x1: f32,
+    pub z0: f32,
+    pub z1: f32,
+    pub k: f32,
+    pub material: Box<dyn Material>,
+}
+
+impl XzRect {
+    /// ## new
+    /// Returns a XzRect in the plane y = k, made of the standard gray diffuse material
+    pub fn new(x0: f32, x1: f32, z0: f32, z1: f32, k: f32) -> XzRect {
+        XzRect::with_material(x0, x1, z0, z1, k, Box::new(Lambertian::new(DEFAULT_ALBEDO)))
+    }
+
+    /// ## with_material
+    /// Returns a XzRect in the plane y = k made of the given material
+    pub fn with_material(x0: f32, x1: f32, z0: f32, z1: f32, k: f32, material: Box<dyn Material>) -> XzRect {
+        XzRect {
+            x0,
+            x1,
+            z0,
+            z1,
+            k,
+            material,
+        }
+    }
+}
+
+impl Hitable for XzRect {
+    /// ## hit_record
+    /// Checks whether a given Ray hits the rectangle, see rect_hit
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        rect_hit(ray, t_min, t_max, [0, 2, 1], [self.x0, self.x1, self.z0, self.z1], self.k, self.material.as_ref())
+    }
+
+    /// ## bounding_box
+    /// Returns the rectangle padded a little along y, see rect_bounding_box
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(rect_bounding_box([0, 2, 1], [self.x0, self.x1, self.z0, self.z1], self.k))
     }
-}
\ No newline at end of file
+
+    /// ## hash_content
+    /// Adds the bounds, plane offset and material to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("XzRect");
+        for value in [self.x0, self.x1, self.z0, self.z1, self.k] {
+            hasher.write_f32(value);
+        }
+        self.material.hash_content(hasher);
+    }
+
+    /// ## tessellate
+    /// Returns the rectangle as two triangles facing +y
+    fn tessellate(&self, _subdivisions: usize) -> Option<Vec<Triangle>> {
+        Some(rect_triangles([0, 2, 1], [self.x0, self.x1, self.z0, self.z1], self.k))
+    }
+}
+
+/// ## YzRect
+/// A flat rectangle in the plane x = k, spanning from y0 to y1 and z0 to z1, with its outward normal along +x.
+/// Works like XyRect, only in another plane.
+#[derive(Debug)]
+pub struct YzRect {
+    pub y0: f32,
+    pub y1: f32,
+    pub z0: f32,
+    pub z1: f32,
+    pub k: f32,
+    pub material: Box<dyn Material>,
+}
+
+impl YzRect {
+    /// ## new
+    /// Returns a YzRect in the plane x = k, made of the standard gray diffuse material
+    pub fn new(y0: f32, y1: f32, z0: f32, z1: f32, k: f32) -> YzRect {
+        YzRect::with_material(y0, y1, z0, z1, k, Box::new(Lambertian::new(DEFAULT_ALBEDO)))
+    }
+
+    /// ## with_material
+    /// Returns a YzRect in the plane x = k made of the given material
+    pub fn with_material(y0: f32, y1: f32, z0: f32, z1: f32, k: f32, material: Box<dyn Material>) -> YzRect {
+        YzRect {
+            y0,
+            y1,
+            z0,
+            z1,
+            k,
+            material,
+        }
+    }
+}
+
+impl Hitable for YzRect {
+    /// ## hit_record
+    /// Checks whether a given Ray hits the rectangle, see rect_hit
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        rect_hit(ray, t_min, t_max, [1, 2, 0], [self.y0, self.y1, self.z0, self.z1], self.k, self.material.as_ref())
+    }
+
+    /// ## bounding_box
+    /// Returns the rectangle padded a little along x, see rect_bounding_box
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(rect_bounding_box([1, 2, 0], [self.y0, self.y1, self.z0, self.z1], self.k))
+    }
+
+    /// ## hash_content
+    /// Adds the bounds, plane offset and material to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("YzRect");
+        for value in [self.y0, self.y1, self.z0, self.z1, self.k] {
+            hasher.write_f32(value);
+        }
+        self.material.hash_content(hasher);
+    }
+
+    /// ## tessellate
+    /// Returns the rectangle as two triangles facing +x
+    fn tessellate(&self, _subdivisions: usize) -> Option<Vec<Triangle>> {
+        Some(rect_triangles([1, 2, 0], [self.y0, self.y1, self.z0, self.z1], self.k))
+    }
+}
+
+/// ## RECT_THICKNESS
+/// Half the thickness given to the bounding box of a rectangle along its normal, so the box isn't flat
+const RECT_THICKNESS: f32 = 1e-4;
+
+/// ## rect_point
+/// Returns the point with the values a, b and k along the axes [a axis, b axis, normal axis]
+fn rect_point(axes: [usize; 3], a: f32, b: f32, k: f32) -> Vector3 {
+    let mut point: Vector3 = Vector3::new(0.0, 0.0, 0.0);
+    point[axes[0]] = a;
+    point[axes[1]] = b;
+    point[axes[2]] = k;
+    point
+}
+
+/// ## rect_hit
+/// Checks whether a given Ray hits the rectangle [a0, a1, b0, b1] in the plane where the normal axis is k,
+/// with axes given as [a axis, b axis, normal axis]. Solves for where the ray crosses the plane and checks
+/// that the point is inside the bounds. Rays parallel to the plane miss. The surface coordinates go from
+/// 0 at a0 and b0 to 1 at a1 and b1.
+fn rect_hit<'a>(ray: &Ray, t_min: f32, t_max: f32, axes: [usize; 3], [a0, a1, b0, b1]: [f32; 4], k: f32, material: &'a dyn Material) -> Option<HitRecord<'a>> {
+    let [a_axis, b_axis, normal_axis] = axes;
+    if ray.direction[normal_axis].abs() < 1e-8 {
+        return None;
+    }
+    let t: f32 = (k - ray.origin[normal_axis]) / ray.direction[normal_axis];
+    if t <= t_min || t >= t_max {
+        return None;
+    }
+    let p: Vector3 = ray.point_at(t);
+    let (a, b): (f32, f32) = (p[a_axis], p[b_axis]);
+    if a < a0 || a > a1 || b < b0 || b > b1 {
+        return None;
+    }
+
+    let mut hit_rec: HitRecord = HitRecord::new();
+    hit_rec.t = t;
+    hit_rec.p = p;
+    hit_rec.p[normal_axis] = k;
+    hit_rec.u = (a - a0) / (a1 - a0);
+    hit_rec.v = (b - b0) / (b1 - b0);
+    hit_rec.set_face_normal(ray, rect_point(axes, 0.0, 0.0, 1.0));
+    hit_rec.material = Some(material);
+    Some(hit_rec)
+}
+
+/// ## rect_bounding_box
+/// Returns the box around a rectangle, RECT_THICKNESS to each side of it along the normal axis
+fn rect_bounding_box(axes: [usize; 3], [a0, a1, b0, b1]: [f32; 4], k: f32) -> Aabb {
+    Aabb::new(rect_point(axes, a0, b0, k - RECT_THICKNESS), rect_point(axes, a1, b1, k + RECT_THICKNESS))
+}
+
+/// ## rect_triangles
+/// Returns a rectangle as two triangles in counter clockwise order seen from the side the normal points to
+fn rect_triangles(axes: [usize; 3], [a0, a1, b0, b1]: [f32; 4], k: f32) -> Vec<Triangle> {
+    let mut corners: [Vector3; 4] = [
+        rect_point(axes, a0, b0, k), rect_point(axes, a1, b0, k),
+        rect_point(axes, a1, b1, k), rect_point(axes, a0, b1, k)];
+    if (corners[1] - corners[0]).cross(corners[2] - corners[0])[axes[2]] < 0.0 {
+        corners.reverse();
+    }
+    vec![
+        Triangle::new(corners[0], corners[1], corners[2]),
+        Triangle::new(corners[0], corners[2], corners[3]),
+    ]
+}
+
+/// Tests for hitable objects
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn sphere_pdf_value_integrates_to_one() {
+        let sphere = Sphere::new(Vector3::new(0.0, 0.0, -3.0), 1.0);
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+        let samples: usize = 200_000;
+        let mut rng = StdRng::seed_from_u64(459);
+
+        let mut sum: f32 = 0.0;
+        for _ in 0..samples {
+            let direction = Vector3::random_unit_vector_with(&mut rng);
+            sum += sphere.pdf_value(origin, direction);
+        }
+        // Uniform directions over the unit sphere have density 1/(4*PI)
+        let integral = sum / samples as f32 * 4.0 * PI;
+
+        assert!((integral - 1.0).abs() < 0.05, "integral was {}", integral);
+    }
+
+    #[test]
+    fn sphere_pdf_value_miss() {
+        let sphere = Sphere::new(Vector3::new(0.0, 0.0, -3.0), 1.0);
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+
+        assert_eq!(0.0, sphere.pdf_value(origin, Vector3::new(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn sphere_random_within_cone() {
+        let sphere = Sphere::new(Vector3::new(1.0, 2.0, -4.0), 1.5);
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+        let axis = (sphere.center - origin).unit_vec();
+        let distance = (sphere.center - origin).normal();
+        let cos_theta_max = (1.0 - sphere.radius*sphere.radius / (distance*distance)).sqrt();
+
+        let mut rng = StdRng::seed_from_u64(459);
+
+        for _ in 0..10_000 {
+            let direction = sphere.random_with(origin, &mut rng);
+            assert!((direction.normal() - 1.0).abs() < 1e-4);
+            // Rounding the direction moves it less than 1e-6 along the axis
+            assert!(direction.dot(axis) >= cos_theta_max - 1e-6);
+            assert!(sphere.pdf_value(origin, direction) > 0.0);
+        }
+    }
+
+    #[test]
+    fn sphere_random_from_center() {
+        let sphere = Sphere::new(Vector3::new(1.0, 2.0, -4.0), 1.5);
+        let mut rng = StdRng::seed_from_u64(459);
+
+        // The center is inside the sphere, so directions cover the whole sphere of directions
+        let directions: Vec<Vector3> = (0..1000).map(|_| sphere.random_with(sphere.center, &mut rng)).collect();
+        assert!(directions.iter().all(|direction| (direction.normal() - 1.0).abs() < 1e-4));
+        assert!(directions.iter().any(|direction| direction.z > 0.5) && directions.iter().any(|direction| direction.z < -0.5));
+        assert_eq!(sphere.pdf_value(sphere.center, directions[0]), 1.0 / (4.0 * PI));
+    }
+
+    #[test]
+    fn sphere_bounding_box() {
+        let sphere = Sphere::new(Vector3::new(0.0, 0.0, 0.0), 1.0);
+
+        assert_eq!(sphere.bounding_box(), Some(Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0))));
+    }
+
+    #[test]
+    fn plane_bounding_box() {
+        let plane = Plane::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let scene = scene::Scene::from_objects(vec![
+            Box::new(Sphere::new(Vector3::new(0.0, 0.0, 0.0), 1.0)),
+            Box::new(plane),
+        ]);
+
+        assert_eq!(scene.objects()[1].bounding_box(), None);
+        assert_eq!(scene.bounding_box(), None);
+    }
+
+    #[test]
+    fn sphere_tessellate() {
+        let sphere = Sphere::new(Vector3::new(1.0, -2.0, 3.0), 2.0);
+        let triangles = sphere.tessellate(6).unwrap();
+
+        assert_eq!(triangles.len(), 4 * 6 * 5);
+        for triangle in triangles {
+            for corner in [triangle.a, triangle.b, triangle.c] {
+                assert!(((corner - sphere.center).normal() - 2.0).abs() < 1e-5);
+            }
+            let facing = (triangle.b - triangle.a).cross(triangle.c - triangle.a);
+            let centroid = (triangle.a + triangle.b + triangle.c) / 3.0;
+            assert!(facing.dot(centroid - sphere.center) > 0.0);
+        }
+    }
+
+    /// Returns the triangle from (-1, -1, -2) to (1, -1, -2) to (0, 1, -2), facing towards +z
+    fn facing_triangle() -> Triangle {
+        Triangle::new(Vector3::new(-1.0, -1.0, -2.0), Vector3::new(1.0, -1.0, -2.0), Vector3::new(0.0, 1.0, -2.0))
+    }
+
+    #[test]
+    fn triangle_hit_center() {
+        let triangle = facing_triangle();
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit_rec = triangle.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        assert_eq!(hit_rec.t, 2.0);
+        assert_eq!(hit_rec.p, Vector3::new(0.0, 0.0, -2.0));
+        assert_eq!(hit_rec.normal, Vector3::new(0.0, 0.0, 1.0));
+        assert!(hit_rec.front_face);
+        assert!(triangle.hit_record(&ray, 0.001, 1.9).is_none());
+        assert!(triangle.hit_record(&ray, 2.1, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn triangle_miss_outside_edges() {
+        let triangle = facing_triangle();
+        let just_inside = Ray::new(Vector3::new(0.0, -0.999, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let below = Ray::new(Vector3::new(0.0, -1.001, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let beside = Ray::new(Vector3::new(0.51, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let above = Ray::new(Vector3::new(0.0, 1.001, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert!(triangle.hit_record(&just_inside, 0.001, f32::MAX).is_some());
+        assert!(triangle.hit_record(&below, 0.001, f32::MAX).is_none());
+        assert!(triangle.hit_record(&beside, 0.001, f32::MAX).is_none());
+        assert!(triangle.hit_record(&above, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn triangle_parallel_ray() {
+        let triangle = facing_triangle();
+        let ray = Ray::new(Vector3::new(-3.0, 0.0, -2.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert!(triangle.hit_record(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn triangle_hit_back_face() {
+        let triangle = facing_triangle();
+        let ray = Ray::new(Vector3::new(0.0, 0.0, -4.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit_rec = triangle.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        assert_eq!(hit_rec.t, 2.0);
+        assert!(!hit_rec.front_face);
+        assert_eq!(hit_rec.normal, Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn triangle_cull_backfaces() {
+        let two_sided = Triangle::new(Vector3::new(-1.0, -1.0, 0.0), Vector3::new(1.0, -1.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let culled = Triangle { cull_backfaces: true, ..two_sided };
+        let front = Ray::new(Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let back = Ray::new(Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(two_sided.hit(&front, 0.001, f32::MAX, &mut HitRecord::new()));
+        assert!(two_sided.hit(&back, 0.001, f32::MAX, &mut HitRecord::new()));
+        assert!(culled.hit(&front, 0.001, f32::MAX, &mut HitRecord::new()));
+        assert!(!culled.hit(&back, 0.001, f32::MAX, &mut HitRecord::new()));
+    }
+
+    #[test]
+    fn sphere_hit_both_through() {
+        let sphere = Sphere::new(Vector3::new(0.0, 0.0, -3.0), 1.0);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert_eq!(sphere.hit_both(&ray, 0.001, f32::MAX), Some((2.0, 4.0)));
+        assert_eq!(sphere.hit_both(&ray, 0.001, 3.0), Some((2.0, 2.0)));
+        assert_eq!(sphere.hit_both(&ray, 0.001, 1.0), None);
+    }
+
+    #[test]
+    fn sphere_hit_both_tangent() {
+        let sphere = Sphere::new(Vector3::new(0.0, 0.0, -3.0), 1.0);
+        let ray = Ray::new(Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert_eq!(sphere.hit_both(&ray, 0.001, f32::MAX), None);
+    }
+
+    #[test]
+    fn sphere_hit_both_inside() {
+        let sphere = Sphere::new(Vector3::new(0.0, 0.0, -3.0), 1.0);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, -3.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert_eq!(sphere.hit_both(&ray, 0.001, f32::MAX), Some((1.0, 1.0)));
+    }
+
+    #[test]
+    fn sphere_hit_from_outside() {
+        let sphere = Sphere::new(Vector3::new(0.0, 0.0, -3.0), 1.0);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit_rec = sphere.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        assert!(hit_rec.front_face);
+        assert_eq!(hit_rec.p, Vector3::new(0.0, 0.0, -2.0));
+        assert_eq!(hit_rec.normal, (hit_rec.p - sphere.center) / sphere.radius);
+    }
+
+    #[test]
+    fn sphere_hit_from_inside() {
+        let sphere = Sphere::new(Vector3::new(1.0, 2.0, -3.0), 2.0);
+        let origin = Vector3::new(1.5, 2.0, -3.0);
+        let ray = Ray::new(origin, Vector3::new(0.0, 1.0, 0.0));
+        let hit_rec = sphere.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        assert!(!hit_rec.front_face);
+        assert!((hit_rec.p - Vector3::new(1.5, 2.0 + 3.75_f32.sqrt(), -3.0)).normal() < 1e-5);
+        assert!((hit_rec.normal.normal() - 1.0).abs() < 1e-5);
+        assert!(hit_rec.normal.dot(origin - hit_rec.p) > 0.0);
+        assert!(hit_rec.normal.dot(ray.direction) < 0.0);
+    }
+
+    #[test]
+    fn sphere_uv_points() {
+        let close = |(u, v): (f32, f32), expected: (f32, f32)| (u - expected.0).abs() < 1e-6 && (v - expected.1).abs() < 1e-6;
+
+        assert!(close(sphere_uv(Vector3::new(0.0, 0.0, -1.0)), (0.75, 0.5)));
+        assert!(close(sphere_uv(Vector3::new(1.0, 0.0, 0.0)), (0.5, 0.5)));
+        assert!(close(sphere_uv(Vector3::new(0.0, 0.0, 1.0)), (0.25, 0.5)));
+        assert_eq!(sphere_uv(Vector3::new(0.0, 1.0, 0.0)).1, 1.0);
+        assert_eq!(sphere_uv(Vector3::new(0.0, -1.0, 0.0)).1, 0.0);
+    }
+
+    #[test]
+    fn sphere_hit_uv() {
+        let sphere = Sphere::new(Vector3::new(0.0, 0.0, -3.0), 2.0);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit_rec = sphere.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        // The ray hits the point of the sphere facing +z
+        assert!((hit_rec.u - 0.25).abs() < 1e-6);
+        assert!((hit_rec.v - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn moving_sphere_center() {
+        let center0 = Vector3::new(0.0, 0.0, -1.0);
+        let center1 = Vector3::new(1.0, 2.0, -1.0);
+        let sphere = MovingSphere::new(center0, center1, 0.5, 1.5, 0.5);
+
+        assert_eq!(sphere.center(0.5), center0);
+        assert_eq!(sphere.center(1.5), center1);
+        assert_eq!(sphere.center(1.0), Vector3::new(0.5, 1.0, -1.0));
+        assert_eq!(MovingSphere::new(center0, center1, 1.0, 1.0, 0.5).center(3.0), center0);
+    }
+
+    #[test]
+    fn moving_sphere_hit_at_time() {
+        let sphere = MovingSphere::new(Vector3::new(0.0, 0.0, -2.0), Vector3::new(3.0, 0.0, -2.0), 0.0, 1.0, 0.5);
+        let at_start = Ray::new_at(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0), 0.0);
+        let at_end = Ray::new_at(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0), 1.0);
+        let following = Ray::new_at(Vector3::new(3.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0), 1.0);
+
+        assert_eq!(sphere.hit_record(&at_start, 0.001, f32::MAX).unwrap().p, Vector3::new(0.0, 0.0, -1.5));
+        assert!(sphere.hit_record(&at_end, 0.001, f32::MAX).is_none());
+        assert_eq!(sphere.hit_record(&following, 0.001, f32::MAX).unwrap().normal, Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn moving_sphere_hits_like_sphere() {
+        let moving = MovingSphere::new(Vector3::new(0.0, 0.0, -2.0), Vector3::new(1.0, 0.5, -2.0), 0.0, 1.0, 0.5);
+        let ray = Ray::new_at(Vector3::new(0.2, 0.1, 0.0), Vector3::new(0.3, 0.2, -1.0), 0.6);
+        let still = Sphere::new(moving.center(0.6), 0.5);
+
+        let (a, b) = (moving.hit_record(&ray, 0.001, f32::MAX).unwrap(), still.hit_record(&ray, 0.001, f32::MAX).unwrap());
+        assert_eq!((a.t, a.p, a.normal, a.u, a.v, a.front_face), (b.t, b.p, b.normal, b.u, b.v, b.front_face));
+    }
+
+    #[test]
+    fn moving_sphere_bounding_box() {
+        let sphere = MovingSphere::new(Vector3::new(0.0, 0.0, -2.0), Vector3::new(3.0, -1.0, -2.0), 0.0, 1.0, 0.5);
+        let bbox = sphere.bounding_box().unwrap();
+
+        assert_eq!(bbox.min, Vector3::new(-0.5, -1.5, -2.5));
+        assert_eq!(bbox.max, Vector3::new(3.5, 0.5, -1.5));
+    }
+
+    #[test]
+    fn plane_hit() {
+        let plane = Plane::new(Vector3::new(0.0, -1.0, 0.0), Vector3::new(0.0, 2.0, 0.0));
+        let ray = Ray::new(Vector3::new(1.0, 1.0, 0.0), Vector3::new(0.0, -1.0, -1.0));
+        let hit_rec = plane.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        assert_eq!(hit_rec.t, 2.0);
+        assert_eq!(hit_rec.p, Vector3::new(1.0, -1.0, -2.0));
+        assert_eq!(hit_rec.normal, Vector3::new(0.0, 1.0, 0.0));
+        assert!(hit_rec.front_face);
+        assert!(plane.hit_record(&ray, 0.001, 1.5).is_none());
+    }
+
+    #[test]
+    fn plane_parallel_ray() {
+        let plane = Plane::new(Vector3::new(0.0, -1.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let above = Ray::new(Vector3::new(0.0, 1.0, 0.0), Vector3::new(1.0, 0.0, -1.0));
+        let inside = Ray::new(Vector3::new(0.0, -1.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert!(plane.hit_record(&above, 0.001, f32::MAX).is_none());
+        assert!(plane.hit_record(&inside, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn plane_hit_from_behind() {
+        let plane = Plane::new(Vector3::new(0.0, -1.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let ray = Ray::new(Vector3::new(0.0, -3.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let hit_rec = plane.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        assert_eq!(hit_rec.t, 2.0);
+        assert!(!hit_rec.front_face);
+        assert_eq!(hit_rec.normal, Vector3::new(0.0, -1.0, 0.0));
+        assert!(plane.hit_record(&Ray::new(Vector3::new(0.0, -3.0, 0.0), Vector3::new(0.0, -1.0, 0.0)), 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn aabox_hit_front_face() {
+        let aabox = AABox::new(Vector3::new(-1.0, -1.0, -3.0), Vector3::new(1.0, 1.0, -2.0));
+        let ray = Ray::new(Vector3::new(0.5, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit_rec = aabox.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        assert_eq!(hit_rec.t, 2.0);
+        assert_eq!(hit_rec.p, Vector3::new(0.5, 0.0, -2.0));
+        assert_eq!(hit_rec.normal, Vector3::new(0.0, 0.0, 1.0));
+        assert!(hit_rec.front_face);
+        assert!(aabox.hit_record(&ray, 0.001, 1.5).is_none());
+    }
+
+    #[test]
+    fn aabox_hit_side_faces() {
+        let aabox = AABox::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+        let from_right = Ray::new(Vector3::new(3.0, 0.2, 0.0), Vector3::new(-1.0, 0.0, 0.1));
+        let from_below = Ray::new(Vector3::new(0.0, -4.0, 0.0), Vector3::new(0.1, 1.0, -0.2));
+
+        assert_eq!(aabox.hit_record(&from_right, 0.001, f32::MAX).unwrap().normal, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(aabox.hit_record(&from_below, 0.001, f32::MAX).unwrap().normal, Vector3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn aabox_miss_beside() {
+        let aabox = AABox::new(Vector3::new(-1.0, -1.0, -3.0), Vector3::new(1.0, 1.0, -2.0));
+        let beside = Ray::new(Vector3::new(1.5, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let away = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(aabox.hit_record(&beside, 0.001, f32::MAX).is_none());
+        assert!(aabox.hit_record(&away, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn aabox_hit_from_inside() {
+        let aabox = AABox::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit_rec = aabox.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        assert_eq!(hit_rec.t, 1.0);
+        assert!(!hit_rec.front_face);
+        assert_eq!(hit_rec.normal, Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn aabox_tessellate() {
+        let aabox = AABox::new(Vector3::new(-1.0, 0.0, -3.0), Vector3::new(1.0, 2.0, -1.0));
+        let center = Vector3::new(0.0, 1.0, -2.0);
+        let triangles = aabox.tessellate(1).unwrap();
+
+        assert_eq!(triangles.len(), 12);
+        for triangle in triangles {
+            let normal = (triangle.b - triangle.a).cross(triangle.c - triangle.a);
+            assert!(normal.dot(triangle.a - center) > 0.0);
+        }
+    }
+
+    #[test]
+    fn xy_rect_hit_center() {
+        let rect = XyRect::new(-1.0, 1.0, -2.0, 2.0, -3.0);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit_rec = rect.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        assert_eq!(hit_rec.t, 3.0);
+        assert_eq!(hit_rec.p, Vector3::new(0.0, 0.0, -3.0));
+        assert_eq!(hit_rec.normal, Vector3::new(0.0, 0.0, 1.0));
+        assert!(hit_rec.front_face);
+        assert_eq!((hit_rec.u, hit_rec.v), (0.5, 0.5));
+    }
+
+    #[test]
+    fn rect_hit_from_behind() {
+        let rect = XzRect::new(0.0, 2.0, 0.0, 2.0, 1.0);
+        let ray = Ray::new(Vector3::new(1.5, 0.0, 0.5), Vector3::new(0.0, 1.0, 0.0));
+        let hit_rec = rect.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        assert_eq!(hit_rec.p, Vector3::new(1.5, 1.0, 0.5));
+        assert_eq!(hit_rec.normal, Vector3::new(0.0, -1.0, 0.0));
+        assert!(!hit_rec.front_face);
+        assert_eq!((hit_rec.u, hit_rec.v), (0.75, 0.25));
+    }
+
+    #[test]
+    fn rect_miss_out_of_bounds() {
+        let rect = YzRect::new(-1.0, 1.0, -1.0, 1.0, 2.0);
+
+        assert!(rect.hit_record(&Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0)), 0.001, f32::MAX).is_some());
+        assert!(rect.hit_record(&Ray::new(Vector3::new(0.0, 1.5, 0.0), Vector3::new(1.0, 0.0, 0.0)), 0.001, f32::MAX).is_none());
+        assert!(rect.hit_record(&Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.6)), 0.001, f32::MAX).is_none());
+        assert!(rect.hit_record(&Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0)), 0.001, 1.5).is_none());
+    }
+
+    #[test]
+    fn rect_parallel_ray() {
+        let rect = XyRect::new(-1.0, 1.0, -1.0, 1.0, 0.0);
+
+        assert!(rect.hit_record(&Ray::new(Vector3::new(-2.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0)), 0.001, f32::MAX).is_none());
+        assert!(rect.hit_record(&Ray::new(Vector3::new(-2.0, 0.0, 1.0), Vector3::new(1.0, 1.0, 0.0)), 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn rect_bounding_box_thickness() {
+        let bbox = XzRect::new(-1.0, 2.0, -3.0, 4.0, 5.0).bounding_box().unwrap();
+
+        assert_eq!(bbox.min, Vector3::new(-1.0, 5.0 - RECT_THICKNESS, -3.0));
+        assert_eq!(bbox.max, Vector3::new(2.0, 5.0 + RECT_THICKNESS, 4.0));
+        assert!(bbox.hit(&Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)), 0.001, f32::MAX));
+    }
+
+    #[test]
+    fn rect_tessellate_faces_normal() {
+        let rects: [Box<dyn Hitable>; 3] = [
+            Box::new(XyRect::new(0.0, 1.0, 0.0, 1.0, 0.0)),
+            Box::new(XzRect::new(0.0, 1.0, 0.0, 1.0, 0.0)),
+            Box::new(YzRect::new(0.0, 1.0, 0.0, 1.0, 0.0)),
+        ];
+        let normals = [Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 1.0, 0.0), Vector3::new(1.0, 0.0, 0.0)];
+        for (rect, normal) in rects.iter().zip(normals) {
+            let triangles = rect.tessellate(1).unwrap();
+            assert_eq!(triangles.len(), 2);
+            for triangle in triangles {
+                assert_eq!((triangle.b - triangle.a).cross(triangle.c - triangle.a).unit_vec(), normal);
+            }
+        }
+    }
+}
@@ -1,41 +1,603 @@
 use super::*;
+use super::aabb::surrounding_box;
+use super::bvh::BvhIndex;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::RwLock;
+
+/// ## OBJ_SUBDIVISIONS
+/// Subdivisions used for curved surfaces when a scene is exported to OBJ
+pub const OBJ_SUBDIVISIONS: usize = 16;
+
+/// ## BVH_THRESHOLD
+/// Standard number of objects a scene can have before a bounding volume hierarchy is used to find hits
+pub const BVH_THRESHOLD: usize = 32;
 
 /// ## Scene
 /// Reptesentation of the scene. 
 /// Contains a list of all hitable objects in the scene.
+/// The list is only changed through the methods of the scene, so the cached hierarchy and bounds are never out of date.
 pub struct Scene {
-    pub object_list: Vec<Box<dyn Hitable>>
+    object_list: Vec<Box<dyn Hitable>>,
+    /// Scenes with more objects than this use a bounding volume hierarchy instead of testing every object
+    pub bvh_threshold: usize,
+    /// Hierarchy over object_list, None until it is needed.
+    /// The hierarchy itself is None if some object has no bounding box.
+    bvh_cache: RwLock<Option<Option<BvhIndex>>>,
+    /// Box surrounding object_list, None until it is needed.
+    /// The box itself is None if the scene is empty or some object has no bounding box.
+    bounds_cache: RwLock<Option<Option<Aabb>>>,
+    /// Indices in object_list of the objects that give off light, in the order they were marked
+    lights: Vec<usize>,
+}
+
+impl Default for Scene {
+    /// ## default
+    /// Creates a scene with standard values: a small sphere resting on a huge sphere used as ground
+    fn default() -> Scene {
+        Scene::from_objects(vec![
+            Box::new(Sphere::new(Vector3::new(0.0, 0.0, -1.0), 0.5)),
+            Box::new(Sphere::new(Vector3::new(0.0, -100.5, -1.0), 100.0)),
+        ])
+    }
 }
 
 impl Scene {
     /// ## new
-    /// Creates a new scene with standard values.
+    /// Creates a new scene with standard values, the same as default
     pub fn new() -> Scene {
+        Scene::default()
+    }
+
+    /// ## empty
+    /// Creates a scene without any objects
+    pub fn empty() -> Scene {
+        Scene::from_objects(Vec::new())
+    }
+
+    /// ## from_objects
+    /// Creates a scene with the given objects
+    pub fn from_objects(object_list: Vec<Box<dyn Hitable>>) -> Scene {
         Scene {
-            object_list: vec![
-                Box::new(Sphere::new(Vector3::new(0.0, 0.0, -1.0), 0.5)),
-                Box::new(Sphere::new(Vector3::new(0.0, -100.5, -1.0), 100.0)),
-                ],
+            object_list,
+            bvh_threshold: BVH_THRESHOLD,
+            bvh_cache: RwLock::new(None),
+            bounds_cache: RwLock::new(None),
+            lights: Vec::new(),
         }
     }
-}
 
-impl Hitable for Scene {
-    /// ## hit
-    /// Goes through all objects in the scene and cheks wheter they are hit by a given ray.
-    /// If it hits a object store information regarding that in HitRecord
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, hit_rec: &mut HitRecord) -> bool {
+    /// ## objects
+    /// Returns the objects in the scene, in the order they were added
+    pub fn objects(&self) -> &[Box<dyn Hitable>] {
+        &self.object_list
+    }
+
+    /// ## add
+    /// Adds an object to the end of the scene. Returns the scene so several objects can be added in a row.
+    pub fn add(&mut self, object: Box<dyn Hitable>) -> &mut Self {
+        self.object_list.push(object);
+        self.invalidate_bvh();
+        self
+    }
+
+    /// ## remove
+    /// Removes and returns the object at index, the objects after it move one step down.
+    /// The object stops being a light, and the lights after it are moved down with the objects.
+    /// Panics if index is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Box<dyn Hitable> {
+        let object: Box<dyn Hitable> = self.object_list.remove(index);
+        self.lights.retain(|light| *light != index);
+        for light in self.lights.iter_mut().filter(|light| **light > index) {
+            *light -= 1;
+        }
+        self.invalidate_bvh();
+        object
+    }
+
+    /// ## replace
+    /// Puts object at index in place of the object there and returns the old one.
+    /// Whether the object at index is a light is kept. Panics if index is out of bounds.
+    pub fn replace(&mut self, index: usize, object: Box<dyn Hitable>) -> Box<dyn Hitable> {
+        let old: Box<dyn Hitable> = std::mem::replace(&mut self.object_list[index], object);
+        self.invalidate_bvh();
+        old
+    }
+
+    /// ## clear
+    /// Removes every object and light from the scene
+    pub fn clear(&mut self) {
+        self.object_list.clear();
+        self.lights.clear();
+        self.invalidate_bvh();
+    }
+
+    /// ## mark_light
+    /// Marks the object at index as giving off light, marking it again does nothing.
+    /// Panics if index is out of bounds.
+    pub fn mark_light(&mut self, index: usize) {
+        assert!(index < self.object_list.len(), "Can't mark object {} as a light in a scene with {} objects", index, self.object_list.len());
+        if !self.lights.contains(&index) {
+            self.lights.push(index);
+        }
+    }
+
+    /// ## unmark_light
+    /// Makes the object at index stop being a light
+    pub fn unmark_light(&mut self, index: usize) {
+        self.lights.retain(|light| *light != index);
+    }
+
+    /// ## lights
+    /// Returns the indices in object_list of the objects marked as lights
+    pub fn lights(&self) -> &[usize] {
+        &self.lights
+    }
+
+    /// ## merge
+    /// Moves all objects of another scene into this one, after the objects already in it
+    pub fn merge(&mut self, other: Scene) {
+        let offset: usize = self.object_list.len();
+        self.lights.extend(other.lights.iter().map(|light| light + offset));
+        self.object_list.extend(other.object_list);
+        self.invalidate_bvh();
+    }
+
+    /// ## invalidate_bvh
+    /// Makes the scene rebuild its bounding volume hierarchy and world bounds the next time they are needed.
+    /// Called by every method that changes the objects.
+    fn invalidate_bvh(&mut self) {
+        *self.bvh_cache.get_mut().expect("Scene BVH lock poisoned") = None;
+        *self.bounds_cache.get_mut().expect("Scene bounds lock poisoned") = None;
+    }
+
+    /// ## world_bounds
+    /// Returns the box surrounding all objects in the scene, like bounding_box.
+    /// The box is computed the first time it is needed after the objects change and then reused.
+    pub fn world_bounds(&self) -> Option<Aabb> {
+        if let Some(bounds) = *self.bounds_cache.read().expect("Scene bounds lock poisoned") {
+            return bounds;
+        }
+        let mut boxes = self.object_list.iter().map(|object| object.bounding_box());
+        let bounds: Option<Aabb> = boxes.next().flatten()
+            .and_then(|first| boxes.try_fold(first, |acc, bbox| Some(surrounding_box(acc, bbox?))));
+        *self.bounds_cache.write().expect("Scene bounds lock poisoned") = Some(bounds);
+        bounds
+    }
+
+    /// ## hit_object
+    /// Checks whether a given ray hits any object in the scene like hit does,
+    /// and returns the index in object_list of the closest object hit. None if nothing is hit.
+    /// Scenes with more than bvh_threshold objects are searched with a bounding volume hierarchy,
+    /// which is built the first time it is needed after the objects change.
+    pub fn hit_object<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32, hit_rec: &mut HitRecord<'a>) -> Option<usize> {
+        if self.object_list.len() > self.bvh_threshold {
+            if let Some(hit_index) = self.hit_bvh(ray, t_min, t_max, hit_rec) {
+                return hit_index;
+            }
+        }
+
         let mut temp_rec: HitRecord = HitRecord::new();
-        let mut hit_anything: bool = false;
+        let mut hit_index: Option<usize> = None;
         let mut closest_yet: f32 = t_max;
 
-        for object in self.object_list.iter() {
+        for (index, object) in self.object_list.iter().enumerate() {
             if object.hit(ray, t_min, closest_yet, &mut temp_rec) {
-                hit_anything = true;
+                hit_index = Some(index);
                 closest_yet = temp_rec.t;
                 *hit_rec = temp_rec;
             }
         }
-        return hit_anything;
+        hit_index
+    }
+
+    /// ## hit_among
+    /// Finds the closest hit like hit_object, but only among the objects at the given indices
+    pub fn hit_among<'a>(&'a self, indices: &[usize], ray: &Ray, t_min: f32, t_max: f32, hit_rec: &mut HitRecord<'a>) -> Option<usize> {
+        let mut temp_rec: HitRecord = HitRecord::new();
+        let mut hit_index: Option<usize> = None;
+        let mut closest_yet: f32 = t_max;
+
+        for &index in indices {
+            if self.object_list[index].hit(ray, t_min, closest_yet, &mut temp_rec) {
+                hit_index = Some(index);
+                closest_yet = temp_rec.t;
+                *hit_rec = temp_rec;
+            }
+        }
+        hit_index
+    }
+
+    /// ## hit_with_hint
+    /// Finds the closest hit like hit_object, but tests the object at index hint first.
+    /// Neighbouring rays often hit the same object, and a close first hit lets the
+    /// other objects be tested with a shorter range. Returns the index of the object hit,
+    /// to use as the hint for the next ray, together with the hit.
+    pub fn hit_with_hint(&self, ray: &Ray, t_min: f32, t_max: f32, hint: Option<usize>) -> Option<(usize, HitRecord<'_>)> {
+        let mut temp_rec: HitRecord = HitRecord::new();
+        let mut closest: Option<(usize, HitRecord)> = None;
+        let mut closest_yet: f32 = t_max;
+
+        let hinted = hint.and_then(|index| Some((index, self.object_list.get(index)?)));
+        let rest = self.object_list.iter().enumerate().filter(|(index, _)| Some(*index) != hint);
+        for (index, object) in hinted.into_iter().chain(rest) {
+            if object.hit(ray, t_min, closest_yet, &mut temp_rec) {
+                closest_yet = temp_rec.t;
+                closest = Some((index, temp_rec));
+            }
+        }
+        closest
+    }
+
+    /// ## hit_bvh
+    /// Finds the closest hit like hit_object using the bounding volume hierarchy, building it first
+    /// if it is missing because the objects changed since it was last built.
+    /// None if the hierarchy can't be built because some object has no bounding box.
+    fn hit_bvh<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32, hit_rec: &mut HitRecord<'a>) -> Option<Option<usize>> {
+        if let Some(bvh) = self.bvh_cache.read().expect("Scene BVH lock poisoned").as_ref() {
+            return bvh.as_ref().map(|bvh| bvh.hit(&self.object_list, ray, t_min, t_max, hit_rec));
+        }
+        let bvh: Option<BvhIndex> = BvhIndex::new(&self.object_list);
+        let hit_index: Option<Option<usize>> = bvh.as_ref().map(|bvh| bvh.hit(&self.object_list, ray, t_min, t_max, hit_rec));
+        *self.bvh_cache.write().expect("Scene BVH lock poisoned") = Some(bvh);
+        hit_index
     }
-}
\ No newline at end of file
+
+    /// ## nearest_object
+    /// Returns the index in object_list of the object with its bounding box center closest to point.
+    /// Objects without a bounding box are skipped, None if no object has one.
+    pub fn nearest_object(&self, point: Vector3) -> Option<usize> {
+        self.object_list.iter().enumerate()
+            .filter_map(|(index, object)| Some((index, (object.bounding_box()?.centroid() - point).normal())))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+
+    /// ## export_obj
+    /// Writes the objects of the scene as triangles to a Wavefront OBJ file at path,
+    /// with curved surfaces split up using OBJ_SUBDIVISIONS.
+    pub fn export_obj(&self, path: &str) -> io::Result<()> {
+        let mut writer: BufWriter<File> = BufWriter::new(File::create(path)?);
+        self.write_obj(&mut writer, OBJ_SUBDIVISIONS)?;
+        writer.flush()
+    }
+
+    /// ## write_obj
+    /// Writes the objects of the scene as triangles in the Wavefront OBJ format, with every triangle
+    /// getting three vertices of its own. Objects that can't be made into triangles are skipped with a warning.
+    pub fn write_obj<W: Write>(&self, writer: &mut W, subdivisions: usize) -> io::Result<()> {
+        let mut vertices: usize = 0;
+        for (index, object) in self.object_list.iter().enumerate() {
+            let triangles: Vec<Triangle> = match object.tessellate(subdivisions) {
+                Some(triangles) => triangles,
+                None => {
+                    eprintln!("Warning: object {} can't be made into triangles and is left out of the OBJ", index);
+                    continue;
+                }
+            };
+            for triangle in triangles {
+                for corner in [triangle.a, triangle.b, triangle.c] {
+                    writeln!(writer, "v {} {} {}", corner.x, corner.y, corner.z)?;
+                }
+                writeln!(writer, "f {} {} {}", vertices + 1, vertices + 2, vertices + 3)?;
+                vertices += 3;
+            }
+        }
+        Ok(())
+    }
+
+    /// ## content_hash
+    /// Returns a hash of the objects in the scene and their parameters.
+    /// Scenes built the same way always get the same hash, also between runs.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher: ContentHasher = ContentHasher::new();
+        self.hash_content(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl Hitable for Scene {
+    /// ## hit_record
+    /// Goes through all objects in the scene and cheks wheter they are hit by a given ray.
+    /// If it hits a object return information regarding the closest hit.
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        let mut hit_rec: HitRecord = HitRecord::new();
+        self.hit_object(ray, t_min, t_max, &mut hit_rec).map(|_| hit_rec)
+    }
+
+    /// ## bounding_box
+    /// Returns the box surrounding all objects in the scene, using the cached world bounds.
+    /// None if the scene is empty or contains an unbounded object.
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.world_bounds()
+    }
+
+    /// ## hash_content
+    /// Adds every object in the scene to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("Scene");
+        hasher.write_u64(self.object_list.len() as u64);
+        for object in self.object_list.iter() {
+            object.hash_content(hasher);
+        }
+    }
+
+    /// ## tessellate
+    /// Returns the triangles of every object, None if some object can't be made into triangles
+    fn tessellate(&self, subdivisions: usize) -> Option<Vec<Triangle>> {
+        let mut triangles: Vec<Triangle> = Vec::new();
+        for object in self.object_list.iter() {
+            triangles.extend(object.tessellate(subdivisions)?);
+        }
+        Some(triangles)
+    }
+}
+
+/// Tests for Scene struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scene_default() {
+        assert_eq!(Scene::default().object_list.len(), 2);
+        assert_eq!(Scene::default().content_hash(), Scene::new().content_hash());
+    }
+
+    #[test]
+    fn scene_content_hash_equal() {
+        assert_eq!(Scene::new().content_hash(), Scene::new().content_hash());
+    }
+
+    #[test]
+    fn scene_content_hash_radius() {
+        let mut scene = Scene::new();
+        scene.replace(0, Box::new(Sphere::new(Vector3::new(0.0, 0.0, -1.0), 0.6)));
+
+        assert_ne!(Scene::new().content_hash(), scene.content_hash());
+    }
+
+    #[test]
+    fn scene_content_hash_material() {
+        use super::materials::Dielectric;
+        let mut scene = Scene::new();
+        scene.replace(0, Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, -1.0), 0.5, Box::new(Dielectric::new(1.5)))));
+
+        assert_ne!(Scene::new().content_hash(), scene.content_hash());
+    }
+
+    #[test]
+    fn scene_empty_add() {
+        let mut scene = Scene::empty();
+        assert!(scene.objects().is_empty());
+
+        scene.add(Box::new(Sphere::new(Vector3::new(-2.0, 0.0, -3.0), 0.5)))
+            .add(Box::new(Sphere::new(Vector3::new(0.0, 0.0, -3.0), 0.5)))
+            .add(Box::new(Sphere::new(Vector3::new(2.0, 0.0, -3.0), 0.5)));
+        assert_eq!(scene.objects().len(), 3);
+
+        let ray = Ray::new(Vector3::new(2.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let mut hit_rec = HitRecord::new();
+        assert_eq!(scene.hit_object(&ray, 0.001, f32::MAX, &mut hit_rec), Some(2));
+        assert_eq!(hit_rec.p, Vector3::new(2.0, 0.0, -2.5));
+
+        scene.clear();
+        assert!(scene.objects().is_empty());
+        assert!(!scene.hit(&ray, 0.001, f32::MAX, &mut HitRecord::new()));
+    }
+
+    #[test]
+    fn scene_hit_object() {
+        let scene = Scene::new();
+        let mut hit_rec = HitRecord::new();
+        let ball = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let ground = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let sky = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+
+        assert_eq!(scene.hit_object(&ball, 0.001, f32::MAX, &mut hit_rec), Some(0));
+        assert_eq!(scene.hit_object(&ground, 0.001, f32::MAX, &mut hit_rec), Some(1));
+        assert_eq!(scene.hit_object(&sky, 0.001, f32::MAX, &mut hit_rec), None);
+    }
+
+    #[test]
+    fn scene_merge() {
+        let mut scene = Scene::new();
+        let other = Scene::from_objects(vec![Box::new(Sphere::new(Vector3::new(0.0, 5.0, -1.0), 0.5))]);
+        scene.merge(other);
+
+        assert_eq!(scene.objects().len(), 3);
+        let mut hit_rec = HitRecord::new();
+        let ball = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let ground = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let merged = Ray::new(Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(scene.hit_object(&ball, 0.001, f32::MAX, &mut hit_rec), Some(0));
+        assert_eq!(scene.hit_object(&ground, 0.001, f32::MAX, &mut hit_rec), Some(1));
+        // Starts inside the first ball, so skip past it before looking for the merged one
+        assert_eq!(scene.hit_object(&merged, 1.0, f32::MAX, &mut hit_rec), Some(2));
+    }
+
+    #[test]
+    fn scene_nearest_object() {
+        let scene = Scene::new();
+
+        assert_eq!(scene.nearest_object(Vector3::new(0.2, 0.3, -1.1)), Some(0));
+        assert_eq!(scene.nearest_object(Vector3::new(5.0, -90.0, 3.0)), Some(1));
+        assert_eq!(Scene::from_objects(vec![]).nearest_object(Vector3::new(0.0, 0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn scene_bvh_threshold_same_hits() {
+        use rand::{Rng, SeedableRng, rngs::StdRng};
+
+        let mut rng = StdRng::seed_from_u64(4);
+        let spheres = |rng: &mut StdRng| -> Vec<Box<dyn Hitable>> {
+            (0..40).map(|_| {
+                let center = Vector3::new(rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0), rng.gen_range(-30.0..-10.0));
+                Box::new(Sphere::new(center, rng.gen_range(0.2..2.0))) as Box<dyn Hitable>
+            }).collect()
+        };
+        let mut linear = Scene::from_objects(spheres(&mut StdRng::seed_from_u64(8)));
+        linear.bvh_threshold = 40;
+        let mut bvh = Scene::from_objects(spheres(&mut StdRng::seed_from_u64(8)));
+        bvh.bvh_threshold = 39;
+
+        let mut hits = 0;
+        for _ in 0..2000 {
+            let direction = Vector3::new(rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5), -1.0);
+            let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), direction);
+            let mut linear_rec = HitRecord::new();
+            let mut bvh_rec = HitRecord::new();
+            let linear_hit = linear.hit_object(&ray, 0.001, f32::MAX, &mut linear_rec);
+            let bvh_hit = bvh.hit_object(&ray, 0.001, f32::MAX, &mut bvh_rec);
+
+            assert_eq!(linear_hit, bvh_hit);
+            if linear_hit.is_some() {
+                assert_eq!(linear_rec.t, bvh_rec.t);
+                assert_eq!(linear_rec.p, bvh_rec.p);
+                hits += 1;
+            }
+        }
+        assert!(hits > 100);
+    }
+
+    #[test]
+    fn scene_bvh_rebuilds_after_adding() {
+        let mut scene = Scene::new();
+        scene.bvh_threshold = 0;
+        let up = Ray::new(Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0));
+
+        assert_eq!(scene.hit_object(&up, 1.0, f32::MAX, &mut HitRecord::new()), None);
+        scene.add(Box::new(Sphere::new(Vector3::new(0.0, 5.0, -1.0), 0.5)));
+        assert_eq!(scene.hit_object(&up, 1.0, f32::MAX, &mut HitRecord::new()), Some(2));
+    }
+
+    #[test]
+    fn scene_bvh_rebuilds_after_replace() {
+        let mut scene = Scene::new();
+        scene.bvh_threshold = 0;
+        let up = Ray::new(Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0));
+        let ball = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert_eq!(scene.hit_object(&ball, 0.001, f32::MAX, &mut HitRecord::new()), Some(0));
+        // Move the ball up without changing the number of objects, the old hierarchy would miss it
+        scene.replace(0, Box::new(Sphere::new(Vector3::new(0.0, 5.0, -1.0), 0.5)));
+        let mut hit_rec = HitRecord::new();
+        assert_eq!(scene.hit_object(&up, 0.001, f32::MAX, &mut hit_rec), Some(0));
+        assert_eq!(hit_rec.p, Vector3::new(0.0, 4.5, -1.0));
+        assert_eq!(scene.hit_object(&ball, 0.001, f32::MAX, &mut HitRecord::new()), None);
+    }
+
+    #[test]
+    fn scene_hit_with_hint() {
+        let mut scene = Scene::new();
+        scene.add(Box::new(Sphere::new(Vector3::new(0.3, 0.1, -2.0), 0.5)));
+        let directions = [
+            Vector3::new(0.0, 0.0, -1.0),
+            Vector3::new(0.3, 0.1, -1.0),
+            Vector3::new(0.2, 0.05, -1.0),
+            Vector3::new(0.0, -1.0, -0.2),
+            Vector3::new(0.0, 1.0, 0.0),
+        ];
+
+        for direction in directions {
+            let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), direction);
+            let mut hit_rec = HitRecord::new();
+            let expected = scene.hit_object(&ray, 0.001, f32::MAX, &mut hit_rec);
+            for hint in [None, Some(0), Some(1), Some(2), Some(7)] {
+                let hinted = scene.hit_with_hint(&ray, 0.001, f32::MAX, hint);
+                assert_eq!(hinted.map(|(index, _)| index), expected);
+                if let Some((_, hinted_rec)) = hinted {
+                    assert_eq!(hinted_rec.t, hit_rec.t);
+                    assert_eq!(hinted_rec.p, hit_rec.p);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scene_world_bounds() {
+        let mut scene = Scene::from_objects(vec![
+            Box::new(Sphere::new(Vector3::new(0.0, 0.0, 0.0), 1.0)),
+            Box::new(Sphere::new(Vector3::new(2.0, 0.0, 0.0), 1.0)),
+        ]);
+        let bounds = Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(3.0, 1.0, 1.0));
+
+        assert_eq!(scene.world_bounds(), Some(bounds));
+        assert_eq!(scene.world_bounds(), scene.world_bounds());
+        assert_eq!(scene.bounding_box(), Some(bounds));
+
+        scene.add(Box::new(Sphere::new(Vector3::new(0.0, 5.0, 0.0), 1.0)));
+        assert_eq!(scene.world_bounds(), Some(Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(3.0, 6.0, 1.0))));
+
+        scene.remove(2);
+        assert_eq!(scene.world_bounds(), Some(bounds));
+
+        scene.clear();
+        assert_eq!(scene.world_bounds(), None);
+    }
+
+    #[test]
+    fn scene_world_bounds_after_replace() {
+        let mut scene = Scene::from_objects(vec![
+            Box::new(Sphere::new(Vector3::new(0.0, 0.0, 0.0), 1.0)),
+            Box::new(Sphere::new(Vector3::new(2.0, 0.0, 0.0), 1.0)),
+        ]);
+        assert_eq!(scene.world_bounds(), Some(Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(3.0, 1.0, 1.0))));
+
+        // Same number of objects as before, the bounds still have to follow the new one
+        let old = scene.replace(1, Box::new(Sphere::new(Vector3::new(0.0, 5.0, 0.0), 1.0)));
+        assert_eq!(old.bounding_box(), Some(Aabb::new(Vector3::new(1.0, -1.0, -1.0), Vector3::new(3.0, 1.0, 1.0))));
+        assert_eq!(scene.bounding_box(), Some(Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 6.0, 1.0))));
+
+        // Adding one object and removing another also keeps the count
+        scene.add(Box::new(Sphere::new(Vector3::new(-4.0, 0.0, 0.0), 1.0)));
+        scene.remove(1);
+        assert_eq!(scene.world_bounds(), Some(Aabb::new(Vector3::new(-5.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0))));
+    }
+
+    #[test]
+    fn scene_export_obj() {
+        use super::mesh::Mesh;
+
+        let path = std::env::temp_dir().join(format!("scene_export_obj_{}.obj", std::process::id()));
+        let path = path.to_str().unwrap();
+        Scene::new().export_obj(path).unwrap();
+        let mesh = Mesh::load_obj(path).unwrap();
+        let obj = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        // Two spheres of 4 * 16 * 15 triangles each
+        let faces = 2 * 4 * OBJ_SUBDIVISIONS * (OBJ_SUBDIVISIONS - 1);
+        assert_eq!(obj.lines().filter(|line| line.starts_with("f ")).count(), faces);
+        assert_eq!(obj.lines().filter(|line| line.starts_with("v ")).count(), 3 * faces);
+        assert_eq!(mesh.triangle_count(), faces);
+    }
+
+    #[test]
+    fn scene_lights() {
+        let mut scene = Scene::new();
+        scene.add(Box::new(Sphere::new(Vector3::new(0.0, 3.0, -1.0), 0.5)));
+        scene.add(Box::new(Sphere::new(Vector3::new(2.0, 3.0, -1.0), 0.5)));
+        assert!(scene.lights().is_empty());
+
+        scene.mark_light(2);
+        scene.mark_light(3);
+        scene.mark_light(2);
+        assert_eq!(scene.lights(), &[2, 3]);
+
+        scene.unmark_light(2);
+        assert_eq!(scene.lights(), &[3]);
+
+        // Removing an object before the light moves the light down with it
+        scene.remove(0);
+        assert_eq!(scene.lights(), &[2]);
+        scene.remove(2);
+        assert!(scene.lights().is_empty());
+
+        let mut other = Scene::new();
+        other.mark_light(1);
+        scene.merge(other);
+        assert_eq!(scene.lights(), &[3]);
+    }
+}
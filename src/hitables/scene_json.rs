@@ -0,0 +1,323 @@
+use super::*;
+use super::scene::Scene;
+use super::objects::{Sphere, Plane, AABox};
+use super::materials::{Lambertian, Metal, Dielectric, DiffuseLight};
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::fs;
+use std::io;
+
+/// ## SceneError
+/// Reasons a scene description can't be turned into a Scene
+#[derive(Debug)]
+pub enum SceneError {
+    /// There is no file at the given path
+    FileNotFound(String),
+    /// The file exists but couldn't be read
+    Io(io::Error),
+    /// The description isn't valid JSON or is missing values an object needs
+    Parse(serde_json::Error),
+    /// An object has a type no Hitable is made from
+    UnknownObject(String),
+    /// A material has a type no Material is made from
+    UnknownMaterial(String),
+    /// An object or its material has values it can't be made from, like a sphere without a positive radius
+    Invalid(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::FileNotFound(path) => write!(f, "scene file {} not found", path),
+            SceneError::Io(error) => write!(f, "failed to read scene file: {}", error),
+            SceneError::Parse(error) => write!(f, "invalid scene description: {}", error),
+            SceneError::UnknownObject(kind) => write!(f, "unknown object type \"{}\"", kind),
+            SceneError::UnknownMaterial(kind) => write!(f, "unknown material type \"{}\"", kind),
+            SceneError::Invalid(reason) => write!(f, "invalid object: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io(error) => Some(error),
+            SceneError::Parse(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(error: serde_json::Error) -> SceneError {
+        SceneError::Parse(error)
+    }
+}
+
+/// ## SceneDescription
+/// The top level of a JSON scene: a list of objects
+#[derive(Deserialize)]
+struct SceneDescription {
+    objects: Vec<Tagged>,
+}
+
+/// ## Tagged
+/// An object or material with its "type" and the rest of its parameters, which depend on the type
+#[derive(Deserialize)]
+struct Tagged {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(flatten)]
+    parameters: Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct SphereDescription {
+    center: [f32; 3],
+    radius: f32,
+    material: Option<Tagged>,
+}
+
+#[derive(Deserialize)]
+struct PlaneDescription {
+    point: [f32; 3],
+    normal: [f32; 3],
+    material: Option<Tagged>,
+}
+
+#[derive(Deserialize)]
+struct BoxDescription {
+    min: [f32; 3],
+    max: [f32; 3],
+    material: Option<Tagged>,
+}
+
+#[derive(Deserialize)]
+struct LambertianDescription {
+    albedo: [f32; 3],
+}
+
+#[derive(Deserialize)]
+struct MetalDescription {
+    albedo: [f32; 3],
+    #[serde(default)]
+    fuzz: f32,
+}
+
+#[derive(Deserialize)]
+struct DielectricDescription {
+    refraction_index: f32,
+}
+
+#[derive(Deserialize)]
+struct DiffuseLightDescription {
+    emit: [f32; 3],
+}
+
+impl Scene {
+    /// ## from_json
+    /// Reads a scene described in JSON from the file at path, see from_json_str for the format
+    pub fn from_json(path: &str) -> Result<Scene, SceneError> {
+        let json: String = fs::read_to_string(path).map_err(|error| match error.kind() {
+            io::ErrorKind::NotFound => SceneError::FileNotFound(path.to_string()),
+            _ => SceneError::Io(error),
+        })?;
+        Scene::from_json_str(&json)
+    }
+
+    /// ## from_json_str
+    /// Creates a scene from a JSON description like
+    /// `{"objects": [{"type": "sphere", "center": [0, 0, -1], "radius": 0.5, "material": {"type": "metal", "albedo": [0.8, 0.6, 0.2], "fuzz": 0.1}}]}`.
+    /// Objects are "sphere" (center, radius), "plane" (point, normal) and "box" (min, max), and materials
+    /// "lambertian" (albedo), "metal" (albedo, optional fuzz), "dielectric" (refraction_index) and "diffuse_light" (emit).
+    /// Objects without a material are made of the standard gray diffuse material.
+    pub fn from_json_str(json: &str) -> Result<Scene, SceneError> {
+        let description: SceneDescription = serde_json::from_str(json)?;
+        let objects: Vec<Box<dyn Hitable>> = description.objects.into_iter()
+            .map(build_object)
+            .collect::<Result<_, _>>()?;
+        Ok(Scene::from_objects(objects))
+    }
+}
+
+/// ## build_object
+/// Returns the Hitable described by an object of a JSON scene.
+/// The values are checked before the object is made, so a bad description gives an error instead of a panic.
+fn build_object(object: Tagged) -> Result<Box<dyn Hitable>, SceneError> {
+    let parameters: Value = Value::Object(object.parameters);
+    Ok(match object.kind.as_str() {
+        "sphere" => {
+            let sphere: SphereDescription = serde_json::from_value(parameters)?;
+            if sphere.radius.is_nan() || sphere.radius <= 0.0 {
+                return Err(SceneError::Invalid(format!("sphere radius must be above zero, got {}", sphere.radius)));
+            }
+            match sphere.material {
+                Some(material) => Box::new(Sphere::with_material(Vector3::from(sphere.center), sphere.radius, build_material(material)?)),
+                None => Box::new(Sphere::new(Vector3::from(sphere.center), sphere.radius)),
+            }
+        }
+        "plane" => {
+            let plane: PlaneDescription = serde_json::from_value(parameters)?;
+            let normal: Vector3 = Vector3::from(plane.normal).try_unit_vec()
+                .ok_or_else(|| SceneError::Invalid("plane normal must not be zero".to_string()))?;
+            match plane.material {
+                Some(material) => Box::new(Plane::with_material(Vector3::from(plane.point), normal, build_material(material)?)),
+                None => Box::new(Plane::new(Vector3::from(plane.point), normal)),
+            }
+        }
+        "box" => {
+            let aabox: BoxDescription = serde_json::from_value(parameters)?;
+            if (0..3).any(|axis| aabox.min[axis].is_nan() || aabox.max[axis].is_nan() || aabox.min[axis] > aabox.max[axis]) {
+                return Err(SceneError::Invalid(format!("box min {:?} must not be above max {:?}", aabox.min, aabox.max)));
+            }
+            match aabox.material {
+                Some(material) => Box::new(AABox::with_material(Vector3::from(aabox.min), Vector3::from(aabox.max), build_material(material)?)),
+                None => Box::new(AABox::new(Vector3::from(aabox.min), Vector3::from(aabox.max))),
+            }
+        }
+        _ => return Err(SceneError::UnknownObject(object.kind)),
+    })
+}
+
+/// ## build_material
+/// Returns the Material described by a material of a JSON scene
+fn build_material(material: Tagged) -> Result<Box<dyn Material>, SceneError> {
+    let parameters: Value = Value::Object(material.parameters);
+    Ok(match material.kind.as_str() {
+        "lambertian" => {
+            let lambertian: LambertianDescription = serde_json::from_value(parameters)?;
+            Box::new(Lambertian::new(Vector3::from(lambertian.albedo)))
+        }
+        "metal" => {
+            let metal: MetalDescription = serde_json::from_value(parameters)?;
+            if metal.fuzz.is_nan() || metal.fuzz < 0.0 {
+                return Err(SceneError::Invalid(format!("metal fuzz must not be negative, got {}", metal.fuzz)));
+            }
+            Box::new(Metal::new(Vector3::from(metal.albedo), metal.fuzz))
+        }
+        "dielectric" => {
+            let dielectric: DielectricDescription = serde_json::from_value(parameters)?;
+            if dielectric.refraction_index.is_nan() || dielectric.refraction_index <= 0.0 {
+                return Err(SceneError::Invalid(format!("dielectric refraction index must be above zero, got {}", dielectric.refraction_index)));
+            }
+            Box::new(Dielectric::new(dielectric.refraction_index))
+        }
+        "diffuse_light" => {
+            let light: DiffuseLightDescription = serde_json::from_value(parameters)?;
+            Box::new(DiffuseLight::new(Vector3::from(light.emit)))
+        }
+        _ => return Err(SceneError::UnknownMaterial(material.kind)),
+    })
+}
+
+/// Tests for loading scenes from JSON
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r#"{
+        "objects": [
+            {"type": "sphere", "center": [0.0, 0.0, -1.0], "radius": 0.5,
+             "material": {"type": "metal", "albedo": [0.8, 0.6, 0.2], "fuzz": 0.1}},
+            {"type": "sphere", "center": [0, -100.5, -1], "radius": 100},
+            {"type": "plane", "point": [0, -2, 0], "normal": [0, 1, 0], "material": {"type": "lambertian", "albedo": [0.5, 0.5, 0.5]}},
+            {"type": "box", "min": [1, 0, -3], "max": [2, 1, -2], "material": {"type": "dielectric", "refraction_index": 1.5}},
+            {"type": "sphere", "center": [0, 3, -1], "radius": 1, "material": {"type": "diffuse_light", "emit": [4, 4, 4]}}
+        ]
+    }"#;
+
+    #[test]
+    fn scene_from_json_str() {
+        let scene = Scene::from_json_str(SCENE).unwrap();
+
+        assert_eq!(scene.objects().len(), 5);
+        assert_eq!(scene.objects()[0].bounding_box().unwrap().centroid(), Vector3::new(0.0, 0.0, -1.0));
+        assert!(scene.objects()[2].bounding_box().is_none());
+
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let mut hit_rec = HitRecord::new();
+        assert_eq!(scene.hit_object(&ray, 0.001, f32::MAX, &mut hit_rec), Some(0));
+        assert_eq!(hit_rec.p, Vector3::new(0.0, 0.0, -0.5));
+    }
+
+    #[test]
+    fn scene_from_json_str_matches_scene() {
+        let scene = Scene::from_json_str(SCENE).unwrap();
+        let expected = Scene::from_objects(vec![
+            Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, -1.0), 0.5, Box::new(Metal::new(Vector3::new(0.8, 0.6, 0.2), 0.1)))),
+            Box::new(Sphere::new(Vector3::new(0.0, -100.5, -1.0), 100.0)),
+            Box::new(Plane::with_material(Vector3::new(0.0, -2.0, 0.0), Vector3::new(0.0, 1.0, 0.0), Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5))))),
+            Box::new(AABox::with_material(Vector3::new(1.0, 0.0, -3.0), Vector3::new(2.0, 1.0, -2.0), Box::new(Dielectric::new(1.5)))),
+            Box::new(Sphere::with_material(Vector3::new(0.0, 3.0, -1.0), 1.0, Box::new(DiffuseLight::new(Vector3::new(4.0, 4.0, 4.0))))),
+        ]);
+
+        assert_eq!(scene.content_hash(), expected.content_hash());
+    }
+
+    #[test]
+    fn scene_from_json_str_errors() {
+        let unknown_object = r#"{"objects": [{"type": "torus", "center": [0, 0, 0]}]}"#;
+        let unknown_material = r#"{"objects": [{"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": {"type": "chrome"}}]}"#;
+        let missing_radius = r#"{"objects": [{"type": "sphere", "center": [0, 0, 0]}]}"#;
+
+        assert!(matches!(Scene::from_json_str(unknown_object), Err(SceneError::UnknownObject(kind)) if kind == "torus"));
+        assert!(matches!(Scene::from_json_str(unknown_material), Err(SceneError::UnknownMaterial(kind)) if kind == "chrome"));
+        assert!(matches!(Scene::from_json_str(missing_radius), Err(SceneError::Parse(_))));
+        assert!(matches!(Scene::from_json_str("{\"objects\": ["), Err(SceneError::Parse(_))));
+    }
+
+    #[test]
+    fn scene_from_json_str_invalid_sphere() {
+        let zero_radius = r#"{"objects": [{"type": "sphere", "center": [0, 0, 0], "radius": 0}]}"#;
+        let negative_radius = r#"{"objects": [{"type": "sphere", "center": [0, 0, 0], "radius": -1}]}"#;
+
+        assert!(matches!(Scene::from_json_str(zero_radius), Err(SceneError::Invalid(_))));
+        assert!(matches!(Scene::from_json_str(negative_radius), Err(SceneError::Invalid(reason)) if reason.contains("-1")));
+    }
+
+    #[test]
+    fn scene_from_json_str_invalid_plane() {
+        let zero_normal = r#"{"objects": [{"type": "plane", "point": [0, 0, 0], "normal": [0, 0, 0]}]}"#;
+        let error = Scene::from_json_str(zero_normal).err().unwrap();
+
+        assert!(matches!(error, SceneError::Invalid(_)));
+        assert_eq!(error.to_string(), "invalid object: plane normal must not be zero");
+    }
+
+    #[test]
+    fn scene_from_json_str_invalid_box() {
+        let flipped = r#"{"objects": [{"type": "box", "min": [0, 2, 0], "max": [1, 1, 1]}]}"#;
+        let flat = r#"{"objects": [{"type": "box", "min": [0, 1, 0], "max": [1, 1, 1]}]}"#;
+
+        assert!(matches!(Scene::from_json_str(flipped), Err(SceneError::Invalid(_))));
+        assert!(Scene::from_json_str(flat).is_ok());
+    }
+
+    #[test]
+    fn scene_from_json_str_invalid_dielectric() {
+        let zero_index = r#"{"objects": [{"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": {"type": "dielectric", "refraction_index": 0}}]}"#;
+        let negative_index = r#"{"objects": [{"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": {"type": "dielectric", "refraction_index": -1.5}}]}"#;
+
+        assert!(matches!(Scene::from_json_str(zero_index), Err(SceneError::Invalid(_))));
+        assert!(matches!(Scene::from_json_str(negative_index), Err(SceneError::Invalid(reason)) if reason.contains("-1.5")));
+    }
+
+    #[test]
+    fn scene_from_json_str_invalid_metal() {
+        let negative_fuzz = r#"{"objects": [{"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": {"type": "metal", "albedo": [1, 1, 1], "fuzz": -0.2}}]}"#;
+        let mirror = r#"{"objects": [{"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": {"type": "metal", "albedo": [1, 1, 1]}}]}"#;
+
+        assert!(matches!(Scene::from_json_str(negative_fuzz), Err(SceneError::Invalid(reason)) if reason.contains("fuzz")));
+        assert!(Scene::from_json_str(mirror).is_ok());
+    }
+
+    #[test]
+    fn scene_from_json_missing_file() {
+        let error = Scene::from_json("no/such/scene.json").err().unwrap();
+
+        assert!(matches!(error, SceneError::FileNotFound(_)));
+        assert_eq!(error.to_string(), "scene file no/such/scene.json not found");
+    }
+}
@@ -0,0 +1,214 @@
+use super::*;
+
+/// ## Translate
+/// An object moved by offset. The object itself stays where it is, rays are moved the other way
+/// before they are tested against it, so the same kind of object can be placed in many spots.
+pub struct Translate {
+    pub object: Box<dyn Hitable>,
+    pub offset: Vector3,
+}
+
+impl Translate {
+    /// ## new
+    /// Returns the object moved by offset
+    pub fn new(object: Box<dyn Hitable>, offset: Vector3) -> Translate {
+        Translate {
+            object,
+            offset,
+        }
+    }
+}
+
+impl Hitable for Translate {
+    /// ## hit_record
+    /// Checks whether a given Ray hits the moved object by testing the ray moved back by offset,
+    /// and moves the hit point of the closest hit along with the object
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        let moved: Ray = Ray { origin: ray.origin - self.offset, direction: ray.direction, wavelength: ray.wavelength, time: ray.time, random: ray.random };
+        let mut hit_rec: HitRecord = self.object.hit_record(&moved, t_min, t_max)?;
+        hit_rec.p += self.offset;
+        Some(hit_rec)
+    }
+
+    /// ## bounding_box
+    /// Returns the box of the object moved by offset, None if the object is unbounded
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bbox: Aabb = self.object.bounding_box()?;
+        Some(Aabb::new(bbox.min + self.offset, bbox.max + self.offset))
+    }
+
+    /// ## hash_content
+    /// Adds the offset and the object to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("Translate");
+        hasher.write_vector(self.offset);
+        self.object.hash_content(hasher);
+    }
+
+    /// ## tessellate
+    /// Returns the triangles of the object moved by offset
+    fn tessellate(&self, subdivisions: usize) -> Option<Vec<Triangle>> {
+        Some(self.object.tessellate(subdivisions)?.into_iter()
+            .map(|triangle| Triangle { a: triangle.a + self.offset, b: triangle.b + self.offset, c: triangle.c + self.offset, ..triangle })
+            .collect())
+    }
+}
+
+/// ## RotateY
+/// An object turned angle_degrees around the y axis, counterclockwise seen from above.
+/// Like Translate, rays are turned the other way before they are tested against the object.
+pub struct RotateY {
+    pub object: Box<dyn Hitable>,
+    pub angle_degrees: f32,
+    sin_theta: f32,
+    cos_theta: f32,
+}
+
+impl RotateY {
+    /// ## new
+    /// Returns the object turned angle_degrees around the y axis
+    pub fn new(object: Box<dyn Hitable>, angle_degrees: f32) -> RotateY {
+        let theta: f32 = angle_degrees.to_radians();
+        RotateY {
+            object,
+            angle_degrees,
+            sin_theta: theta.sin(),
+            cos_theta: theta.cos(),
+        }
+    }
+
+    /// ## to_object
+    /// Returns v turned from world space into the space of the object, the opposite way of the rotation
+    fn to_object(&self, v: Vector3) -> Vector3 {
+        Vector3::new(self.cos_theta*v.x - self.sin_theta*v.z, v.y, self.sin_theta*v.x + self.cos_theta*v.z)
+    }
+
+    /// ## to_world
+    /// Returns v turned from the space of the object into world space
+    fn to_world(&self, v: Vector3) -> Vector3 {
+        Vector3::new(self.cos_theta*v.x + self.sin_theta*v.z, v.y, -self.sin_theta*v.x + self.cos_theta*v.z)
+    }
+}
+
+impl Hitable for RotateY {
+    /// ## hit_record
+    /// Checks whether a given Ray hits the turned object by testing the ray turned back,
+    /// and turns the hit point and normal of the closest hit along with the object.
+    /// Turning keeps the angle between the ray and the normal, so the normal still points against the ray.
+    fn hit_record<'a>(&'a self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        let turned: Ray = Ray { origin: self.to_object(ray.origin), direction: self.to_object(ray.direction), wavelength: ray.wavelength, time: ray.time, random: ray.random };
+        let mut hit_rec: HitRecord = self.object.hit_record(&turned, t_min, t_max)?;
+        hit_rec.p = self.to_world(hit_rec.p);
+        hit_rec.normal = self.to_world(hit_rec.normal);
+        Some(hit_rec)
+    }
+
+    /// ## bounding_box
+    /// Returns the box around the eight turned corners of the box of the object, None if the object is unbounded
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bbox: Aabb = self.object.bounding_box()?;
+        let mut min: Vector3 = Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max: Vector3 = Vector3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for corner in 0..8 {
+            let point: Vector3 = self.to_world(Vector3::new(
+                if corner & 1 == 0 { bbox.min.x } else { bbox.max.x },
+                if corner & 2 == 0 { bbox.min.y } else { bbox.max.y },
+                if corner & 4 == 0 { bbox.min.z } else { bbox.max.z }));
+            for axis in 0..3 {
+                min[axis] = min[axis].min(point[axis]);
+                max[axis] = max[axis].max(point[axis]);
+            }
+        }
+        Some(Aabb::new(min, max))
+    }
+
+    /// ## hash_content
+    /// Adds the angle and the object to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("RotateY");
+        hasher.write_f32(self.angle_degrees);
+        self.object.hash_content(hasher);
+    }
+
+    /// ## tessellate
+    /// Returns the triangles of the object turned around the y axis
+    fn tessellate(&self, subdivisions: usize) -> Option<Vec<Triangle>> {
+        Some(self.object.tessellate(subdivisions)?.into_iter()
+            .map(|triangle| Triangle { a: self.to_world(triangle.a), b: self.to_world(triangle.b), c: self.to_world(triangle.c), ..triangle })
+            .collect())
+    }
+}
+
+/// Tests for Translate and RotateY structs
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::objects::AABox;
+
+    #[test]
+    fn translate_sphere_hit() {
+        let sphere = Translate::new(Box::new(Sphere::new(Vector3::new(0.0, 0.0, 0.0), 0.5)), Vector3::new(1.0, 2.0, -3.0));
+        let ray = Ray::new(Vector3::new(1.0, 2.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit_rec = sphere.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        assert_eq!(hit_rec.p, Vector3::new(1.0, 2.0, -2.5));
+        assert_eq!(hit_rec.normal, Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(hit_rec.t, 2.5);
+        assert!(sphere.hit_record(&Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0)), 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn translate_bounding_box() {
+        let sphere = Translate::new(Box::new(Sphere::new(Vector3::new(0.0, 0.0, 0.0), 0.5)), Vector3::new(1.0, 2.0, -3.0));
+        let bbox = sphere.bounding_box().unwrap();
+
+        assert_eq!(bbox.min, Vector3::new(0.5, 1.5, -3.5));
+        assert_eq!(bbox.max, Vector3::new(1.5, 2.5, -2.5));
+    }
+
+    #[test]
+    fn rotate_y_quarter_turn_hit() {
+        // Turning a quarter counterclockwise moves the sphere from +x to -z
+        let sphere = RotateY::new(Box::new(Sphere::new(Vector3::new(2.0, 0.0, 0.0), 0.5)), 90.0);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit_rec = sphere.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        assert!((hit_rec.p - Vector3::new(0.0, 0.0, -1.5)).normal() < 1e-5);
+        assert!((hit_rec.normal - Vector3::new(0.0, 0.0, 1.0)).normal() < 1e-5);
+        assert!(hit_rec.front_face);
+        assert!(sphere.hit_record(&Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0)), 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn rotate_y_normal_is_turned() {
+        // A box far from the axis, where translating the normal along with the point would break it
+        let aabox = RotateY::new(Box::new(AABox::new(Vector3::new(4.0, -1.0, -1.0), Vector3::new(6.0, 1.0, 1.0))), 45.0);
+        let center = Vector3::new(5.0, 0.0, -5.0) / 2.0_f32.sqrt();
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), center);
+        let hit_rec = aabox.hit_record(&ray, 0.001, f32::MAX).unwrap();
+
+        assert!((hit_rec.normal.normal() - 1.0).abs() < 1e-5);
+        assert!((hit_rec.normal - Vector3::new(-1.0, 0.0, 1.0).unit_vec()).normal() < 1e-5);
+        assert!((hit_rec.p - center.unit_vec() * 4.0).normal() < 1e-4);
+    }
+
+    #[test]
+    fn rotate_y_bounding_box() {
+        let aabox = RotateY::new(Box::new(AABox::new(Vector3::new(1.0, -1.0, -1.0), Vector3::new(3.0, 2.0, 1.0))), 90.0);
+        let bbox = aabox.bounding_box().unwrap();
+
+        assert!((bbox.min - Vector3::new(-1.0, -1.0, -3.0)).normal() < 1e-5);
+        assert!((bbox.max - Vector3::new(1.0, 2.0, -1.0)).normal() < 1e-5);
+    }
+
+    #[test]
+    fn rotate_y_tessellate() {
+        let turned = RotateY::new(Box::new(Translate::new(Box::new(Sphere::new(Vector3::new(0.0, 0.0, 0.0), 1.0)), Vector3::new(2.0, 0.0, 0.0))), 90.0);
+        let triangles = turned.tessellate(4).unwrap();
+
+        assert_eq!(triangles.len(), Sphere::new(Vector3::new(0.0, 0.0, 0.0), 1.0).tessellate(4).unwrap().len());
+        for triangle in triangles {
+            assert!(((triangle.a - Vector3::new(0.0, 0.0, -2.0)).normal() - 1.0).abs() < 1e-4);
+        }
+    }
+}
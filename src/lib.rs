@@ -0,0 +1,19 @@
+//! A simple raytracer rendering scenes of spheres, triangles and meshes.
+//!
+//! Build a [`hitables::scene::Scene`], look at it with a [`camera::Camera`] and render it with a
+//! [`render::Renderer`], either into memory or straight to a PPM image.
+
+pub mod vector;
+pub mod ray;
+pub mod hitables;
+pub mod camera;
+pub mod render;
+pub mod background;
+pub mod texture;
+pub mod perlin;
+pub mod spectrum;
+pub mod content_hash;
+pub mod output;
+pub mod postprocess;
+pub mod sampling;
+pub mod cli;
@@ -0,0 +1,149 @@
+use crate::{vector::*, render::{Framebuffer, ppm_pixel_with_gamma}};
+
+use std::io::{self, Write};
+use std::path::Path;
+
+/// ## write_exr
+/// Writes the linear colors of a Framebuffer to an OpenEXR file at path as 32 bit floats.
+/// No gamma correction or clamping is done, so values above 1.0 are kept exactly.
+pub fn write_exr<P: AsRef<Path>>(path: P, framebuffer: &Framebuffer) -> io::Result<()> {
+    exr::prelude::write_rgb_file(path, framebuffer.width, framebuffer.height, |x, y| {
+        let color: Color = framebuffer.pixels[y * framebuffer.width + x];
+        (color.x, color.y, color.z)
+    }).map_err(|error| io::Error::other(error.to_string()))
+}
+
+/// ## PpmFormat
+/// The two encodings of a PPM image: ascii "r g b" lines after a P3 header,
+/// or raw bytes, three per pixel, after a P6 header
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum PpmFormat {
+    #[default]
+    Ascii,
+    Binary,
+}
+
+impl PpmFormat {
+    /// ## magic
+    /// Returns the magic number starting a PPM image in this format
+    pub fn magic(&self) -> &'static str {
+        match self {
+            PpmFormat::Ascii => "P3",
+            PpmFormat::Binary => "P6",
+        }
+    }
+}
+
+/// ## write_ppm
+/// Writes width x height colors, row by row starting with the top row, to writer as an ascii PPM image.
+/// The colors are written as they are, so they should already be gamma corrected like the ones
+/// from Renderer::render_corrected. Every pixel is written as soon as it's formatted.
+/// Returns an InvalidInput error if the number of pixels doesn't match the dimensions.
+pub fn write_ppm<W: Write>(writer: &mut W, pixels: &[Color], width: usize, height: usize) -> io::Result<()> {
+    write_ppm_as(writer, pixels, width, height, PpmFormat::Ascii)
+}
+
+/// ## write_ppm_as
+/// Writes the colors to writer like write_ppm, in the given PpmFormat. The binary format writes
+/// exactly three bytes per pixel after the header, with the channels clamped to 0..=255.
+pub fn write_ppm_as<W: Write>(writer: &mut W, pixels: &[Color], width: usize, height: usize, format: PpmFormat) -> io::Result<()> {
+    if pixels.len() != width * height {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+            format!("{} pixels don't make a {}x{} image", pixels.len(), width, height)));
+    }
+    write!(writer, "{}\n{} {}\n255\n", format.magic(), width, height)?;
+    for color in pixels {
+        match format {
+            PpmFormat::Ascii => writeln!(writer, "{}", ppm_pixel_with_gamma(*color, None))?,
+            PpmFormat::Binary => writer.write_all(&ppm_bytes(*color))?,
+        }
+    }
+    Ok(())
+}
+
+/// ## ppm_bytes
+/// Returns the channels of a Color as the bytes of a binary PPM image, clamped to 0..=255
+fn ppm_bytes(color: Color) -> [u8; 3] {
+    [quantize(color.x), quantize(color.y), quantize(color.z)]
+}
+
+/// Tests for image output
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exr::prelude::*;
+
+    #[test]
+    fn write_ppm_to_memory() {
+        let pixels = vec![Color::new(1.0, 0.5, 0.0), Color::new(0.0, 0.0, 2.0), Color::new(0.25, -1.0, 1.0)];
+        let mut buffer: Vec<u8> = Vec::new();
+        write_ppm(&mut buffer, &pixels, 3, 1).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+
+        assert!(text.starts_with("P3\n3 1\n255\n"));
+        assert_eq!(text, "P3\n3 1\n255\n255 127 0\n0 0 255\n63 0 255\n");
+    }
+
+    #[test]
+    fn write_ppm_errors() {
+        struct Failing;
+        impl Write for Failing {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("closed"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let pixels = vec![Color::new(0.0, 0.0, 0.0); 4];
+        assert_eq!(write_ppm(&mut Failing, &pixels, 2, 2).unwrap_err().to_string(), "closed");
+        assert_eq!(write_ppm(&mut Vec::new(), &pixels, 3, 2).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn write_ppm_binary() {
+        let pixels = vec![Color::new(0.5, 2.0, -1.0); 16];
+        let mut buffer: Vec<u8> = Vec::new();
+        write_ppm_as(&mut buffer, &pixels, 4, 4, PpmFormat::Binary).unwrap();
+
+        let header = b"P6\n4 4\n255\n";
+        assert_eq!(&buffer[..header.len()], header);
+        let body = &buffer[header.len()..];
+        assert_eq!(body.len(), 4 * 4 * 3);
+        for pixel in body.chunks(3) {
+            assert_eq!(pixel, [127, 255, 0]);
+        }
+    }
+
+    #[test]
+    fn write_ppm_as_ascii_matches_write_ppm() {
+        let pixels = vec![Color::new(0.1, 0.6, 0.9), Color::new(1.0, 0.0, 0.3)];
+        let mut ascii: Vec<u8> = Vec::new();
+        let mut plain: Vec<u8> = Vec::new();
+        write_ppm_as(&mut ascii, &pixels, 1, 2, PpmFormat::Ascii).unwrap();
+        write_ppm(&mut plain, &pixels, 1, 2).unwrap();
+        assert_eq!(ascii, plain);
+    }
+
+    #[test]
+    fn write_exr_round_trip() {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        framebuffer.pixels[4] = Color::new(3.5, 0.1, 12.0);
+        framebuffer.pixels[0] = Color::new(-0.25, 1.0e6, 0.0);
+        let path = std::env::temp_dir().join(format!("write_exr_round_trip_{}.exr", std::process::id()));
+        write_exr(&path, &framebuffer).unwrap();
+
+        let image = read_first_rgba_layer_from_file(
+            &path,
+            |resolution, _| Framebuffer::new(resolution.width(), resolution.height()),
+            |buffer: &mut Framebuffer, position, (r, g, b, _a): (f32, f32, f32, f32)| {
+                let width = buffer.width;
+                buffer.pixels[position.y() * width + position.x()] = Color::new(r, g, b);
+            },
+        ).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(image.layer_data.channel_data.pixels, framebuffer);
+    }
+}
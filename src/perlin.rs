@@ -0,0 +1,130 @@
+use crate::vector::*;
+
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+/// ## POINT_COUNT
+/// Number of random gradients, the noise repeats itself every POINT_COUNT units along each axis
+const POINT_COUNT: usize = 256;
+
+/// ## Perlin
+/// Perlin noise: a smooth random value for every point in space, made by interpolating random gradients
+/// placed at the corners of a grid of unit cubes. The same seed always gives the same noise.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Perlin {
+    seed: u64,
+    gradients: Vec<Vector3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    /// ## new
+    /// Returns Perlin noise made from random gradients and permutations picked with the given seed
+    pub fn new(seed: u64) -> Perlin {
+        let mut rng: StdRng = StdRng::seed_from_u64(seed);
+        let gradients: Vec<Vector3> = (0..POINT_COUNT).map(|_| Vector3::random_unit_vector_with(&mut rng)).collect();
+        let mut permutation = || {
+            let mut perm: Vec<usize> = (0..POINT_COUNT).collect();
+            perm.shuffle(&mut rng);
+            perm
+        };
+        let perm_x: Vec<usize> = permutation();
+        let perm_y: Vec<usize> = permutation();
+        let perm_z: Vec<usize> = permutation();
+        Perlin {
+            seed,
+            gradients,
+            perm_x,
+            perm_y,
+            perm_z,
+        }
+    }
+
+    /// ## seed
+    /// Returns the seed the noise was made with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// ## noise
+    /// Returns the noise at p, between -1 and 1. The gradients of the eight corners of the cube
+    /// around p are interpolated with a smoothstep, so the noise has no seams between cubes.
+    pub fn noise(&self, p: Vector3) -> f32 {
+        let (u, v, w): (f32, f32, f32) = (p.x - p.x.floor(), p.y - p.y.floor(), p.z - p.z.floor());
+        let (i, j, k): (i64, i64, i64) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+        let uu: f32 = u*u*(3.0 - 2.0*u);
+        let vv: f32 = v*v*(3.0 - 2.0*v);
+        let ww: f32 = w*w*(3.0 - 2.0*w);
+
+        let mut accum: f32 = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let index: usize = self.perm_x[((i + di) & 255) as usize]
+                        ^ self.perm_y[((j + dj) & 255) as usize]
+                        ^ self.perm_z[((k + dk) & 255) as usize];
+                    let (fi, fj, fk): (f32, f32, f32) = (di as f32, dj as f32, dk as f32);
+                    let weight: Vector3 = Vector3::new(u - fi, v - fj, w - fk);
+                    accum += (fi*uu + (1.0-fi)*(1.0-uu))
+                        * (fj*vv + (1.0-fj)*(1.0-vv))
+                        * (fk*ww + (1.0-fk)*(1.0-ww))
+                        * self.gradients[index].dot(weight);
+                }
+            }
+        }
+        accum
+    }
+
+    /// ## turbulence
+    /// Returns the absolute value of depth layers of noise added together,
+    /// each at twice the frequency and half the weight of the one before
+    pub fn turbulence(&self, p: Vector3, depth: usize) -> f32 {
+        let mut accum: f32 = 0.0;
+        let mut point: Vector3 = p;
+        let mut weight: f32 = 1.0;
+        for _layer in 0..depth {
+            accum += weight * self.noise(point);
+            weight *= 0.5;
+            point *= 2.0;
+        }
+        accum.abs()
+    }
+}
+
+/// Tests for Perlin struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perlin_deterministic() {
+        let a = Perlin::new(7);
+        let p = Vector3::new(1.3, -2.7, 0.45);
+
+        assert_eq!(a.noise(p), a.noise(p));
+        assert_eq!(a.noise(p), Perlin::new(7).noise(p));
+        assert_ne!(a.noise(p), Perlin::new(8).noise(p));
+    }
+
+    #[test]
+    fn perlin_zero_at_grid_points() {
+        let perlin = Perlin::new(3);
+
+        assert_eq!(perlin.noise(Vector3::new(0.0, 0.0, 0.0)), 0.0);
+        assert_eq!(perlin.noise(Vector3::new(4.0, -2.0, 9.0)), 0.0);
+    }
+
+    #[test]
+    fn perlin_continuous() {
+        let perlin = Perlin::new(1);
+        let step = Vector3::new(1e-3, 1e-3, 1e-3);
+        // Walk across many cube boundaries in small steps, the noise never jumps
+        let mut p = Vector3::new(-3.0, -2.5, -1.7);
+        for _ in 0..5000 {
+            assert!((perlin.noise(p + step) - perlin.noise(p)).abs() < 0.01);
+            assert!(perlin.noise(p).abs() <= 1.0);
+            p += step;
+        }
+    }
+}
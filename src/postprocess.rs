@@ -0,0 +1,160 @@
+use crate::{vector::*, render::Framebuffer};
+
+/// ## Bloom
+/// Post process making bright parts of an image glow into their surroundings.
+/// The light above threshold luminance is blurred with a gaussian reaching radius pixels
+/// and added back to the linear image, before it is gamma corrected.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Bloom {
+    threshold: f32,
+    radius: usize,
+}
+
+impl Bloom {
+    /// ## new
+    /// Returns a Bloom with the given threshold and radius, or an error if the threshold is negative,
+    /// which would make black pixels glow with 0 / 0
+    pub fn new(threshold: f32, radius: usize) -> Result<Bloom, String> {
+        if threshold.is_nan() || threshold < 0.0 {
+            return Err(format!("Bloom threshold must not be negative, got {}", threshold));
+        }
+        Ok(Bloom {
+            threshold,
+            radius,
+        })
+    }
+
+    /// ## threshold
+    /// Returns the luminance above which pixels glow, which is never negative
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// ## radius
+    /// Returns how many pixels the glow reaches
+    pub fn radius(&self) -> usize {
+        self.radius
+    }
+
+    /// ## apply
+    /// Adds the glow of the pixels brighter than the threshold to the framebuffer
+    pub fn apply(&self, framebuffer: &mut Framebuffer) {
+        let bright: Vec<Color> = framebuffer.pixels.iter()
+            .map(|pixel| {
+                let luminance: f32 = pixel.luminance();
+                if luminance > self.threshold {
+                    *pixel * ((luminance - self.threshold) / luminance)
+                } else {
+                    Color::new(0.0, 0.0, 0.0)
+                }
+            })
+            .collect();
+
+        // The gaussian is separable, so blur the rows first and then the columns
+        let kernel: Vec<f32> = gaussian_kernel(self.radius);
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        let rows: Vec<Color> = blur(&bright, &kernel, width, height, 1, 0);
+        let glow: Vec<Color> = blur(&rows, &kernel, width, height, 0, 1);
+        for (pixel, glow) in framebuffer.pixels.iter_mut().zip(glow) {
+            *pixel += glow;
+        }
+    }
+}
+
+/// ## gaussian_kernel
+/// Returns the weights of a normalized gaussian from -radius to radius with sigma radius / 2
+fn gaussian_kernel(radius: usize) -> Vec<f32> {
+    let sigma: f32 = (radius as f32 / 2.0).max(0.5);
+    let weights: Vec<f32> = (0..=2 * radius)
+        .map(|i| {
+            let x: f32 = i as f32 - radius as f32;
+            (-x*x / (2.0 * sigma*sigma)).exp()
+        })
+        .collect();
+    let sum: f32 = weights.iter().sum();
+    weights.iter().map(|weight| weight / sum).collect()
+}
+
+/// ## blur
+/// Returns the pixels blurred with the kernel along the direction (dx, dy).
+/// Light that would be blurred outside of the image is lost.
+fn blur(pixels: &[Color], kernel: &[f32], width: usize, height: usize, dx: usize, dy: usize) -> Vec<Color> {
+    let radius: i64 = (kernel.len() / 2) as i64;
+    let mut blurred: Vec<Color> = vec![Color::new(0.0, 0.0, 0.0); pixels.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let pixel: Color = pixels[y * width + x];
+            if pixel == Color::new(0.0, 0.0, 0.0) {
+                continue;
+            }
+            // Spread the pixel out over its neighbours
+            for (i, weight) in kernel.iter().enumerate() {
+                let offset: i64 = i as i64 - radius;
+                let nx: i64 = x as i64 + offset * dx as i64;
+                let ny: i64 = y as i64 + offset * dy as i64;
+                if nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height {
+                    blurred[ny as usize * width + nx as usize] += pixel * *weight;
+                }
+            }
+        }
+    }
+    blurred
+}
+
+/// Tests for post processing
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a 9x9 Framebuffer where every pixel is the given gray
+    fn field(value: f32) -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(9, 9);
+        for pixel in framebuffer.pixels.iter_mut() {
+            *pixel = Color::new(value, value, value);
+        }
+        framebuffer
+    }
+
+    #[test]
+    fn bloom_spreads_bright_pixel() {
+        let mut framebuffer = field(0.01);
+        framebuffer.pixels[4 * 9 + 4] = Color::new(50.0, 50.0, 50.0);
+        Bloom::new(1.0, 2).unwrap().apply(&mut framebuffer);
+
+        for neighbour in [3 * 9 + 4, 5 * 9 + 4, 4 * 9 + 3, 4 * 9 + 5, 3 * 9 + 3] {
+            assert!(framebuffer.pixels[neighbour].x > 0.5);
+        }
+        // Far away from the bright pixel nothing changes
+        assert_eq!(framebuffer.pixels[0], Color::new(0.01, 0.01, 0.01));
+    }
+
+    #[test]
+    fn bloom_dim_field_unchanged() {
+        let mut framebuffer = field(0.5);
+        Bloom::new(1.0, 3).unwrap().apply(&mut framebuffer);
+
+        assert_eq!(framebuffer, field(0.5));
+    }
+
+    #[test]
+    fn bloom_invalid_threshold() {
+        assert!(Bloom::new(-0.5, 2).is_err());
+        assert!(Bloom::new(f32::NAN, 2).is_err());
+
+        // Black pixels stay black at the lowest threshold
+        let bloom = Bloom::new(0.0, 2).unwrap();
+        let mut framebuffer = field(0.0);
+        bloom.apply(&mut framebuffer);
+        assert_eq!(framebuffer, field(0.0));
+        assert_eq!((bloom.threshold(), bloom.radius()), (0.0, 2));
+    }
+
+    #[test]
+    fn gaussian_kernel_normalized() {
+        let kernel = gaussian_kernel(4);
+
+        assert_eq!(kernel.len(), 9);
+        assert!((kernel.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        assert!(kernel[4] > kernel[3] && kernel[3] > kernel[0]);
+    }
+}
@@ -1,21 +1,131 @@
-use crate::{vector::{Vector3, Color}, hitables::{HitRecord, Hitable, scene::Scene}};
+use crate::{vector::{Vector3, Color}, hitables::{HitRecord, Hitable, scene::Scene}, background::Background};
+use crate::hitables::materials::{Material, DEFAULT_MATERIAL};
+use crate::spectrum::at_wavelength;
+
+use rand::{Rng, RngCore};
+
+/// ## NORMAL_OFFSET
+/// Standard distance bounced rays are moved away from the surface they bounce on
+pub const NORMAL_OFFSET: f32 = 1e-4;
+
+/// ## SINGLE_BOUNCE_DEPTH
+/// Depth used in single bounce mode: the camera ray, the ray lighting the surface it hits directly
+/// and the ray lighting that surface after one more bounce
+pub const SINGLE_BOUNCE_DEPTH: usize = 3;
+
+/// ## TraceSettings
+/// Settings deciding how the path of a ray is followed through a scene.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct TraceSettings {
+    /// Color seen by rays that don't hit anything
+    pub background: Background,
+    /// Distance bounced rays are moved away from the surface to avoid hitting it again
+    pub normal_offset: f32,
+    /// Randomly end paths that carry little light, and brighten the paths that go on to make up for it
+    pub russian_roulette: bool,
+    /// Number of bounces that are always followed before Russian roulette can end a path
+    pub rr_min_bounces: usize,
+    /// Shade every surface with the gray default material instead of its own, to look at the shapes only
+    pub clay: bool,
+    /// Number of bounces on materials like glass that a path may make without using up its max depth.
+    /// Zero makes every bounce count.
+    pub free_bounces: usize,
+    /// Hard limit on the number of bounces of a path whatever the depth, so a broken material can't make it go on forever
+    pub bounce_limit: usize,
+    /// Follow paths for at most one indirect bounce whatever the max depth, a fast preview of the lighting
+    pub single_bounce: bool,
+    /// Make specular bounces after the first diffuse bounce of a path rougher, which removes fireflies
+    /// from caustics at the cost of blurring reflections seen in indirect light
+    pub regularize: bool,
+    /// Roughness specular materials get at least when regularized
+    pub regularization_roughness: f32,
+}
+
+impl Default for TraceSettings {
+    /// ## default
+    /// Returns the default background and normal offset, with Russian roulette and clay mode turned off
+    fn default() -> TraceSettings {
+        TraceSettings {
+            background: Background::default(),
+            normal_offset: NORMAL_OFFSET,
+            russian_roulette: false,
+            rr_min_bounces: 3,
+            clay: false,
+            free_bounces: 16,
+            bounce_limit: 1000,
+            single_bounce: false,
+            regularize: false,
+            regularization_roughness: 0.3,
+        }
+    }
+}
+
+/// ## TraceCounters
+/// Counts of what happened while tracing rays through a scene
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct TraceCounters {
+    /// Rays traced through the scene, the first ray as well as bounced rays
+    pub rays: u64,
+    /// Paths stopped for reaching the bounce limit
+    pub stopped_paths: u64,
+}
+
+/// ## PathState
+/// How far a path has come: the number of bounces made, how much of the light found further
+/// along it reaches the camera, how many free bounces it has left and whether it has bounced diffusely.
+#[derive(Debug, Clone, Copy)]
+struct PathState {
+    bounces: usize,
+    throughput: Color,
+    free_bounces: usize,
+    diffuse_bounced: bool,
+}
+
+impl PathState {
+    /// ## start
+    /// Returns the state of a path that hasn't bounced yet
+    fn start(settings: &TraceSettings) -> PathState {
+        PathState {
+            bounces: 0,
+            throughput: Color::new(1.0, 1.0, 1.0),
+            free_bounces: settings.free_bounces,
+            diffuse_bounced: false,
+        }
+    }
+}
 
 /// ## Ray
 /// Representation of a ray on the form p(t) = A + tB.
 /// Where A and B are 3-vectors and t is a real number (represented with f32)
+/// In spectral mode the ray carries a single wavelength of light in nanometers.
+/// time is when the ray was sent off, which decides where moving objects are when it hits them.
+/// random is a number in 0..1 drawn for the ray from the generator of the render, which media use
+/// to pick where the ray scatters. It is 0.5 for rays made without a generator.
 #[derive(PartialEq, Debug)]
 pub struct Ray {
     pub origin: Vector3,
     pub direction: Vector3,
+    pub wavelength: Option<f32>,
+    pub time: f32,
+    pub random: f32,
 }
 
 impl Ray {
     //7 ## new
     /// Returns a Ray with origin and direction given as arguments
     pub fn new(origin: Vector3, direction: Vector3) -> Ray {
-        Ray { 
-            origin: origin,
-            direction: direction
+        Ray::new_at(origin, direction, 0.0)
+    }
+
+    /// ## new_at
+    /// Returns a Ray with origin and direction sent off at the given time
+    pub fn new_at(origin: Vector3, direction: Vector3, time: f32) -> Ray {
+        Ray {
+            origin,
+            direction,
+            wavelength: None,
+            time,
+            random: 0.5,
         }
     }
 
@@ -26,33 +136,229 @@ impl Ray {
         self.origin + self.direction * t
     }
 
+    /// ## spawn
+    /// Returns a Ray leaving the surface point p in the given direction.
+    /// The origin is moved offset along the normal to the side the ray leaves through,
+    /// so rounding errors in p can't make the ray hit the surface it starts on.
+    pub fn spawn(p: Vector3, normal: Vector3, direction: Vector3, offset: f32) -> Ray {
+        if direction.dot(normal) >= 0.0 {
+            Ray::new(p + normal * offset, direction)
+        } else {
+            Ray::new(p - normal * offset, direction)
+        }
+    }
+
     /// ## color
-    /// Returns a Color (Vector3 type) depending on if the ray hits and how it bounces.. 
-    pub fn color(ray: &Ray, scene: &Scene, depth: usize) -> Color {
+    /// Returns a Color (Vector3 type) depending on if the ray hits and how it bounces..
+    /// Rays that don't hit anything get their color from the background.
+    /// Surfaces add the light they emit to the light they scatter, lights stop the ray and only emit.
+    /// Bounced rays start normal_offset away from the surface they bounced on.
+    /// A ray with a wavelength sees every color as the gray of its spectral value at that wavelength.
+    /// The random choices are made with the thread's own generator, so the color isn't repeatable.
+    /// Use color_counted with a seeded generator for that.
+    pub fn color(ray: &Ray, scene: &Scene, settings: &TraceSettings, depth: usize) -> Color {
+        let mut counters: TraceCounters = TraceCounters::default();
+        Ray::color_counted(ray, scene, settings, depth, &mut counters, &mut rand::thread_rng())
+    }
+
+    /// ## color_counted
+    /// Returns the same as color and adds the number of rays traced through the scene,
+    /// and whether the path was stopped by the bounce limit, to counters.
+    /// Every random choice along the path is made with rng, so a seeded rng gives the same color every time.
+    /// In single bounce mode the depth is cut down to SINGLE_BOUNCE_DEPTH.
+    pub fn color_counted(ray: &Ray, scene: &Scene, settings: &TraceSettings, depth: usize, counters: &mut TraceCounters, rng: &mut dyn RngCore) -> Color {
+        let depth: usize = if settings.single_bounce { depth.min(SINGLE_BOUNCE_DEPTH) } else { depth };
+        Ray::color_from(ray, scene, None, settings, depth, PathState::start(settings), counters, rng)
+    }
+
+    /// ## color_counted_among
+    /// Returns the same as color_counted when the ray can only hit the objects at the given indices
+    /// of the scene. Only the ray itself is limited to them, bounced rays may hit any object.
+    pub fn color_counted_among(ray: &Ray, scene: &Scene, candidates: &[usize], settings: &TraceSettings, depth: usize, counters: &mut TraceCounters, rng: &mut dyn RngCore) -> Color {
+        let depth: usize = if settings.single_bounce { depth.min(SINGLE_BOUNCE_DEPTH) } else { depth };
+        Ray::color_from(ray, scene, Some(candidates), settings, depth, PathState::start(settings), counters, rng)
+    }
+
+    /// ## color_from
+    /// Returns the color of a ray that has already come some way along a path.
+    /// Used by color, and keeps track of the path for Russian roulette and free bounces.
+    /// Follows the path bounce by bounce in a loop, adding the light emitted and finally the background
+    /// seen along it, each weighted by the throughput of the path up to there, so even a very large
+    /// depth can't overflow the stack.
+    /// Bounces on materials that don't count towards depth are free while the path has free bounces left.
+    /// With regularization on, specular bounces after a diffuse one are made rougher.
+    /// Paths reaching the bounce limit are stopped no matter how much depth they have left.
+    /// If candidates are given the ray is only tested against the objects at those indices.
+    #[allow(clippy::too_many_arguments)]
+    fn color_from(ray: &Ray, scene: &Scene, candidates: Option<&[usize]>, settings: &TraceSettings, depth: usize, path: PathState, counters: &mut TraceCounters, rng: &mut dyn RngCore) -> Color {
         let mut hit_rec: HitRecord = HitRecord::new();
-        if depth == 0 {return Vector3::new(0.0, 0.0, 0.0);}
-        if scene.hit(ray, 0.001, f32::MAX, &mut hit_rec) {
-            let target: Vector3 = hit_rec.p + hit_rec.normal + Vector3::random_in_unit();
-            Ray::color(&Ray::new(hit_rec.p, target - hit_rec.p),  scene, depth-1) * 0.5
+        let mut color: Color = Color::new(0.0, 0.0, 0.0);
+        let mut current: Ray = Ray { origin: ray.origin, direction: ray.direction, wavelength: ray.wavelength, time: ray.time, random: ray.random };
+        let mut candidates: Option<&[usize]> = candidates;
+        let mut depth: usize = depth;
+        let mut path: PathState = path;
+        // Rays traced for this path, checked against the bounce limit independently of path.bounces
+        let first_bounce: usize = path.bounces;
+        let mut traced: usize = 0;
+        loop {
+            if depth == 0 {return color;}
+            if path.bounces >= settings.bounce_limit {
+                counters.stopped_paths += 1;
+                return color;
+            }
+            counters.rays += 1;
+            traced += 1;
+            debug_assert!(first_bounce + traced <= settings.bounce_limit,
+                          "Path traced {} rays from bounce {}, past the bounce limit of {}", traced, first_bounce, settings.bounce_limit);
+            let hit: bool = match candidates {
+                Some(candidates) => scene.hit_among(candidates, &current, 0.001, f32::MAX, &mut hit_rec).is_some(),
+                None => scene.hit(&current, 0.001, f32::MAX, &mut hit_rec),
+            };
+            if !hit {
+                return color + path.throughput.entrywise(at_wavelength(settings.background.value(current.direction), current.wavelength));
+            }
+
+            let material: &dyn Material = Ray::material(&hit_rec, settings);
+            color += path.throughput.entrywise(at_wavelength(material.emitted(), current.wavelength));
+            let (mut attenuation, scattered): (Color, Ray) = match Ray::scatter(&current, &hit_rec, settings, &path, rng) {
+                Some(scatter) => scatter,
+                None => return color,
+            };
+            if settings.russian_roulette {
+                let survival: f32 = survival_probability(path.throughput.entrywise(attenuation), path.bounces, settings.rr_min_bounces);
+                if survival < 1.0 {
+                    if rng.gen_range(0.0..1.0) >= survival {
+                        return color;
+                    }
+                    attenuation /= survival;
+                }
+            }
+            let free: bool = path.free_bounces > 0 && !material.counts_towards_depth();
+            path = PathState {
+                bounces: path.bounces + 1,
+                throughput: path.throughput.entrywise(attenuation),
+                free_bounces: if free { path.free_bounces - 1 } else { path.free_bounces },
+                diffuse_bounced: path.diffuse_bounced || !material.is_specular(),
+            };
+            if !free {
+                depth -= 1;
+            }
+            current = scattered;
+            candidates = None;
+        }
+    }
+
+    /// ## color_mirror
+    /// Debug integrator treating every surface as a perfect mirror, to check reflections and normals
+    /// without any materials involved. Returns the background seen by the ray when it escapes,
+    /// or black if it is still bouncing after max_depth rays.
+    pub fn color_mirror(ray: &Ray, scene: &Scene, settings: &TraceSettings, max_depth: usize) -> Color {
+        let mut hit_rec: HitRecord = HitRecord::new();
+        let mut current: Ray = Ray::new_at(ray.origin, ray.direction, ray.time);
+        for _depth in 0..max_depth {
+            if !scene.hit(&current, 0.001, f32::MAX, &mut hit_rec) {
+                return at_wavelength(settings.background.value(current.direction), ray.wavelength);
+            }
+            let direction: Vector3 = match current.direction.try_unit_vec() {
+                Some(unit_dir) => unit_dir.reflect(hit_rec.normal),
+                None => break,
+            };
+            current = Ray::spawn(hit_rec.p, hit_rec.normal, direction, settings.normal_offset);
+            current.time = ray.time;
+        }
+        Vector3::new(0.0, 0.0, 0.0)
+    }
+
+    /// ## trace_path
+    /// Follows a ray through the scene the same way color does and returns the points it hits in order.
+    /// If the ray escapes before max_depth bounces, the direction it escapes in is added last.
+    /// Like color it isn't repeatable, use trace_path_with and a seeded generator for that.
+    pub fn trace_path(ray: &Ray, scene: &Scene, max_depth: usize) -> Vec<Vector3> {
+        Ray::trace_path_with(ray, scene, max_depth, &mut rand::thread_rng())
+    }
+
+    /// ## trace_path_with
+    /// Returns the same as trace_path with every random choice made with rng
+    pub fn trace_path_with(ray: &Ray, scene: &Scene, max_depth: usize, rng: &mut dyn RngCore) -> Vec<Vector3> {
+        let mut path: Vec<Vector3> = Vec::new();
+        let mut hit_rec: HitRecord = HitRecord::new();
+        let settings: TraceSettings = TraceSettings::default();
+        let mut current: Ray = Ray::new_at(ray.origin, ray.direction, ray.time);
+        for _depth in 0..max_depth {
+            if !scene.hit(&current, 0.001, f32::MAX, &mut hit_rec) {
+                path.push(current.direction);
+                break;
+            }
+            path.push(hit_rec.p);
+            match Ray::scatter(&current, &hit_rec, &settings, &PathState::start(&settings), rng) {
+                Some((_, scattered)) => current = scattered,
+                None => break,
+            }
+        }
+        path
+    }
+
+    /// ## material
+    /// Returns the material used for a hit, the default material in clay mode or if the object has none
+    fn material<'a>(hit_rec: &HitRecord<'a>, settings: &TraceSettings) -> &'a dyn Material {
+        if settings.clay {
+            &*DEFAULT_MATERIAL
         } else {
-            let unit_dir: Vector3 = ray.direction.unit_vec();
-            let t: f32 = 0.5*(unit_dir.y + 1.0);
-            Vector3::new(1.0, 1.0, 1.0) * (1.0-t) + Vector3::new(0.5, 0.7, 1.0) * t
+            hit_rec.material.unwrap_or(&*DEFAULT_MATERIAL)
+        }
+    }
 
+    /// ## scatter
+    /// Scatters a ray off the material of a hit. Returns the attenuation and the scattered ray,
+    /// which keeps the wavelength and time of the incoming ray and gets a new random number from rng,
+    /// or None if the ray is absorbed.
+    /// The material is regularized if it is on and the path has bounced diffusely before.
+    fn scatter(ray: &Ray, hit_rec: &HitRecord, settings: &TraceSettings, path: &PathState, rng: &mut dyn RngCore) -> Option<(Color, Ray)> {
+        let material: &dyn Material = Ray::material(hit_rec, settings);
+        let mut attenuation: Color = Color::new(0.0, 0.0, 0.0);
+        let mut scattered: Ray = Ray::new(hit_rec.p, hit_rec.normal);
+        let scatters: bool = if settings.regularize && path.diffuse_bounced {
+            material.scatter_regularized(ray, hit_rec, settings.regularization_roughness, &mut attenuation, &mut scattered, rng)
+        } else {
+            material.scatter(ray, hit_rec, &mut attenuation, &mut scattered, rng)
+        };
+        if !scatters {
+            return None;
         }
+        let mut spawned: Ray = Ray::spawn(scattered.origin, hit_rec.normal, scattered.direction, settings.normal_offset);
+        spawned.wavelength = ray.wavelength;
+        spawned.time = ray.time;
+        spawned.random = rng.gen_range(0.0..1.0);
+        Some((at_wavelength(attenuation, ray.wavelength), spawned))
     }
 }
 
+/// ## survival_probability
+/// Returns the probability that Russian roulette lets a path go on after the given number of bounces.
+/// Paths always go on before min_bounces, after that the probability is the brightest channel of
+/// the throughput, so paths carrying little light are likely to end.
+pub fn survival_probability(throughput: Color, bounces: usize, min_bounces: usize) -> f32 {
+    if bounces < min_bounces {
+        return 1.0;
+    }
+    throughput.x.max(throughput.y).max(throughput.z).clamp(0.0, 1.0)
+}
+
 /// Tests for Ray struct
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+    use crate::hitables::objects::Sphere;
 
     #[test]
     fn ray_new() {
         let a: Ray = Ray {
             origin: Vector3::new(1.0, 0.0, 0.0),
             direction: Vector3::new(-1.0, -1.0, 0.0),
+            wavelength: None,
+            time: 0.0,
+            random: 0.5,
         };
         let b: Ray = Ray::new(
             Vector3::new(1.0, 0.0, 0.0),
@@ -70,4 +376,412 @@ mod tests {
 
         assert_eq!(a, b);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn ray_spawn_side() {
+        let p = Vector3::new(0.0, 0.0, 0.0);
+        let normal = Vector3::new(0.0, 1.0, 0.0);
+
+        let out = Ray::spawn(p, normal, Vector3::new(1.0, 1.0, 0.0), 0.5);
+        let inward = Ray::spawn(p, normal, Vector3::new(1.0, -1.0, 0.0), 0.5);
+
+        assert_eq!(out.origin, Vector3::new(0.0, 0.5, 0.0));
+        assert_eq!(inward.origin, Vector3::new(0.0, -0.5, 0.0));
+    }
+
+    #[test]
+    fn ray_spawn_avoids_self_hit() {
+        // A huge sphere is flat enough locally, and large enough for rounding errors in the hit point
+        let ground = Sphere::new(Vector3::new(0.0, -1.0e4, 0.0), 1.0e4);
+        let incoming = Ray::new(Vector3::new(-4.63, 1.0, 0.3), Vector3::new(1.0, -0.1, 0.05));
+        let mut hit_rec = HitRecord::new();
+        assert!(ground.hit(&incoming, 0.001, f32::MAX, &mut hit_rec));
+
+        let grazing = Vector3::new(1.0, 0.001, 0.0);
+        let bare = Ray::new(hit_rec.p, grazing);
+        let offset = Ray::spawn(hit_rec.p, hit_rec.normal, grazing, 0.01);
+
+        let mut bounce_rec = HitRecord::new();
+        assert!(ground.hit(&bare, 0.001, f32::MAX, &mut bounce_rec));
+        assert!(!ground.hit(&offset, 0.001, f32::MAX, &mut bounce_rec));
+    }
+
+    #[test]
+    fn ray_survival_probability_min_bounces() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        for bounces in 0..20 {
+            assert_eq!(survival_probability(black, bounces, 20), 1.0);
+        }
+        assert_eq!(survival_probability(black, 20, 20), 0.0);
+        assert_eq!(survival_probability(Color::new(0.1, 0.4, 0.2), 20, 20), 0.4);
+        assert_eq!(survival_probability(Color::new(3.0, 0.4, 0.2), 20, 20), 1.0);
+    }
+
+    #[test]
+    fn ray_color_russian_roulette_unbiased() {
+        // A gray sphere under a white sky: roulette adds noise but shouldn't change the average
+        let scene = Scene::new();
+        let plain = TraceSettings::default();
+        let roulette = TraceSettings {
+            russian_roulette: true,
+            rr_min_bounces: 0,
+            ..TraceSettings::default()
+        };
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let samples = 20000;
+        let mut rng = StdRng::seed_from_u64(472);
+        let mut counters = TraceCounters::default();
+        let mut plain_sum = Color::new(0.0, 0.0, 0.0);
+        let mut roulette_sum = Color::new(0.0, 0.0, 0.0);
+        for _ in 0..samples {
+            plain_sum += Ray::color_counted(&ray, &scene, &plain, 50, &mut counters, &mut rng);
+            roulette_sum += Ray::color_counted(&ray, &scene, &roulette, 50, &mut counters, &mut rng);
+        }
+
+        // The difference is at most 0.016 for seeds 0 to 39
+        assert!(((plain_sum - roulette_sum) / samples as f32).normal() < 0.02);
+    }
+
+    #[test]
+    fn ray_color_russian_roulette_black_terminates() {
+        use crate::hitables::materials::Lambertian;
+
+        // Light never comes back from a black ball, so roulette ends the path at the first hit
+        let scene = Scene::from_objects(vec![
+            Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, -1.0), 0.5, Box::new(Lambertian::new(Color::new(0.0, 0.0, 0.0))))),
+            Box::new(Sphere::new(Vector3::new(0.0, -100.5, -1.0), 100.0)),
+        ]);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let plain = TraceSettings::default();
+        let roulette = TraceSettings { russian_roulette: true, rr_min_bounces: 0, ..TraceSettings::default() };
+
+        for _ in 0..100 {
+            let mut counters = TraceCounters::default();
+            assert_eq!(Ray::color_counted(&ray, &scene, &roulette, 50, &mut counters, &mut rand::thread_rng()), Color::new(0.0, 0.0, 0.0));
+            assert_eq!(counters.rays, 1);
+        }
+        let mut counters = TraceCounters::default();
+        for _ in 0..100 {
+            Ray::color_counted(&ray, &scene, &plain, 50, &mut counters, &mut rand::thread_rng());
+        }
+        assert!(counters.rays > 100);
+    }
+
+    #[test]
+    fn ray_color_material_albedo() {
+        use crate::hitables::materials::Lambertian;
+
+        let ball = |albedo: Color| Scene::from_objects(vec![Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, -1.0), 0.5, Box::new(Lambertian::new(albedo))))]);
+        let red = ball(Color::new(0.9, 0.1, 0.1));
+        let blue = ball(Color::new(0.1, 0.1, 0.9));
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let settings = TraceSettings::default();
+
+        // The camera ray hits the front of the ball, which bounces straight out to the sky
+        let red_color = Ray::color(&ray, &red, &settings, 2);
+        let blue_color = Ray::color(&ray, &blue, &settings, 2);
+        assert!(red_color.x > red_color.z);
+        assert!(blue_color.z > blue_color.x);
+    }
+
+    #[test]
+    fn ray_color_glass_over_ground() {
+        use crate::hitables::materials::Dielectric;
+
+        let scene = |material: Box<dyn Material>| Scene::from_objects(vec![
+            Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, -1.0), 0.5, material)),
+            Box::new(Sphere::new(Vector3::new(0.0, -100.5, -1.0), 100.0)),
+        ]);
+        let glass = scene(Box::new(Dielectric::new(1.5)));
+        let gray = scene(Box::new(crate::hitables::materials::Lambertian::new(crate::hitables::materials::DEFAULT_ALBEDO)));
+        // Straight through the middle of the ball the light isn't bent, so the sky behind it shows
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let settings = TraceSettings::default();
+        let average = |scene: &Scene| {
+            let samples = 2000;
+            let mut sum = Color::new(0.0, 0.0, 0.0);
+            let mut rng = StdRng::seed_from_u64(499);
+            for _ in 0..samples {
+                sum += Ray::color_counted(&ray, scene, &settings, 50, &mut TraceCounters::default(), &mut rng);
+            }
+            sum / samples as f32
+        };
+        let sky = settings.background.value(ray.direction);
+
+        assert!((average(&glass) - sky).normal() < 0.1);
+        assert!((average(&gray) - sky).normal() > 0.2);
+    }
+
+    #[test]
+    fn ray_scatter_regularized_after_diffuse() {
+        use crate::hitables::materials::Metal;
+
+        let mirror = Sphere::with_material(Vector3::new(0.0, -1.0, 0.0), 1.0, Box::new(Metal::new(Color::new(1.0, 1.0, 1.0), 0.0)));
+        let ray = Ray::new(Vector3::new(-1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        let mut hit_rec = HitRecord::new();
+        assert!(mirror.hit(&ray, 0.001, f32::MAX, &mut hit_rec));
+        let reflected = Vector3::new(1.0, 1.0, 0.0).unit_vec();
+        // Largest angle, as 1 - cos, between the reflection and the mirror direction
+        let spread = |settings: &TraceSettings, path: &PathState| {
+            let mut rng = StdRng::seed_from_u64(503);
+            (0..500)
+                .filter_map(|_| Ray::scatter(&ray, &hit_rec, settings, path, &mut rng))
+                .map(|(_, scattered)| 1.0 - scattered.direction.unit_vec().dot(reflected))
+                .fold(0.0_f32, f32::max)
+        };
+
+        let plain = TraceSettings::default();
+        let regularized = TraceSettings { regularize: true, ..TraceSettings::default() };
+        let first_bounce = PathState::start(&regularized);
+        let after_diffuse = PathState { bounces: 1, diffuse_bounced: true, ..first_bounce };
+
+        assert!(spread(&plain, &after_diffuse) < 1e-5);
+        assert!(spread(&regularized, &first_bounce) < 1e-5);
+        assert!(spread(&regularized, &after_diffuse) > 0.01);
+    }
+
+    /// Straightforward recursive version of color, without Russian roulette, free bounces or a bounce limit
+    fn recursive_color(ray: &Ray, scene: &Scene, settings: &TraceSettings, depth: usize, rng: &mut dyn RngCore) -> Color {
+        if depth == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let mut hit_rec = HitRecord::new();
+        if !scene.hit(ray, 0.001, f32::MAX, &mut hit_rec) {
+            return settings.background.value(ray.direction);
+        }
+        let emitted = Ray::material(&hit_rec, settings).emitted();
+        match Ray::scatter(ray, &hit_rec, settings, &PathState::start(settings), rng) {
+            Some((attenuation, scattered)) => emitted + recursive_color(&scattered, scene, settings, depth - 1, rng).entrywise(attenuation),
+            None => emitted,
+        }
+    }
+
+    #[test]
+    fn ray_color_matches_recursive() {
+        use crate::hitables::materials::{Lambertian, Metal, DiffuseLight};
+
+        let scene = Scene::from_objects(vec![
+            Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, -1.0), 0.5, Box::new(Lambertian::new(Color::new(0.7, 0.3, 0.3))))),
+            Box::new(Sphere::with_material(Vector3::new(1.0, 0.0, -1.0), 0.5, Box::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.3)))),
+            Box::new(Sphere::with_material(Vector3::new(-1.0, 0.5, -1.5), 0.3, Box::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0))))),
+            Box::new(Sphere::new(Vector3::new(0.0, -100.5, -1.0), 100.0)),
+        ]);
+        let settings = TraceSettings { free_bounces: 0, ..TraceSettings::default() };
+        let directions = [Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.9, 0.1, -1.0), Vector3::new(-0.8, 0.4, -1.0), Vector3::new(0.2, -0.6, -1.0)];
+        for (seed, direction) in directions.into_iter().enumerate() {
+            let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), direction);
+            for depth in [1, 5, 50] {
+                let mut counters = TraceCounters::default();
+                let iterative = Ray::color_counted(&ray, &scene, &settings, depth, &mut counters, &mut StdRng::seed_from_u64(seed as u64));
+                let recursive = recursive_color(&ray, &scene, &settings, depth, &mut StdRng::seed_from_u64(seed as u64));
+
+                assert!((iterative - recursive).normal() <= 1e-5 * (1.0 + recursive.normal()), "iterative {:?} recursive {:?}", iterative, recursive);
+            }
+        }
+    }
+
+    #[test]
+    fn ray_color_deep_path() {
+        use crate::hitables::materials::Metal;
+
+        // Inside a closed mirror ball a path uses up all of a very large depth without running out of stack
+        let scene = Scene::from_objects(vec![Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, 0.0), 1.0, Box::new(Metal::new(Color::new(1.0, 1.0, 1.0), 0.0))))]);
+        let ray = Ray::new(Vector3::new(0.1, 0.2, 0.0), Vector3::new(0.3, -0.2, 1.0));
+        let settings = TraceSettings { bounce_limit: usize::MAX, ..TraceSettings::default() };
+        let mut counters = TraceCounters::default();
+
+        assert_eq!(Ray::color_counted(&ray, &scene, &settings, 200_000, &mut counters, &mut rand::thread_rng()), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(counters.rays, 200_000);
+    }
+
+    #[test]
+    fn ray_color_solid_background() {
+        use crate::background::Background;
+
+        let scene = Scene::new();
+        let settings = TraceSettings { background: Background::SolidColor(Color::new(0.0, 0.0, 0.0)), ..TraceSettings::default() };
+        let miss = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let hit = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert_eq!(Ray::color(&miss, &scene, &settings, 50), Color::new(0.0, 0.0, 0.0));
+        // Every bounce off the gray spheres escapes into the black background too
+        assert_eq!(Ray::color(&hit, &scene, &settings, 50), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn ray_color_diffuse_light() {
+        use crate::hitables::materials::DiffuseLight;
+        use crate::background::Background;
+
+        let emit = Color::new(4.0, 2.0, 0.5);
+        let scene = Scene::from_objects(vec![Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, -1.0), 0.5, Box::new(DiffuseLight::new(emit))))]);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let miss = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let dark = TraceSettings { background: Background::SolidColor(Color::new(0.0, 0.0, 0.0)), ..TraceSettings::default() };
+
+        for settings in [TraceSettings::default(), dark] {
+            assert_eq!(Ray::color(&ray, &scene, &settings, 50), emit);
+        }
+        assert_eq!(Ray::color(&miss, &scene, &dark, 50), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn ray_color_clay() {
+        use crate::hitables::materials::{Metal, Dielectric};
+
+        let ball = |material: Box<dyn Material>| Scene::from_objects(vec![Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, -1.0), 0.5, material))]);
+        let metal = ball(Box::new(Metal::new(Color::new(0.9, 0.2, 0.1), 0.0)));
+        let glass = ball(Box::new(Dielectric::new(1.5)));
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.1, 0.1, -1.0));
+        let average = |scene: &Scene, settings: &TraceSettings| {
+            let samples = 4000;
+            let mut sum = Color::new(0.0, 0.0, 0.0);
+            let mut rng = StdRng::seed_from_u64(480);
+            for _ in 0..samples {
+                sum += Ray::color_counted(&ray, scene, settings, 10, &mut TraceCounters::default(), &mut rng);
+            }
+            sum / samples as f32
+        };
+
+        let plain = TraceSettings::default();
+        assert!((average(&metal, &plain) - average(&glass, &plain)).normal() > 0.1);
+        // With the same random numbers clay mode traces both balls the same way
+        let clay = TraceSettings { clay: true, ..TraceSettings::default() };
+        assert_eq!(average(&metal, &clay), average(&glass, &clay));
+    }
+
+    #[test]
+    fn ray_color_free_dielectric_bounces() {
+        use crate::hitables::materials::Dielectric;
+
+        // Going through a glass ball takes two bounces, so with a max depth of two the ray is
+        // stopped inside the ball unless the glass bounces are free
+        let scene = Scene::from_objects(vec![Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, -1.0), 0.5, Box::new(Dielectric::new(1.5))))]);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.01, 0.02, -1.0));
+        let average = |settings: &TraceSettings| {
+            let samples = 500;
+            let mut sum = Color::new(0.0, 0.0, 0.0);
+            let mut rng = StdRng::seed_from_u64(490);
+            for _ in 0..samples {
+                sum += Ray::color_counted(&ray, &scene, settings, 2, &mut TraceCounters::default(), &mut rng);
+            }
+            sum / samples as f32
+        };
+
+        let counted = average(&TraceSettings { free_bounces: 0, ..TraceSettings::default() });
+        let free = average(&TraceSettings::default());
+        assert!(free.average() > counted.average() + 0.5, "free {:?} counted {:?}", free, counted);
+    }
+
+    #[test]
+    fn ray_color_bounce_limit() {
+        use crate::hitables::materials::{FreeMirror, Metal};
+
+        // Inside a closed mirror ball with unlimited free bounces the path never ends by itself
+        let mirror = FreeMirror(Metal::new(Color::new(1.0, 1.0, 1.0), 0.0));
+        let scene = Scene::from_objects(vec![Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, 0.0), 1.0, Box::new(mirror)))]);
+        let ray = Ray::new(Vector3::new(0.1, 0.2, 0.0), Vector3::new(0.3, -0.2, 1.0));
+        let settings = TraceSettings {
+            free_bounces: usize::MAX,
+            bounce_limit: 200,
+            ..TraceSettings::default()
+        };
+        let mut counters = TraceCounters::default();
+
+        assert_eq!(Ray::color_counted(&ray, &scene, &settings, 5, &mut counters, &mut rand::thread_rng()), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(counters, TraceCounters { rays: 200, stopped_paths: 1 });
+    }
+
+    #[test]
+    fn ray_color_single_bounce() {
+        use crate::hitables::materials::Lambertian;
+
+        // A red ball on a green floor, looking at the floor right next to where the ball touches it,
+        // where a lot of the light has bounced around between them
+        let scene = Scene::from_objects(vec![
+            Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, -1.0), 0.5, Box::new(Lambertian::new(Color::new(0.95, 0.3, 0.3))))),
+            Box::new(Sphere::with_material(Vector3::new(0.0, -100.5, -1.0), 100.0, Box::new(Lambertian::new(Color::new(0.3, 0.95, 0.3))))),
+        ]);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.3, -0.5, -1.0));
+        let average = |settings: &TraceSettings, counters: &mut TraceCounters| {
+            let samples = 4000;
+            let mut sum = Color::new(0.0, 0.0, 0.0);
+            let mut rng = StdRng::seed_from_u64(499);
+            for _ in 0..samples {
+                sum += Ray::color_counted(&ray, &scene, settings, 50, counters, &mut rng);
+            }
+            sum / samples as f32
+        };
+
+        let mut full_counters = TraceCounters::default();
+        let mut single_counters = TraceCounters::default();
+        let full = average(&TraceSettings::default(), &mut full_counters);
+        let single = average(&TraceSettings { single_bounce: true, ..TraceSettings::default() }, &mut single_counters);
+
+        assert!(single.x.is_finite() && single.y.is_finite() && single.z.is_finite());
+        assert!(single.average() < full.average(), "single {:?} full {:?}", single, full);
+        assert!(single_counters.rays <= 4000 * SINGLE_BOUNCE_DEPTH as u64);
+        assert!(single_counters.rays < full_counters.rays);
+    }
+
+    #[test]
+    fn ray_color_mirror_parallel_planes() {
+        use crate::hitables::objects::Triangle;
+
+        // Two 20 by 20 squares facing each other at y = 0 and y = 1
+        let square = |y: f32| -> Vec<Box<dyn Hitable>> {
+            let corner = |x: f32, z: f32| Vector3::new(x, y, z);
+            vec![
+                Box::new(Triangle::new(corner(-10.0, -10.0), corner(10.0, -10.0), corner(10.0, 10.0))),
+                Box::new(Triangle::new(corner(-10.0, -10.0), corner(10.0, 10.0), corner(-10.0, 10.0))),
+            ]
+        };
+        let scene = Scene::from_objects(square(0.0).into_iter().chain(square(1.0)).collect());
+        let settings = TraceSettings::default();
+        // Bounces at x = 0.5, 1.5, ..., 9.5, ten times in all, and escapes going up
+        let ray = Ray::new(Vector3::new(0.0, 0.5, 0.0), Vector3::new(1.0, 1.0, 0.0));
+        let escaped = settings.background.value(Vector3::new(1.0, 1.0, 0.0));
+
+        assert!((Ray::color_mirror(&ray, &scene, &settings, 50) - escaped).normal() < 1e-4);
+        assert_eq!(Ray::color_mirror(&ray, &scene, &settings, 10), Color::new(0.0, 0.0, 0.0));
+        assert!((Ray::color_mirror(&ray, &scene, &settings, 11) - escaped).normal() < 1e-4);
+    }
+
+    #[test]
+    fn ray_trace_path_hit() {
+        let scene = Scene::new();
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let path = Ray::trace_path(&ray, &scene, 10);
+
+        assert!(!path.is_empty());
+        assert!((path[0] - Vector3::new(0.0, 0.0, -0.5)).normal() < 1e-5);
+    }
+
+    #[test]
+    fn ray_trace_path_miss() {
+        let scene = Scene::new();
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let path = Ray::trace_path(&ray, &scene, 10);
+
+        assert_eq!(path, vec![Vector3::new(0.0, 1.0, 0.0)]);
+    }
+
+    #[test]
+    fn ray_trace_path_max_depth() {
+        let scene = Scene::new();
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert!(Ray::trace_path(&ray, &scene, 3).len() <= 3);
+        assert!(Ray::trace_path(&ray, &scene, 0).is_empty());
+    }
+
+    #[test]
+    fn ray_trace_path_with_seed() {
+        let scene = Scene::new();
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, -0.5, -1.0));
+        let path = Ray::trace_path_with(&ray, &scene, 10, &mut StdRng::seed_from_u64(528));
+
+        assert!(path.len() > 1);
+        assert_eq!(path, Ray::trace_path_with(&ray, &scene, 10, &mut StdRng::seed_from_u64(528)));
+    }
+}
@@ -0,0 +1,1485 @@
+use crate::{vector::*, ray::{Ray, TraceSettings, TraceCounters}, camera::{Camera, Frustum}, hitables::{HitRecord, Hitable, scene::Scene}};
+use crate::spectrum::{sample_wavelength, spectral_weight};
+use crate::postprocess::Bloom;
+use crate::sampling::{SamplingPattern, stream_seed};
+
+use rand::{SeedableRng, rngs::StdRng};
+use rayon::prelude::*;
+use std::io::{self, Write};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// ## Framebuffer
+/// Linear colors of a rendered image, stored row by row starting with the top row.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Framebuffer {
+    /// ## new
+    /// Returns a black Framebuffer with the given dimensions
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Color::new(0.0, 0.0, 0.0); width * height],
+        }
+    }
+
+    /// ## downscale
+    /// Returns the image made smaller by factor, with every pixel the average of a factor x factor block.
+    /// Panics if the dimensions aren't multiples of factor.
+    pub fn downscale(&self, factor: usize) -> Framebuffer {
+        assert!(factor > 0 && self.width.is_multiple_of(factor) && self.height.is_multiple_of(factor),
+                "Can't downscale a {}x{} Framebuffer by {}", self.width, self.height, factor);
+        let width: usize = self.width / factor;
+        let height: usize = self.height / factor;
+        let mut pixels: Vec<Color> = Vec::with_capacity(width * height);
+        for row in 0..height {
+            for col in 0..width {
+                let mut sum: Color = Color::new(0.0, 0.0, 0.0);
+                for y in row * factor..(row + 1) * factor {
+                    for x in col * factor..(col + 1) * factor {
+                        sum += self.pixels[y * self.width + x];
+                    }
+                }
+                pixels.push(sum / (factor * factor) as f32);
+            }
+        }
+        Framebuffer {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// ## ToneMap
+/// How the brightness of linear colors is mapped before they are written out, to fit bright
+/// highlights into the displayable range instead of clipping them to white
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum ToneMap {
+    /// Leave the colors as they are
+    #[default]
+    None,
+    /// Map every channel c to c / (1 + c), which squeezes any brightness below 1
+    Reinhard,
+    /// Like Reinhard but mapping white_point and brighter to 1, so less of the range is spent on highlights.
+    /// The white point has to be above zero, otherwise plain Reinhard is used.
+    ReinhardExtended { white_point: f32 },
+}
+
+impl ToneMap {
+    /// ## reinhard_extended
+    /// Returns ToneMap::ReinhardExtended with the given white point, or an error if it isn't above zero
+    pub fn reinhard_extended(white_point: f32) -> Result<ToneMap, String> {
+        if white_point.is_nan() || white_point <= 0.0 {
+            return Err(format!("White point must be above zero, got {}", white_point));
+        }
+        Ok(ToneMap::ReinhardExtended { white_point })
+    }
+
+    /// ## apply
+    /// Returns the color with every channel tone mapped
+    pub fn apply(&self, color: Color) -> Color {
+        let map = |channel: f32| match *self {
+            ToneMap::None => channel,
+            ToneMap::ReinhardExtended { white_point } if white_point > 0.0 => channel * (1.0 + channel / (white_point * white_point)) / (1.0 + channel),
+            ToneMap::Reinhard | ToneMap::ReinhardExtended { .. } => channel / (1.0 + channel),
+        };
+        Color::new(map(color.x), map(color.y), map(color.z))
+    }
+}
+
+/// ## RenderMode
+/// What the color of a sample shows. The debug modes only look at the first hit of each camera ray,
+/// which tells wrong geometry apart from wrong lighting when a scene renders wrong.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum RenderMode {
+    /// Follow the rays through the scene for the full lit image
+    #[default]
+    PathTraced,
+    /// Color the first hit by its normal, 0.5 * (normal + 1), and the background black
+    Normals,
+    /// Color the first hit gray, white at the camera fading to black at max_distance and beyond
+    Depth { max_distance: f32 },
+}
+
+impl RenderMode {
+    /// ## first_hit_color
+    /// Returns the color of the first hit of the ray in one of the debug modes, black if nothing is hit.
+    /// Path traced colors need the whole path, so PathTraced returns None.
+    pub fn first_hit_color(&self, ray: &Ray, scene: &Scene) -> Option<Color> {
+        if *self == RenderMode::PathTraced {
+            return None;
+        }
+        let mut hit_rec: HitRecord = HitRecord::new();
+        if !scene.hit(ray, 0.001, f32::MAX, &mut hit_rec) {
+            return Some(Color::new(0.0, 0.0, 0.0));
+        }
+        match *self {
+            RenderMode::Depth { max_distance } => {
+                let distance: f32 = hit_rec.t * ray.direction.normal();
+                let gray: f32 = 1.0 - (distance / max_distance).clamp(0.0, 1.0);
+                Some(Color::new(gray, gray, gray))
+            }
+            _ => Some(0.5 * (hit_rec.normal + Vector3::new(1.0, 1.0, 1.0))),
+        }
+    }
+}
+
+/// ## Overflow
+/// How colors with a channel above 1 are brought back into range before they are written out
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum Overflow {
+    /// Clamp every channel to 1 on its own, which shifts the hue of bright colors towards the primaries
+    #[default]
+    Clamp,
+    /// Divide the whole color by its largest channel, which keeps the hue
+    Rescale,
+}
+
+impl Overflow {
+    /// ## apply
+    /// Returns the color with every channel at most 1
+    pub fn apply(&self, color: Color) -> Color {
+        match self {
+            Overflow::Clamp => Color::new(color.x.min(1.0), color.y.min(1.0), color.z.min(1.0)),
+            Overflow::Rescale => {
+                let max: f32 = color.x.max(color.y).max(color.z);
+                if max > 1.0 { color / max } else { color }
+            }
+        }
+    }
+}
+
+/// ## AccumulationBuffer
+/// Sum of the samples added so far in a progressive render. It is behind a lock so other threads
+/// can look at the image while the render is still adding samples to it.
+pub struct AccumulationBuffer {
+    pub width: usize,
+    pub height: usize,
+    /// Sum of the samples of every pixel and the number of passes added
+    sums: Mutex<(Vec<Color>, usize)>,
+}
+
+impl AccumulationBuffer {
+    /// ## new
+    /// Returns an empty AccumulationBuffer with the given dimensions
+    pub fn new(width: usize, height: usize) -> AccumulationBuffer {
+        AccumulationBuffer {
+            width,
+            height,
+            sums: Mutex::new((vec![Color::new(0.0, 0.0, 0.0); width * height], 0)),
+        }
+    }
+
+    /// ## add_pass
+    /// Adds one sample for every pixel, in the same order as in a Framebuffer
+    pub fn add_pass(&self, pass: &[Color]) {
+        assert_eq!(pass.len(), self.width * self.height, "Pass has the wrong number of pixels");
+        let mut sums = self.sums.lock().expect("Accumulation buffer lock poisoned");
+        for (sum, sample) in sums.0.iter_mut().zip(pass) {
+            *sum += *sample;
+        }
+        sums.1 += 1;
+    }
+
+    /// ## passes
+    /// Returns the number of passes added so far
+    pub fn passes(&self) -> usize {
+        self.sums.lock().expect("Accumulation buffer lock poisoned").1
+    }
+
+    /// ## current_image
+    /// Returns the average of the passes added so far. Black if no pass has been added.
+    pub fn current_image(&self) -> Framebuffer {
+        let sums = self.sums.lock().expect("Accumulation buffer lock poisoned");
+        let passes: f32 = sums.1.max(1) as f32;
+        Framebuffer {
+            width: self.width,
+            height: self.height,
+            pixels: sums.0.iter().map(|sum| *sum / passes).collect(),
+        }
+    }
+}
+
+/// ## THREAD_POOLS
+/// Pools renders with a set number of threads run on, by number of threads, so they are only started once
+static THREAD_POOLS: LazyLock<Mutex<HashMap<usize, Arc<rayon::ThreadPool>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// ## RenderStats
+/// Counters that are updated while rendering. They are atomic so all render threads can share them.
+#[derive(Debug, Default)]
+pub struct RenderStats {
+    /// Rays shot from the camera
+    pub primary_rays: AtomicU64,
+    /// All rays traced through the scene, camera rays as well as bounced rays
+    pub rays: AtomicU64,
+    /// Paths stopped by the bounce limit, which means something is wrong with a material
+    pub stopped_paths: AtomicU64,
+    /// Pixel counted from the top left of the image of one of the stopped paths, None if no path was stopped
+    pub stopped_pixel: Mutex<Option<(usize, usize)>>,
+    /// Tiles that are done
+    pub tiles: AtomicUsize,
+}
+
+impl RenderStats {
+    /// ## new
+    /// Returns RenderStats with all counters at zero
+    pub fn new() -> RenderStats {
+        RenderStats::default()
+    }
+
+    /// ## add
+    /// Adds the rays counted in a TileState to the counters, and keeps its stopped pixel if there is none yet
+    fn add(&self, state: &TileState) {
+        self.primary_rays.fetch_add(state.primary_rays, Ordering::Relaxed);
+        self.rays.fetch_add(state.counters.rays, Ordering::Relaxed);
+        self.stopped_paths.fetch_add(state.counters.stopped_paths, Ordering::Relaxed);
+        if let Some(pixel) = state.stopped_pixel {
+            self.stopped_pixel.lock().expect("RenderStats lock poisoned").get_or_insert(pixel);
+        }
+    }
+}
+
+/// ## TileState
+/// Random number generator, scratch space and counters used while rendering a tile
+struct TileState {
+    rng: StdRng,
+    samples: Vec<Color>,
+    primary_rays: u64,
+    counters: TraceCounters,
+    /// Pixel counted from the top left of the image of the first path stopped by the bounce limit
+    stopped_pixel: Option<(usize, usize)>,
+    /// Indices of the objects camera rays of the tile can hit, None to test every object
+    candidates: Option<Vec<usize>>,
+}
+
+/// ## Renderer
+/// Settings deciding how an image of a scene is rendered.
+pub struct Renderer {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: usize,
+    pub max_depth: usize,
+    /// Seed for every random choice of a render: sampling offsets, lens points, wavelengths and scattering.
+    /// The same seed renders the same image. None picks a new random seed for every render.
+    pub seed: Option<u64>,
+    /// Background, normal offset and Russian roulette used when following rays through the scene
+    pub trace: TraceSettings,
+    /// Number of brightest samples of each pixel left out of its average, to remove fireflies
+    pub drop_brightest: usize,
+    /// Trace every sample with a single random wavelength instead of rgb, for effects like dispersion
+    pub spectral: bool,
+    /// Width and height in pixels of the tiles used when rendering in parallel
+    pub tile_size: usize,
+    /// Number of threads used when rendering in parallel, 0 uses one per available core
+    pub threads: usize,
+    /// Glow added around bright parts of images rendered into a Framebuffer, None for no glow
+    pub bloom: Option<Bloom>,
+    /// Add an ordered dither before PPM colors are rounded to 8 bits, to break up banding in smooth gradients
+    pub dither: bool,
+    /// Gamma the PPM colors are corrected with, None writes the linear colors without gamma correction.
+    /// Only set through set_gamma and set_linear, so it is always above zero.
+    gamma: Option<f32>,
+    /// How the points within a pixel that the samples are taken at are chosen
+    pub sampling: SamplingPattern,
+    /// How PPM colors are tone mapped, before overflow and gamma
+    pub tone_map: ToneMap,
+    /// How PPM colors with a channel above 1 are brought back into range
+    pub overflow: Overflow,
+    /// Only test camera rays of a tile against the objects whose bounding box is in view of the tile
+    pub tile_culling: bool,
+    /// Whether samples are path traced or show the normals or depth of the first hit
+    pub mode: RenderMode,
+}
+
+impl Renderer {
+    /// ## new
+    /// Returns a Renderer with the given dimensions, samples per pixel and max depth
+    pub fn new(width: usize, height: usize, samples_per_pixel: usize, max_depth: usize) -> Renderer {
+        Renderer {
+            width,
+            height,
+            samples_per_pixel,
+            max_depth,
+            seed: None,
+            trace: TraceSettings::default(),
+            drop_brightest: 0,
+            spectral: false,
+            tile_size: 32,
+            threads: 0,
+            bloom: None,
+            dither: false,
+            gamma: Some(2.0),
+            sampling: SamplingPattern::Random,
+            tone_map: ToneMap::None,
+            overflow: Overflow::Clamp,
+            tile_culling: false,
+            mode: RenderMode::PathTraced,
+        }
+    }
+
+    /// ## gamma
+    /// Returns the gamma PPM colors are corrected with, None if they are written linear
+    pub fn gamma(&self) -> Option<f32> {
+        self.gamma
+    }
+
+    /// ## set_gamma
+    /// Sets the gamma PPM colors are corrected with, where 1 leaves them linear.
+    /// Returns an error and keeps the old gamma if it isn't above zero.
+    pub fn set_gamma(&mut self, gamma: f32) -> Result<(), String> {
+        self.gamma = Some(validate_gamma(gamma)?);
+        Ok(())
+    }
+
+    /// ## set_linear
+    /// Makes the PPM colors be written linear, without gamma correction
+    pub fn set_linear(&mut self) {
+        self.gamma = None;
+    }
+
+    /// ## render
+    /// Renders the scene as seen by the camera into a new Framebuffer
+    pub fn render(&self, camera: &Camera, scene: &Scene) -> Framebuffer {
+        let mut buffer: Framebuffer = Framebuffer::new(self.width, self.height);
+        self.render_into(&mut buffer, camera, scene);
+        buffer
+    }
+
+    /// ## render_corrected
+    /// Renders the scene as seen by the camera and returns the colors row by row starting with the top row,
+    /// tone mapped and clamped or rescaled like the PPM output and gamma corrected with the gamma of the Renderer.
+    ///
+    /// ```
+    /// use emilhul_task_13::{camera::Camera, hitables::scene::Scene, render::Renderer, vector::Color};
+    ///
+    /// let renderer: Renderer = Renderer::new(2, 2, 1, 5);
+    /// let pixels: Vec<Color> = renderer.render_corrected(&Camera::default(), &Scene::new());
+    /// assert_eq!(pixels.len(), 4);
+    /// ```
+    pub fn render_corrected(&self, camera: &Camera, scene: &Scene) -> Vec<Color> {
+        self.render(camera, scene).pixels.iter()
+            .map(|color| {
+                let color: Color = self.display_color(*color);
+                Color::new(
+                    gamma_correct(color.x, self.gamma).min(1.0),
+                    gamma_correct(color.y, self.gamma).min(1.0),
+                    gamma_correct(color.z, self.gamma).min(1.0),
+                )
+            })
+            .collect()
+    }
+
+    /// ## render_rgb8
+    /// Renders the scene as seen by the camera and returns the pixels packed as r, g, b bytes,
+    /// width * height * 3 of them starting with the top row. The colors are corrected like in render_corrected
+    /// and rounded like the PPM output, without the dither.
+    pub fn render_rgb8(&self, camera: &Camera, scene: &Scene) -> Vec<u8> {
+        self.render_corrected(camera, scene).iter()
+            .flat_map(|color| [quantize(color.x), quantize(color.y), quantize(color.z)])
+            .collect()
+    }
+
+    /// ## render_image
+    /// Renders the scene as seen by the camera into an image::RgbImage, which can be saved as a PNG
+    ///
+    /// ```no_run
+    /// use emilhul_task_13::{camera::Camera, hitables::scene::Scene, render::Renderer};
+    ///
+    /// let renderer: Renderer = Renderer::new(200, 100, 10, 50);
+    /// renderer.render_image(&Camera::default(), &Scene::new()).save("out.png").expect("Could not write out.png");
+    /// ```
+    pub fn render_image(&self, camera: &Camera, scene: &Scene) -> image::RgbImage {
+        image::RgbImage::from_raw(self.width as u32, self.height as u32, self.render_rgb8(camera, scene))
+            .expect("Buffer has width * height * 3 bytes")
+    }
+
+    /// ## render_into
+    /// Renders the scene as seen by the camera into an existing Framebuffer, reusing its allocation.
+    /// The rows are rendered in parallel, each with its own random numbers seeded from the seed
+    /// and the row, so the image is the same whatever the number of threads.
+    /// Panics if the dimensions of the buffer don't match the Renderer.
+    pub fn render_into(&self, buffer: &mut Framebuffer, camera: &Camera, scene: &Scene) {
+        assert!(buffer.width == self.width && buffer.height == self.height,
+                "Framebuffer is {}x{} but the renderer expects {}x{}",
+                buffer.width, buffer.height, self.width, self.height);
+        assert_eq!(buffer.pixels.len(), self.width * self.height, "Framebuffer has the wrong number of pixels");
+
+        let stats: RenderStats = RenderStats::new();
+        self.install(|| {
+            buffer.pixels.par_chunks_mut(self.width.max(1)).enumerate().for_each(|(image_row, pixels)| {
+                let row: usize = self.height - 1 - image_row;
+                let mut state: TileState = self.tile_state(self.row_rng(row));
+                for (col, pixel) in pixels.iter_mut().enumerate() {
+                    *pixel = self.render_pixel(col, row, camera, scene, &mut state);
+                }
+                stats.add(&state);
+            });
+        });
+        self.warn_stopped_paths(&stats, 0);
+        if let Some(bloom) = self.bloom {
+            bloom.apply(buffer);
+        }
+    }
+
+    /// ## render_supersampled
+    /// Renders the scene as seen by the camera at factor times the given width and height, with
+    /// the other settings of the Renderer, and averages factor x factor blocks down to a width x height image.
+    /// Bloom is added after scaling down. Returns an error if factor is zero.
+    pub fn render_supersampled(&self, width: usize, height: usize, factor: usize, camera: &Camera, scene: &Scene) -> Result<Framebuffer, String> {
+        if factor == 0 {
+            return Err(String::from("Supersampling factor must be above zero, got 0"));
+        }
+        let internal: Renderer = Renderer {
+            width: width * factor,
+            height: height * factor,
+            bloom: None,
+            ..*self
+        };
+        let mut image: Framebuffer = internal.render(camera, scene).downscale(factor);
+        if let Some(bloom) = &self.bloom {
+            bloom.apply(&mut image);
+        }
+        Ok(image)
+    }
+
+    /// ## render_progressive
+    /// Renders the scene as seen by the camera in samples_per_pixel passes of one sample per pixel.
+    /// Every pass is added to buffer as soon as it is done, so the image can be looked at with
+    /// current_image while the render goes on. Panics if the dimensions of the buffer don't match.
+    /// The brightest samples are never dropped, as the samples of a pixel aren't kept.
+    pub fn render_progressive(&self, buffer: &AccumulationBuffer, camera: &Camera, scene: &Scene) {
+        assert!(buffer.width == self.width && buffer.height == self.height,
+                "AccumulationBuffer is {}x{} but the renderer expects {}x{}",
+                buffer.width, buffer.height, self.width, self.height);
+
+        let mut state: TileState = self.tile_state(match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        });
+        let mut pass: Vec<Color> = Vec::with_capacity(self.width * self.height);
+        for sample in 0..self.samples_per_pixel {
+            pass.clear();
+            for row in (0..self.height).rev() {
+                for col in 0..self.width {
+                    pass.push(self.sample_pixel(col, row, sample, camera, scene, &mut state));
+                }
+            }
+            buffer.add_pass(&pass);
+        }
+        let stats: RenderStats = RenderStats::new();
+        stats.add(&state);
+        self.warn_stopped_paths(&stats, 0);
+    }
+
+    /// ## render_tiled
+    /// Renders the scene as seen by the camera into a new Framebuffer, with the image split into
+    /// square tiles which are rendered in parallel. Every tile has its own random numbers seeded from
+    /// the seed and the position of the tile, so the image is the same whatever the number of threads.
+    /// The number of rays and tiles rendered are added to stats as the render goes on.
+    pub fn render_tiled(&self, camera: &Camera, scene: &Scene, stats: &RenderStats) -> Framebuffer {
+        let stopped_before: u64 = stats.stopped_paths.load(Ordering::Relaxed);
+        let mut buffer: Framebuffer = Framebuffer::new(self.width, self.height);
+        let band_size: usize = self.width * self.tile_size.max(1);
+        self.install(|| {
+            for (tile_y, band) in buffer.pixels.chunks_mut(band_size).enumerate() {
+                band.copy_from_slice(&self.render_band(tile_y, camera, scene, stats));
+            }
+        });
+        self.warn_stopped_paths(stats, stopped_before);
+        if let Some(bloom) = self.bloom {
+            bloom.apply(&mut buffer);
+        }
+        buffer
+    }
+
+    /// ## render_ids
+    /// Returns the index in the object list of the object seen through the center of each pixel,
+    /// or None where the background is seen. The pixels are in the same order as in a Framebuffer.
+    /// The rays go through the center of the lens, so the ids are the same every time even with depth of field.
+    pub fn render_ids(&self, camera: &Camera, scene: &Scene) -> Vec<Option<usize>> {
+        let mut ids: Vec<Option<usize>> = Vec::with_capacity(self.width * self.height);
+        let mut hit_rec: HitRecord = HitRecord::new();
+        for row in (0..self.height).rev() {
+            for col in 0..self.width {
+                let u: f32 = (col as f32 + 0.5) / self.width as f32;
+                let v: f32 = (row as f32 + 0.5) / self.height as f32;
+                ids.push(scene.hit_object(&camera.get_center_ray(u, v), 0.001, f32::MAX, &mut hit_rec));
+            }
+        }
+        ids
+    }
+
+    /// ## render_coverage
+    /// Returns the fraction of the samples of each pixel that hit an object, for use as an alpha channel.
+    /// Pixels on the edge of an object get a coverage between 0 and 1. The pixels are in the same
+    /// order as in a Framebuffer and the samples are placed by the sampling pattern as in render,
+    /// with the lens points and times taken from the seeded generator of the row.
+    pub fn render_coverage(&self, camera: &Camera, scene: &Scene) -> Vec<f32> {
+        let mut coverage: Vec<f32> = Vec::with_capacity(self.width * self.height);
+        let mut hit_rec: HitRecord = HitRecord::new();
+        for row in (0..self.height).rev() {
+            let mut rng: StdRng = self.row_rng(row);
+            for col in 0..self.width {
+                let mut hits: usize = 0;
+                for sample in 0..self.samples_per_pixel {
+                    let (offset_u, offset_v) = self.sampling.offset(col, row, sample, self.samples_per_pixel, &mut rng);
+                    let u: f32 = (col as f32 + offset_u) / self.width as f32;
+                    let v: f32 = (row as f32 + offset_v) / self.height as f32;
+                    if scene.hit(&camera.get_ray_with(u, v, &mut rng), 0.001, f32::MAX, &mut hit_rec) {
+                        hits += 1;
+                    }
+                }
+                coverage.push(hits as f32 / self.samples_per_pixel.max(1) as f32);
+            }
+        }
+        coverage
+    }
+
+    /// ## render_ppm
+    /// Renders the scene as seen by the camera and writes it to writer as an ascii PPM image.
+    /// The image is split into square tiles which are rendered in parallel. Only one row of tiles
+    /// is kept in memory at a time and it is written as soon as all of its tiles are done,
+    /// so memory use doesn't grow with the size of the image. Bloom needs the whole image and isn't applied.
+    pub fn render_ppm<W: Write>(&self, writer: &mut W, camera: &Camera, scene: &Scene) -> io::Result<()> {
+        write!(writer, "P3\n{} {}\n255\n", self.width, self.height)?;
+
+        let stats: RenderStats = RenderStats::new();
+        let tile_size: usize = self.tile_size.max(1);
+        for tile_y in 0..self.height.div_ceil(tile_size) {
+            let band: Vec<Color> = self.install(|| self.render_band(tile_y, camera, scene, &stats));
+            for (index, color) in band.iter().enumerate() {
+                let color: Color = self.display_color(*color);
+                if self.dither {
+                    let row: usize = tile_y * tile_size + index / self.width;
+                    writeln!(writer, "{}", ppm_pixel_dithered(color, index % self.width, row, self.gamma))?;
+                } else {
+                    writeln!(writer, "{}", ppm_pixel_with_gamma(color, self.gamma))?;
+                }
+            }
+        }
+        self.warn_stopped_paths(&stats, 0);
+        Ok(())
+    }
+
+    /// ## display_color
+    /// Returns a linear color tone mapped and then brought into range, ready for gamma correction
+    fn display_color(&self, color: Color) -> Color {
+        self.overflow.apply(self.tone_map.apply(color))
+    }
+
+    /// ## render_band
+    /// Renders the row of tiles tile_y counted from the top of the image in parallel, on the current thread pool.
+    /// Returns the pixels of the band row by row starting with the top row.
+    fn render_band(&self, tile_y: usize, camera: &Camera, scene: &Scene, stats: &RenderStats) -> Vec<Color> {
+        let tile_size: usize = self.tile_size.max(1);
+        let tiles_x: usize = self.width.div_ceil(tile_size);
+        let band_height: usize = tile_size.min(self.height - tile_y * tile_size);
+        let tiles: Vec<Vec<Color>> = (0..tiles_x).into_par_iter()
+            .map(|tile_x| self.render_tile(tile_x, tile_y, camera, scene, stats))
+            .collect();
+
+        // Put the tiles together into whole rows
+        let mut band: Vec<Color> = vec![Color::new(0.0, 0.0, 0.0); self.width * band_height];
+        for (tile_x, pixels) in tiles.into_iter().enumerate() {
+            let left: usize = tile_x * tile_size;
+            let tile_width: usize = tile_size.min(self.width - left);
+            for (row, tile_row) in pixels.chunks(tile_width).enumerate() {
+                band[row * self.width + left..row * self.width + left + tile_width].copy_from_slice(tile_row);
+            }
+        }
+        band
+    }
+
+    /// ## render_tile
+    /// Renders the tile in column tile_x and row tile_y counted from the top left of the image.
+    /// Returns the pixels of the tile row by row starting with the top row.
+    fn render_tile(&self, tile_x: usize, tile_y: usize, camera: &Camera, scene: &Scene, stats: &RenderStats) -> Vec<Color> {
+        let tile_size: usize = self.tile_size.max(1);
+        let left: usize = tile_x * tile_size;
+        let top: usize = tile_y * tile_size;
+        let right: usize = (left + tile_size).min(self.width);
+        let bottom: usize = (top + tile_size).min(self.height);
+
+        // Every tile gets its own generator so tiles can be rendered in any order
+        let mut state: TileState = self.tile_state(match self.seed {
+            Some(seed) => StdRng::seed_from_u64(stream_seed(seed, ((tile_y as u64) << 32) | tile_x as u64)),
+            None => StdRng::from_entropy(),
+        });
+        if self.tile_culling {
+            state.candidates = self.tile_candidates(tile_x, tile_y, camera, scene);
+        }
+        let mut pixels: Vec<Color> = Vec::with_capacity((right - left) * (bottom - top));
+        for image_row in top..bottom {
+            for col in left..right {
+                let row: usize = self.height - 1 - image_row;
+                pixels.push(self.render_pixel(col, row, camera, scene, &mut state));
+            }
+        }
+        stats.add(&state);
+        stats.tiles.fetch_add(1, Ordering::Relaxed);
+        pixels
+    }
+
+    /// ## tile_candidates
+    /// Returns the indices of the objects that camera rays of the tile in column tile_x and row tile_y,
+    /// counted from the top left of the image, may hit. Objects without a bounding box are always included.
+    /// None if the camera can't tell which objects are in view.
+    pub fn tile_candidates(&self, tile_x: usize, tile_y: usize, camera: &Camera, scene: &Scene) -> Option<Vec<usize>> {
+        let tile_size: usize = self.tile_size.max(1);
+        let left: usize = tile_x * tile_size;
+        let top: usize = tile_y * tile_size;
+        let right: usize = (left + tile_size).min(self.width);
+        let bottom: usize = (top + tile_size).min(self.height);
+        let frustum: Frustum = camera.frustum(
+            left as f32 / self.width as f32,
+            (self.height - bottom) as f32 / self.height as f32,
+            right as f32 / self.width as f32,
+            (self.height - top) as f32 / self.height as f32,
+        )?;
+        Some(scene.objects().iter().enumerate()
+            .filter(|(_, object)| object.bounding_box().is_none_or(|bbox| frustum.may_contain(&bbox)))
+            .map(|(index, _)| index)
+            .collect())
+    }
+
+    /// ## warn_stopped_paths
+    /// Prints one warning for all the paths stopped by the bounce limit since stats counted stopped_before of them
+    fn warn_stopped_paths(&self, stats: &RenderStats, stopped_before: u64) {
+        let stopped: u64 = stats.stopped_paths.load(Ordering::Relaxed) - stopped_before;
+        if stopped > 0 {
+            let (col, row) = stats.stopped_pixel.lock().expect("RenderStats lock poisoned").unwrap_or_default();
+            eprintln!("Warning: {} paths were stopped after {} bounces, one of them from pixel ({}, {})",
+                      stopped, self.trace.bounce_limit, col, row);
+        }
+    }
+
+    /// ## install
+    /// Runs op with the number of threads set in the Renderer. With threads at 0 op runs on the
+    /// global pool, otherwise on a pool with that many threads, which is built on first use and then kept.
+    fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        if self.threads == 0 {
+            return op();
+        }
+        let pool: Arc<rayon::ThreadPool> = THREAD_POOLS.lock().expect("Thread pool lock poisoned")
+            .entry(self.threads)
+            .or_insert_with(|| Arc::new(rayon::ThreadPoolBuilder::new()
+                .num_threads(self.threads)
+                .build()
+                .expect("Failed to start render threads")))
+            .clone();
+        pool.install(op)
+    }
+
+    /// ## row_rng
+    /// Returns the random number generator for the pixels in row row counted from the bottom of the
+    /// image when the image isn't split into tiles, seeded from the seed and the row
+    fn row_rng(&self, row: usize) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(stream_seed(seed, row as u64)),
+            None => StdRng::from_entropy(),
+        }
+    }
+
+    /// ## tile_state
+    /// Returns a TileState with the given random number generator and no rays counted
+    fn tile_state(&self, rng: StdRng) -> TileState {
+        TileState {
+            rng,
+            samples: Vec::with_capacity(self.samples_per_pixel),
+            primary_rays: 0,
+            counters: TraceCounters::default(),
+            stopped_pixel: None,
+            candidates: None,
+        }
+    }
+
+    /// ## render_pixel
+    /// Returns the color of the pixel in column col and row row counted from the bottom of the image.
+    /// The random numbers and sample scratch space are taken from state, and the rays traced are counted there.
+    fn render_pixel(&self, col: usize, row: usize, camera: &Camera, scene: &Scene, state: &mut TileState) -> Color {
+        state.samples.clear();
+        for sample in 0..self.samples_per_pixel {
+            let color: Color = self.sample_pixel(col, row, sample, camera, scene, state);
+            state.samples.push(color);
+        }
+        average_samples(&mut state.samples, self.drop_brightest)
+    }
+
+    /// ## sample_pixel
+    /// Returns the color of sample number sample, taken at a point picked by the sampling pattern
+    /// in the pixel in column col and row row counted from the bottom of the image.
+    /// In the debug modes only the first hit of the camera ray is looked at.
+    /// The rays traced are counted in state, and the pixel is kept there if the path of the sample
+    /// is the first of state to be stopped by the bounce limit.
+    fn sample_pixel(&self, col: usize, row: usize, sample: usize, camera: &Camera, scene: &Scene, state: &mut TileState) -> Color {
+        let (offset_u, offset_v) = self.sampling.offset(col, row, sample, self.samples_per_pixel, &mut state.rng);
+        let u: f32 = (col as f32 + offset_u) / self.width as f32;
+        let v: f32 = (row as f32 + offset_v) / self.height as f32;
+        let mut ray: Ray = camera.get_ray_with(u, v, &mut state.rng);
+        state.primary_rays += 1;
+        if let Some(color) = self.mode.first_hit_color(&ray, scene) {
+            return color;
+        }
+        let stopped_before: u64 = state.counters.stopped_paths;
+        let wavelength: Option<f32> = self.spectral.then(|| sample_wavelength(&mut state.rng));
+        ray.wavelength = wavelength;
+        let radiance: Color = match &state.candidates {
+            Some(candidates) => Ray::color_counted_among(&ray, scene, candidates, &self.trace, self.max_depth, &mut state.counters, &mut state.rng),
+            None => Ray::color_counted(&ray, scene, &self.trace, self.max_depth, &mut state.counters, &mut state.rng),
+        };
+        let color: Color = match wavelength {
+            Some(wavelength) => radiance.entrywise(spectral_weight(wavelength)),
+            None => radiance,
+        };
+        if state.counters.stopped_paths > stopped_before {
+            state.stopped_pixel.get_or_insert((col, self.height - 1 - row));
+        }
+        color
+    }
+}
+
+/// ## ppm_pixel
+/// Returns the "r g b" text of a linear Color in an ascii PPM image, gamma corrected with gamma 2
+pub fn ppm_pixel(color: Color) -> String {
+    ppm_pixel_with_gamma(color, Some(2.0))
+}
+
+/// ## ppm_pixel_with_gamma
+/// Returns the "r g b" text of a linear Color in an ascii PPM image, gamma corrected with the given
+/// gamma or left linear for None. Channels are clamped to 0..=255.
+pub fn ppm_pixel_with_gamma(color: Color, gamma: Option<f32>) -> String {
+    let byte = |channel: f32| quantize(gamma_correct(channel, gamma));
+    format!("{} {} {}", byte(color.x), byte(color.y), byte(color.z))
+}
+
+/// ## ppm_pixel_dithered
+/// Returns the "r g b" text of a linear Color like ppm_pixel_with_gamma, with the ordered dither
+/// offset of the pixel in column col and row row counted from the top added before rounding down.
+/// Neighbouring pixels of the same color round differently,
+/// so a smooth gradient turns into a fine pattern instead of visible bands.
+pub fn ppm_pixel_dithered(color: Color, col: usize, row: usize, gamma: Option<f32>) -> String {
+    let offset: f32 = bayer_offset(col, row);
+    let byte = |channel: f32| quantize(gamma_correct(channel, gamma) + offset / 255.99);
+    format!("{} {} {}", byte(color.x), byte(color.y), byte(color.z))
+}
+
+/// ## validate_gamma
+/// Returns the gamma if colors can be corrected with it, or an error if it isn't above zero
+pub fn validate_gamma(gamma: f32) -> Result<f32, String> {
+    if gamma.is_nan() || gamma <= 0.0 {
+        return Err(format!("Gamma must be above zero, got {}", gamma));
+    }
+    Ok(gamma)
+}
+
+/// ## gamma_correct
+/// Returns a linear color channel gamma corrected with the given gamma, or as it is for None
+fn gamma_correct(channel: f32, gamma: Option<f32>) -> f32 {
+    match gamma {
+        Some(2.0) => channel.max(0.0).sqrt(), // Exact and fast for the standard gamma
+        Some(gamma) => channel.max(0.0).powf(1.0 / gamma),
+        None => channel,
+    }
+}
+
+/// ## bayer_offset
+/// Returns the offset between -0.5 and 0.5 of the pixel in a 4x4 Bayer ordered dither matrix
+fn bayer_offset(col: usize, row: usize) -> f32 {
+    const BAYER: [[usize; 4]; 4] = [
+        [0, 8, 2, 10],
+        [12, 4, 14, 6],
+        [3, 11, 1, 9],
+        [15, 7, 13, 5],
+    ];
+    (BAYER[row % 4][col % 4] as f32 + 0.5) / 16.0 - 0.5
+}
+
+/// ## average_samples
+/// Returns the average of the samples of a pixel after leaving out the drop_brightest samples
+/// with the highest luminance. At least one sample is always kept.
+fn average_samples(samples: &mut [Color], drop_brightest: usize) -> Color {
+    let mut kept: &[Color] = samples;
+    if drop_brightest > 0 && samples.len() > 1 {
+        samples.sort_by(|a, b| a.luminance().total_cmp(&b.luminance()));
+        kept = &samples[..samples.len() - drop_brightest.min(samples.len() - 1)];
+    }
+    let mut color: Color = Color::new(0.0, 0.0, 0.0);
+    for sample in kept {
+        color += *sample;
+    }
+    color.try_div(kept.len() as f32).unwrap_or(color)
+}
+
+/// Tests for rendering
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use std::thread;
+
+    #[test]
+    fn framebuffer_new() {
+        let buffer = Framebuffer::new(3, 2);
+
+        assert_eq!(buffer.pixels.len(), 6);
+        assert!(buffer.pixels.iter().all(|p| *p == Color::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn renderer_render_into_matches_render() {
+        let mut renderer = Renderer::new(8, 4, 4, 5);
+        renderer.seed = Some(7);
+        let cam = Camera::default();
+        let scene = Scene::from_objects(vec![]);
+
+        let fresh = renderer.render(&cam, &scene);
+
+        let mut buffer = Framebuffer::new(8, 4);
+        let capacity = buffer.pixels.capacity();
+        let ptr = buffer.pixels.as_ptr();
+        renderer.render_into(&mut buffer, &cam, &scene);
+        assert_eq!(fresh, buffer);
+        renderer.render_into(&mut buffer, &cam, &scene);
+        assert_eq!(fresh, buffer);
+
+        assert_eq!(capacity, buffer.pixels.capacity());
+        assert_eq!(ptr, buffer.pixels.as_ptr());
+    }
+
+    #[test]
+    fn renderer_render_into_wrong_dimensions() {
+        let renderer = Renderer::new(8, 4, 1, 1);
+        let mut buffer = Framebuffer::new(4, 8);
+
+        let result = std::panic::catch_unwind(move || {
+            renderer.render_into(&mut buffer, &Camera::default(), &Scene::from_objects(vec![]))
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn renderer_render_ids() {
+        let renderer = Renderer::new(20, 10, 1, 1);
+        let ids = renderer.render_ids(&Camera::default(), &Scene::new());
+
+        assert_eq!(ids.len(), 200);
+        // The ball is in the middle of the image, the sky in the top corners and the ground at the bottom
+        assert_eq!(ids[5 * 20 + 10], Some(0));
+        assert_eq!(ids[0], None);
+        assert_eq!(ids[19], None);
+        assert_eq!(ids[9 * 20], Some(1));
+    }
+
+    #[test]
+    fn renderer_render_ids_with_lens() {
+        let renderer = Renderer::new(20, 10, 1, 1);
+        let camera = Camera::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0), 90.0, 2.0, 2.0, 1.0);
+        let scene = Scene::new();
+
+        // A wide lens would scatter the rays of pixels on the edge of the ball onto the sky or ground
+        let ids = renderer.render_ids(&camera, &scene);
+        for _ in 0..4 {
+            assert_eq!(renderer.render_ids(&camera, &scene), ids);
+        }
+        assert_eq!(ids[5 * 20 + 10], Some(0));
+    }
+
+    #[test]
+    fn renderer_render_coverage() {
+        use crate::hitables::objects::Sphere;
+
+        let mut renderer = Renderer::new(40, 20, 64, 1);
+        renderer.seed = Some(9);
+        let scene = Scene::from_objects(vec![Box::new(Sphere::new(Vector3::new(0.0, 0.0, -1.0), 0.5))]);
+        let coverage = renderer.render_coverage(&Camera::default(), &scene);
+
+        assert_eq!(coverage.len(), 800);
+        assert_eq!(coverage[10 * 40 + 20], 1.0);
+        assert_eq!(coverage[0], 0.0);
+        // The left edge of the ball crosses the middle row at x = -tan(30 degrees) on the image plane
+        assert!(coverage[10 * 40 + 14] > 0.0 && coverage[10 * 40 + 14] < 1.0);
+    }
+
+    #[test]
+    fn renderer_render_coverage_seeded_with_lens() {
+        use crate::hitables::objects::Sphere;
+        use crate::sampling::SamplingPattern;
+
+        let mut renderer = Renderer::new(16, 8, 16, 1);
+        renderer.seed = Some(481);
+        renderer.sampling = SamplingPattern::Stratified;
+        let camera = Camera::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0), 90.0, 2.0, 0.4, 2.0);
+        let scene = Scene::from_objects(vec![Box::new(Sphere::new(Vector3::new(0.0, 0.0, -1.0), 0.5))]);
+
+        // The out of focus edge of the ball is soft, but the same for every render with the same seed
+        let coverage = renderer.render_coverage(&camera, &scene);
+        assert_eq!(coverage, renderer.render_coverage(&camera, &scene));
+        assert!(coverage.iter().any(|alpha| *alpha > 0.0 && *alpha < 1.0));
+    }
+
+    #[test]
+    fn renderer_render_progressive() {
+        let renderer = Renderer::new(16, 8, 40, 5);
+        let buffer = AccumulationBuffer::new(16, 8);
+        let scene = Scene::new();
+
+        thread::scope(|s| {
+            s.spawn(|| renderer.render_progressive(&buffer, &Camera::default(), &scene));
+            while buffer.passes() == 0 {
+                thread::yield_now();
+            }
+            let snapshot = buffer.current_image();
+            assert_eq!((snapshot.width, snapshot.height, snapshot.pixels.len()), (16, 8, 128));
+            assert!(snapshot.pixels.iter().all(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite()));
+        });
+
+        assert_eq!(buffer.passes(), 40);
+        let image = buffer.current_image();
+        assert!(image.pixels.iter().all(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite()));
+        // The top left corner only sees the sky
+        assert!(image.pixels[0].z > 0.9);
+    }
+
+    #[test]
+    fn ppm_pixel_dithered_gradient() {
+        let channels = |text: String| text.split(' ').map(|c| c.parse::<i64>().unwrap()).collect::<Vec<i64>>();
+        let mut changed = 0;
+        for col in 0..64 {
+            // Gray getting brighter so slowly that it would band without dithering
+            let gray = (0.3 + col as f32 * 0.0005).powi(2);
+            let color = Color::new(gray, gray, gray);
+            for row in 0..4 {
+                let plain = channels(ppm_pixel(color));
+                let dithered = channels(ppm_pixel_dithered(color, col, row, Some(2.0)));
+                for (a, b) in plain.iter().zip(dithered.iter()) {
+                    assert!((a - b).abs() <= 1);
+                    if a != b {
+                        changed += 1;
+                    }
+                }
+            }
+        }
+        assert!(changed > 0);
+    }
+
+    #[test]
+    fn ppm_pixel_dithered_flat_average() {
+        let color = Color::new(0.2, 0.45, 0.7);
+        let plain = ppm_pixel(color).split(' ').map(|c| c.parse::<f32>().unwrap()).collect::<Vec<f32>>();
+        let mut sums = [0.0; 3];
+        for row in 0..4 {
+            for col in 0..4 {
+                for (sum, c) in sums.iter_mut().zip(ppm_pixel_dithered(color, col, row, Some(2.0)).split(' ')) {
+                    *sum += c.parse::<f32>().unwrap() / 16.0;
+                }
+            }
+        }
+        for (average, plain) in sums.iter().zip(plain.iter()) {
+            assert!((average - plain).abs() <= 0.5, "average {} plain {}", average, plain);
+        }
+    }
+
+    #[test]
+    fn ppm_pixel_with_gamma_none() {
+        let color = Color::new(0.25, 2.0, -0.5);
+
+        assert_eq!(ppm_pixel_with_gamma(color, None), "63 255 0");
+        assert_eq!(ppm_pixel_with_gamma(Color::new(0.25, 0.5, 1.0), None), "63 127 255");
+    }
+
+    #[test]
+    fn overflow_clamp_and_rescale() {
+        let color = Color::new(2.0, 1.0, 1.0);
+
+        assert_eq!(Overflow::Clamp.apply(color), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(Overflow::Rescale.apply(color), Color::new(1.0, 0.5, 0.5));
+        assert_eq!(Overflow::Rescale.apply(Color::new(0.5, 0.25, 1.0)), Color::new(0.5, 0.25, 1.0));
+    }
+
+    #[test]
+    fn tone_map_reinhard() {
+        assert_eq!(ToneMap::Reinhard.apply(Color::new(1.0, 0.0, 3.0)), Color::new(0.5, 0.0, 0.75));
+        assert_eq!(ToneMap::None.apply(Color::new(1.0, 0.0, 3.0)), Color::new(1.0, 0.0, 3.0));
+        assert!(ToneMap::Reinhard.apply(Color::new(1000.0, 1000.0, 1000.0)).x < 1.0);
+    }
+
+    #[test]
+    fn tone_map_reinhard_extended() {
+        let tone_map = ToneMap::reinhard_extended(4.0).unwrap();
+
+        assert_eq!(tone_map, ToneMap::ReinhardExtended { white_point: 4.0 });
+        assert_eq!(tone_map.apply(Color::new(4.0, 0.0, 0.0)).x, 1.0);
+        assert_eq!(tone_map.apply(Color::new(0.0, 0.0, 0.0)), Color::new(0.0, 0.0, 0.0));
+        // Dim colors are mapped close to plain Reinhard, brighter ones further above it
+        let dim = tone_map.apply(Color::new(0.1, 0.1, 0.1)).x;
+        assert!((dim - ToneMap::Reinhard.apply(Color::new(0.1, 0.1, 0.1)).x).abs() < 0.01);
+        assert!(tone_map.apply(Color::new(2.0, 2.0, 2.0)).x > ToneMap::Reinhard.apply(Color::new(2.0, 2.0, 2.0)).x);
+    }
+
+    #[test]
+    fn tone_map_reinhard_extended_invalid_white_point() {
+        for white_point in [0.0, -2.0, f32::NAN] {
+            assert!(ToneMap::reinhard_extended(white_point).is_err());
+            // Built directly the variant falls back to plain Reinhard instead of dividing by zero
+            let color = Color::new(0.5, 2.0, 0.0);
+            assert_eq!(ToneMap::ReinhardExtended { white_point }.apply(color), ToneMap::Reinhard.apply(color));
+        }
+    }
+
+    #[test]
+    fn renderer_tone_map_ppm() {
+        use crate::background::Background;
+
+        // A bright empty sky is clipped to white without tone mapping, and kept below white with it
+        let mut renderer = Renderer::new(2, 1, 1, 5);
+        renderer.trace.background = Background::SolidColor(Color::new(3.0, 3.0, 3.0));
+        let render = |renderer: &Renderer| {
+            let mut output: Vec<u8> = Vec::new();
+            renderer.render_ppm(&mut output, &Camera::default(), &Scene::empty()).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        assert_eq!(render(&renderer), "P3\n2 1\n255\n255 255 255\n255 255 255\n");
+        renderer.tone_map = ToneMap::Reinhard;
+        // 3 / (1 + 3) = 0.75, which is sqrt(0.75) = 0.866 after gamma correction
+        assert_eq!(render(&renderer), "P3\n2 1\n255\n221 221 221\n221 221 221\n");
+    }
+
+    #[test]
+    fn render_mode_normals_head_on() {
+        let scene = Scene::from_objects(vec![Box::new(crate::hitables::objects::Sphere::new(Vector3::new(0.0, 0.0, -3.0), 1.0))]);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let color = RenderMode::Normals.first_hit_color(&ray, &scene).unwrap();
+
+        assert!((color - Color::new(0.5, 0.5, 1.0)).normal() < 1e-5);
+        assert_eq!(RenderMode::Normals.first_hit_color(&Ray::new(ray.origin, -ray.direction), &scene), Some(Color::new(0.0, 0.0, 0.0)));
+        assert_eq!(RenderMode::PathTraced.first_hit_color(&ray, &scene), None);
+    }
+
+    #[test]
+    fn render_mode_depth() {
+        let scene = Scene::from_objects(vec![Box::new(crate::hitables::objects::Sphere::new(Vector3::new(0.0, 0.0, -3.0), 1.0))]);
+        // The ray direction isn't a unit vector, the gray follows the distance and not t
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -2.0));
+
+        assert!((RenderMode::Depth { max_distance: 8.0 }.first_hit_color(&ray, &scene).unwrap() - Color::new(0.75, 0.75, 0.75)).normal() < 1e-5);
+        assert_eq!(RenderMode::Depth { max_distance: 1.0 }.first_hit_color(&ray, &scene), Some(Color::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn renderer_render_normals() {
+        let mut renderer = Renderer::new(8, 4, 1, 50);
+        renderer.mode = RenderMode::Normals;
+        let image = renderer.render(&Camera::default(), &Scene::new());
+
+        // With one sample per pixel every pixel is the color of a single unit normal, or black for the sky
+        for color in &image.pixels {
+            let normal: Vector3 = 2.0 * *color - Vector3::new(1.0, 1.0, 1.0);
+            assert!(*color == Color::new(0.0, 0.0, 0.0) || (normal.normal() - 1.0).abs() < 1e-4, "{:?}", color);
+        }
+        assert_eq!(image.pixels[0], Color::new(0.0, 0.0, 0.0));
+        // The ground in the bottom right corner faces up
+        assert!((image.pixels[renderer.width * renderer.height - 1] - Color::new(0.5, 1.0, 0.5)).normal() < 0.1);
+    }
+
+    #[test]
+    fn gamma_correct_values() {
+        assert_eq!(gamma_correct(0.25, Some(1.0)), 0.25);
+        assert_eq!(gamma_correct(0.25, Some(2.0)), 0.5);
+        assert!((gamma_correct(0.25, Some(2.2)) - 0.25_f32.powf(1.0 / 2.2)).abs() < 1e-6);
+        assert_eq!(gamma_correct(0.25, None), 0.25);
+    }
+
+    #[test]
+    fn renderer_set_gamma() {
+        let mut renderer = Renderer::new(1, 1, 1, 1);
+        assert_eq!(renderer.gamma(), Some(2.0));
+
+        assert!(renderer.set_gamma(2.2).is_ok());
+        assert_eq!(renderer.gamma(), Some(2.2));
+        for gamma in [0.0, -1.0, f32::NAN] {
+            assert!(renderer.set_gamma(gamma).is_err());
+        }
+        assert_eq!(renderer.gamma(), Some(2.2));
+        renderer.set_linear();
+        assert_eq!(renderer.gamma(), None);
+    }
+
+    #[test]
+    fn ppm_pixel_with_gamma_two() {
+        for i in 0..=100 {
+            let c = i as f32 / 100.0;
+            let color = Color::new(c, c * 0.5, c * 0.25);
+            let expected = format!("{} {} {}", (255.99*color.x.sqrt()) as usize, (255.99*color.y.sqrt()) as usize, (255.99*color.z.sqrt()) as usize);
+
+            assert_eq!(ppm_pixel_with_gamma(color, Some(2.0)), expected);
+            assert_eq!(ppm_pixel(color), expected);
+        }
+    }
+
+    #[test]
+    fn average_samples_drops_brightest() {
+        let mut samples = vec![Color::new(0.5, 0.5, 0.5); 15];
+        samples.insert(7, Color::new(1.0e6, 1.0e6, 1.0e6));
+
+        let kept = average_samples(&mut samples.clone(), 1);
+        let all = average_samples(&mut samples, 0);
+
+        assert!((kept - Color::new(0.5, 0.5, 0.5)).normal() < 1e-6);
+        assert!(all.x > 1000.0);
+    }
+
+    #[test]
+    fn average_samples_keeps_one() {
+        let mut samples = vec![Color::new(0.2, 0.2, 0.2), Color::new(0.4, 0.4, 0.4)];
+
+        assert_eq!(average_samples(&mut samples, 5), Color::new(0.2, 0.2, 0.2));
+    }
+
+    #[test]
+    fn average_samples_empty() {
+        assert_eq!(average_samples(&mut [], 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn renderer_spectral_matches_rgb_for_gray_scene() {
+        // Enough samples that the difference stays below the tolerance for any seed, at most 0.07 for seeds 0 to 47
+        let mut renderer = Renderer::new(4, 2, 2048, 5);
+        renderer.seed = Some(11);
+        let cam = Camera::default();
+        let scene = Scene::from_objects(vec![]);
+
+        let rgb = renderer.render(&cam, &scene);
+        renderer.spectral = true;
+        let spectral = renderer.render(&cam, &scene);
+
+        let average = |buffer: &Framebuffer| {
+            let mut sum = Color::new(0.0, 0.0, 0.0);
+            for pixel in buffer.pixels.iter() {
+                sum += *pixel;
+            }
+            sum / buffer.pixels.len() as f32
+        };
+        assert!((average(&rgb) - average(&spectral)).normal() < 0.1);
+    }
+
+    #[test]
+    fn renderer_spectral_dispersion() {
+        use crate::hitables::{objects::Sphere, materials::Dielectric};
+        use crate::background::Background;
+
+        // Glass ball in front of a background going from black at the bottom to white at the top.
+        // The ball flips the background upside down, and blue light is bent more than red,
+        // so the top of the ball gets a red tint and the bottom a blue tint.
+        let mut renderer = Renderer::new(16, 8, 1024, 10);
+        renderer.seed = Some(5);
+        renderer.trace.background = Background::Gradient { bottom: Color::new(0.0, 0.0, 0.0), top: Color::new(1.0, 1.0, 1.0) };
+        let cam = Camera::default();
+        let glass_scene = |cauchy_b: f32| Scene::from_objects(vec![Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, -1.0), 0.5, Box::new(Dielectric::with_dispersion(1.5, cauchy_b))))]);
+        // Only the 4x4 pixels looking at the ball are summed, the background around it only adds
+        // the color noise of the random wavelengths
+        let separation = |buffer: &Framebuffer| {
+            let mut sum: f32 = 0.0;
+            for (i, p) in buffer.pixels.iter().enumerate() {
+                let (col, row): (usize, usize) = (i % buffer.width, i / buffer.width);
+                if (6..10).contains(&col) && (2..6).contains(&row) {
+                    let sign: f32 = if row < buffer.height / 2 { 1.0 } else { -1.0 };
+                    sum += sign * (p.x - p.z);
+                }
+            }
+            sum
+        };
+
+        // Over seeds 0 to 23 the dispersive ball separates the colors by 0.14 to 0.79 more than the plain one,
+        // 0.5 on average, so it stays ahead for any seed
+        renderer.spectral = true;
+        let plain = separation(&renderer.render(&cam, &glass_scene(0.0)));
+        let dispersive = separation(&renderer.render(&cam, &glass_scene(0.3)));
+        assert!(dispersive > plain, "dispersive {} plain {}", dispersive, plain);
+
+        // Without spectral mode the dispersion has no effect and gray stays gray
+        renderer.spectral = false;
+        assert!(separation(&renderer.render(&cam, &glass_scene(0.3))).abs() < 1e-3);
+    }
+
+    #[test]
+    fn renderer_render_supersampled() {
+        use crate::hitables::{objects::Sphere, materials::Metal};
+
+        let mut renderer = Renderer::new(4, 4, 1, 8);
+        renderer.seed = Some(5);
+        let cam = Camera::default();
+        let scene = Scene::from_objects(vec![
+            Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, -1.0), 0.5, Box::new(Metal::new(Color::new(0.9, 0.6, 0.3), 0.0)))),
+        ]);
+        let internal = renderer.render(&cam, &scene);
+        let supersampled = renderer.render_supersampled(2, 2, 2, &cam, &scene).unwrap();
+
+        assert_eq!(internal.pixels.len(), 16);
+        assert_eq!((supersampled.width, supersampled.height), (2, 2));
+        for row in 0..2 {
+            for col in 0..2 {
+                let block = internal.pixels[(2 * row) * 4 + 2 * col] + internal.pixels[(2 * row) * 4 + 2 * col + 1]
+                    + internal.pixels[(2 * row + 1) * 4 + 2 * col] + internal.pixels[(2 * row + 1) * 4 + 2 * col + 1];
+                assert!((supersampled.pixels[row * 2 + col] - block / 4.0).normal() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn renderer_render_supersampled_zero_factor() {
+        let renderer = Renderer::new(4, 4, 1, 8);
+
+        assert!(renderer.render_supersampled(2, 2, 0, &Camera::default(), &Scene::new()).is_err());
+    }
+
+    #[test]
+    fn renderer_render_corrected() {
+        let mut renderer = Renderer::new(3, 2, 2, 4);
+        renderer.seed = Some(3);
+        let cam = Camera::default();
+        let scene = Scene::from_objects(vec![]);
+        let linear = renderer.render(&cam, &scene);
+        let corrected = renderer.render_corrected(&cam, &scene);
+
+        assert_eq!(corrected.len(), 6);
+        for (linear, corrected) in linear.pixels.iter().zip(&corrected) {
+            let expected = Color::new(linear.x.sqrt(), linear.y.sqrt(), linear.z.sqrt());
+            assert!((*corrected - expected).normal() < 1e-6);
+            assert_eq!(ppm_pixel(*linear), ppm_pixel_with_gamma(*corrected, None));
+        }
+    }
+
+    #[test]
+    fn renderer_render_rgb8() {
+        let mut renderer = Renderer::new(2, 2, 1, 5);
+        renderer.trace.background = crate::background::Background::SolidColor(Color::new(0.25, 1.0, 0.0));
+        let bytes = renderer.render_rgb8(&Camera::default(), &Scene::from_objects(vec![]));
+
+        assert_eq!(bytes.len(), 12);
+        assert_eq!(bytes, [127, 255, 0].repeat(4));
+    }
+
+    #[test]
+    fn renderer_render_rgb8_matches_ppm() {
+        let mut renderer = Renderer::new(3, 2, 1, 5);
+        renderer.mode = RenderMode::Normals;
+        renderer.seed = Some(5);
+        let (cam, scene) = (Camera::default(), Scene::new());
+        let ppm_bytes: Vec<u8> = renderer.render(&cam, &scene).pixels.iter()
+            .flat_map(|color| ppm_pixel_with_gamma(*color, renderer.gamma()).split(' ').map(|value| value.parse().unwrap()).collect::<Vec<u8>>())
+            .collect();
+        let image = renderer.render_image(&cam, &scene);
+
+        assert_eq!(renderer.render_rgb8(&cam, &scene), ppm_bytes);
+        assert_eq!(image.dimensions(), (3, 2));
+        assert_eq!(image.into_raw(), ppm_bytes);
+    }
+
+    #[test]
+    fn renderer_render_seeded_repeatable_with_fog() {
+        use crate::hitables::{objects::Sphere, medium::ConstantMedium};
+
+        let mut renderer = Renderer::new(6, 4, 4, 8);
+        renderer.seed = Some(540);
+        let cam = Camera::default();
+        let scene = Scene::from_objects(vec![
+            Box::new(ConstantMedium::new(Box::new(Sphere::new(Vector3::new(0.0, 0.0, -1.0), 0.5)), 2.0, Color::new(0.8, 0.8, 0.8)).unwrap()),
+            Box::new(Sphere::new(Vector3::new(0.0, -100.5, -1.0), 100.0)),
+        ]);
+        let first = renderer.render(&cam, &scene);
+
+        assert_eq!(first, renderer.render(&cam, &scene));
+        renderer.seed = Some(541);
+        assert_ne!(first, renderer.render(&cam, &scene));
+    }
+
+    #[test]
+    fn renderer_render_seeded_repeatable() {
+        use crate::hitables::{objects::Sphere, materials::{Dielectric, Metal}};
+
+        let mut renderer = Renderer::new(6, 4, 4, 8);
+        renderer.seed = Some(21);
+        let cam = Camera::new(Vector3::new(0.0, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0), 90.0, 1.5, 0.1, 2.0);
+        let mut scene = Scene::new();
+        scene.add(Box::new(Sphere::with_material(Vector3::new(1.0, 0.0, -1.0), 0.5, Box::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.3)))))
+            .add(Box::new(Sphere::with_material(Vector3::new(-1.0, 0.0, -1.0), 0.5, Box::new(Dielectric::new(1.5)))));
+
+        let first = renderer.render(&cam, &scene);
+        assert_eq!(first, renderer.render(&cam, &scene));
+
+        renderer.seed = Some(22);
+        assert_ne!(first, renderer.render(&cam, &scene));
+    }
+
+    #[test]
+    fn renderer_render_parallel_rows() {
+        use crate::hitables::{objects::Sphere, materials::Metal};
+
+        let mut renderer = Renderer::new(4, 4, 4, 8);
+        renderer.seed = Some(9);
+        let cam = Camera::default();
+        let scene = Scene::from_objects(vec![
+            Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, -1.0), 0.5, Box::new(Metal::new(Color::new(0.9, 0.6, 0.3), 0.0)))),
+        ]);
+
+        renderer.threads = 1;
+        let serial = renderer.render(&cam, &scene);
+        renderer.threads = 4;
+        let parallel = renderer.render(&cam, &scene);
+
+        assert_eq!(parallel.pixels.len(), 16);
+        assert_eq!(parallel, serial);
+    }
+
+    #[test]
+    fn renderer_thread_pool_kept() {
+        let mut renderer = Renderer::new(4, 2, 1, 2);
+        renderer.threads = 3;
+        let pool = |renderer: &Renderer| {
+            renderer.render(&Camera::default(), &Scene::new());
+            THREAD_POOLS.lock().unwrap()[&3].clone()
+        };
+        let first = pool(&renderer);
+
+        assert!(Arc::ptr_eq(&first, &pool(&renderer)));
+        assert_eq!(first.current_num_threads(), 3);
+        assert_eq!(renderer.install(rayon::current_num_threads), 3);
+    }
+
+    #[test]
+    fn renderer_seeds_dont_overlap() {
+        // Row 1 of seed 7 used to be seeded like row 0 of seed 8, giving the same random numbers
+        let mut renderer = Renderer::new(4, 4, 1, 2);
+        renderer.seed = Some(7);
+        let mut row_1: StdRng = renderer.row_rng(1);
+        renderer.seed = Some(8);
+        let mut row_0: StdRng = renderer.row_rng(0);
+        assert_ne!(row_1.gen::<u64>(), row_0.gen::<u64>());
+    }
+
+    #[test]
+    fn renderer_tile_candidates() {
+        use crate::hitables::objects::Sphere;
+
+        let mut renderer = Renderer::new(40, 20, 1, 8);
+        renderer.tile_size = 10;
+        let cam = Camera::default();
+        // A small sphere seen in the top left tile, and a big one in the middle of the image with a box
+        // just reaching the corner tiles
+        let scene = Scene::from_objects(vec![
+            Box::new(Sphere::new(Vector3::new(-1.5, 0.5, -1.0), 0.1)),
+            Box::new(Sphere::new(Vector3::new(0.0, 0.0, -1.0), 0.5)),
+        ]);
+
+        assert_eq!(renderer.tile_candidates(0, 0, &cam, &scene), Some(vec![0, 1]));
+        assert_eq!(renderer.tile_candidates(1, 0, &cam, &scene), Some(vec![1]));
+        assert_eq!(renderer.tile_candidates(2, 1, &cam, &scene), Some(vec![1]));
+    }
+
+    #[test]
+    fn renderer_render_tiled_culling_matches() {
+        use crate::hitables::{objects::Sphere, materials::Metal};
+
+        let mut renderer = Renderer::new(45, 30, 2, 8);
+        renderer.seed = Some(4);
+        renderer.tile_size = 8;
+        let cam = Camera::default();
+        let scene = Scene::from_objects(vec![
+            Box::new(Sphere::with_material(Vector3::new(-0.5, 0.0, -1.0), 0.5, Box::new(Metal::new(Color::new(0.9, 0.6, 0.3), 0.0)))),
+            Box::new(Sphere::with_material(Vector3::new(1.5, 0.6, -1.0), 0.2, Box::new(Metal::new(Color::new(0.3, 0.6, 0.9), 0.0)))),
+            Box::new(Sphere::with_material(Vector3::new(0.0, -100.5, -1.0), 100.0, Box::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.0)))),
+        ]);
+
+        let plain = renderer.render_tiled(&cam, &scene, &RenderStats::new());
+        renderer.tile_culling = true;
+        let culled = renderer.render_tiled(&cam, &scene, &RenderStats::new());
+
+        assert_eq!(plain, culled);
+    }
+
+    #[test]
+    fn renderer_render_tiled_thread_count() {
+        use crate::hitables::{objects::Sphere, materials::Metal};
+
+        // Perfect mirrors scatter without random numbers, so bounced rays are the same every time
+        let mut renderer = Renderer::new(45, 30, 4, 8);
+        renderer.seed = Some(3);
+        renderer.tile_size = 8;
+        let cam = Camera::default();
+        let scene = Scene::from_objects(vec![
+            Box::new(Sphere::with_material(Vector3::new(-0.5, 0.0, -1.0), 0.5, Box::new(Metal::new(Color::new(0.9, 0.6, 0.3), 0.0)))),
+            Box::new(Sphere::with_material(Vector3::new(0.5, 0.0, -1.0), 0.5, Box::new(Metal::new(Color::new(0.3, 0.6, 0.9), 0.0)))),
+            Box::new(Sphere::with_material(Vector3::new(0.0, -100.5, -1.0), 100.0, Box::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.0)))),
+        ]);
+
+        renderer.threads = 1;
+        let single_stats = RenderStats::new();
+        let single = renderer.render_tiled(&cam, &scene, &single_stats);
+        renderer.threads = 4;
+        let multi_stats = RenderStats::new();
+        let multi = renderer.render_tiled(&cam, &scene, &multi_stats);
+
+        assert_eq!(single, multi);
+        assert_eq!(single_stats.primary_rays.load(Ordering::Relaxed), 45 * 30 * 4);
+        assert_eq!(single_stats.primary_rays.load(Ordering::Relaxed), multi_stats.primary_rays.load(Ordering::Relaxed));
+        assert_eq!(single_stats.rays.load(Ordering::Relaxed), multi_stats.rays.load(Ordering::Relaxed));
+        assert!(single_stats.rays.load(Ordering::Relaxed) > single_stats.primary_rays.load(Ordering::Relaxed));
+        assert_eq!(single_stats.tiles.load(Ordering::Relaxed), 6 * 4);
+        assert_eq!(multi_stats.tiles.load(Ordering::Relaxed), 6 * 4);
+    }
+
+    #[test]
+    fn renderer_render_tiled_bounce_limit() {
+        use crate::hitables::{objects::Sphere, materials::{FreeMirror, Metal}};
+
+        // The camera is inside a closed mirror ball, so no path would ever end without the limit
+        let mut renderer = Renderer::new(4, 2, 2, 5);
+        renderer.trace.free_bounces = usize::MAX;
+        renderer.trace.bounce_limit = 100;
+        let scene = Scene::from_objects(vec![
+            Box::new(Sphere::with_material(Vector3::new(0.0, 0.0, 0.0), 10.0, Box::new(FreeMirror(Metal::new(Color::new(1.0, 1.0, 1.0), 0.0))))),
+        ]);
+        let stats = RenderStats::new();
+        let image = renderer.render_tiled(&Camera::default(), &scene, &stats);
+
+        assert_eq!(image.pixels.len(), 8);
+        assert_eq!(stats.stopped_paths.load(Ordering::Relaxed), 16);
+        assert!(stats.stopped_pixel.lock().unwrap().is_some_and(|(col, row)| col < 4 && row < 2));
+        assert_eq!(stats.rays.load(Ordering::Relaxed), 1600);
+    }
+
+    #[test]
+    fn renderer_render_ppm_tiled() {
+        let mut renderer = Renderer::new(203, 150, 1, 2);
+        renderer.tile_size = 16;
+        let path = std::env::temp_dir().join(format!("render_ppm_tiled_{}.ppm", std::process::id()));
+        let mut file = io::BufWriter::new(std::fs::File::create(&path).unwrap());
+        renderer.render_ppm(&mut file, &Camera::default(), &Scene::from_objects(vec![])).unwrap();
+        drop(file);
+
+        let ppm = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut lines = ppm.lines();
+        assert_eq!(lines.next(), Some("P3"));
+        assert_eq!(lines.next(), Some("203 150"));
+        assert_eq!(lines.next(), Some("255"));
+        let pixels: Vec<Vec<usize>> = lines.map(|line| line.split(' ').map(|c| c.parse().unwrap()).collect()).collect();
+        assert_eq!(pixels.len(), 203 * 150);
+        assert!(pixels.iter().all(|p| p.len() == 3));
+
+        // The sky gets whiter towards the bottom, so the red channel grows row by row
+        let red_of_row = |row: usize| pixels[row * 203..(row + 1) * 203].iter().map(|p| p[0]).sum::<usize>();
+        for row in 1..150 {
+            assert!(red_of_row(row) >= red_of_row(row - 1));
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use rand::{Rng, rngs::StdRng};
+
+/// ## SamplingPattern
+/// How the points within a pixel that the samples are taken at are chosen
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum SamplingPattern {
+    /// Uniformly random points from the random number generator
+    #[default]
+    Random,
+    /// Points from a precomputed blue noise table, the same for every render.
+    /// The samples of a pixel go through the table in order, shifted by a table entry picked
+    /// from the position of the pixel in an 8x8 tile so neighbouring pixels don't sample alike.
+    BlueNoiseTable,
+    /// One uniformly random point in each cell of a grid over the pixel, as large a square grid as
+    /// the samples per pixel fill. Samples left over when the count isn't a square are random points.
+    Stratified,
+}
+
+impl SamplingPattern {
+    /// ## offset
+    /// Returns the offset within the pixel in column col and row row of sample number sample
+    /// out of samples_per_pixel, with both coordinates in 0..1
+    pub fn offset(&self, col: usize, row: usize, sample: usize, samples_per_pixel: usize, rng: &mut StdRng) -> (f32, f32) {
+        match self {
+            SamplingPattern::Random => (rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0)),
+            SamplingPattern::Stratified => {
+                let cells: usize = samples_per_pixel.isqrt();
+                if sample >= cells * cells {
+                    return (rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0));
+                }
+                let (cell_x, cell_y): (usize, usize) = (sample % cells, sample / cells);
+                let jitter = |cell: usize, rng: &mut StdRng| ((cell as f32 + rng.gen_range(0.0..1.0)) / cells as f32).min(1.0 - f32::EPSILON);
+                (jitter(cell_x, rng), jitter(cell_y, rng))
+            }
+            SamplingPattern::BlueNoiseTable => {
+                let (x, y) = BLUE_NOISE[sample % BLUE_NOISE.len()];
+                let (shift_x, shift_y) = BLUE_NOISE[(row % 8) * 8 + col % 8];
+                (wrap(x + shift_x), wrap(y + shift_y))
+            }
+        }
+    }
+}
+
+/// ## GOLDEN_GAMMA
+/// 2^64 divided by the golden ratio, the odd step SplitMix64 takes between its states
+pub const GOLDEN_GAMMA: u64 = 0x9e3779b97f4a7c15;
+
+/// ## split_mix64
+/// Returns value scrambled with the SplitMix64 finalizer, where inputs differing in a single bit
+/// give unrelated outputs. Used to turn seeds into well spread random numbers.
+pub fn split_mix64(value: u64) -> u64 {
+    let mut z: u64 = value.wrapping_add(GOLDEN_GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// ## stream_seed
+/// Returns the seed of random number stream number stream of a render seeded with seed.
+/// Neighbouring seeds and streams give unrelated seeds, so the streams of one seed don't overlap those of the next.
+pub fn stream_seed(seed: u64, stream: u64) -> u64 {
+    split_mix64(seed ^ stream.wrapping_mul(GOLDEN_GAMMA))
+}
+
+/// ## wrap
+/// Returns the fractional part of a non-negative value, kept below 1 after rounding
+fn wrap(value: f32) -> f32 {
+    value.fract().min(1.0 - f32::EPSILON)
+}
+
+/// 64 points in the unit square picked with Mitchell's best candidate algorithm, wrapping around
+/// the edges. Every prefix of the table is spread out evenly, so any sample count gets good coverage.
+const BLUE_NOISE: [(f32, f32); 64] = [
+    (0.2622, 0.9574), (0.7535, 0.3333), (0.7023, 0.7648), (0.2233, 0.4443),
+    (0.5703, 0.0811), (0.9748, 0.6611), (0.9637, 0.1156), (0.4166, 0.7139),
+    (0.4334, 0.3000), (0.0426, 0.9017), (0.1897, 0.7108), (0.0040, 0.3590),
+    (0.7228, 0.5445), (0.7709, 0.0610), (0.2019, 0.1727), (0.5087, 0.5326),
+    (0.4858, 0.8985), (0.8184, 0.8982), (0.4143, 0.1226), (0.0727, 0.5250),
+    (0.5898, 0.3776), (0.8661, 0.4712), (0.6454, 0.2236), (0.6581, 0.9318),
+    (0.2672, 0.5864), (0.8766, 0.2385), (0.8276, 0.6747), (0.3838, 0.4493),
+    (0.3223, 0.8163), (0.6123, 0.6605), (0.1307, 0.0267), (0.2820, 0.2976),
+    (0.1147, 0.2710), (0.0629, 0.7646), (0.5807, 0.8060), (0.9228, 0.8112),
+    (0.1897, 0.8478), (0.0796, 0.1580), (0.4034, 0.0016), (0.9141, 0.0053),
+    (0.7650, 0.2181), (0.3947, 0.5817), (0.5051, 0.1992), (0.2932, 0.0860),
+    (0.0949, 0.6539), (0.6995, 0.4325), (0.1181, 0.3838), (0.3553, 0.2155),
+    (0.2994, 0.7097), (0.8824, 0.3534), (0.9660, 0.5489), (0.9933, 0.2447),
+    (0.8089, 0.7926), (0.4849, 0.4095), (0.4968, 0.6407), (0.6069, 0.4844),
+    (0.8657, 0.5759), (0.0107, 0.0237), (0.6648, 0.1202), (0.5635, 0.9704),
+    (0.1729, 0.5429), (0.4296, 0.8155), (0.5455, 0.2883), (0.8602, 0.1231),
+];
+
+/// Tests for SamplingPattern enum
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn sampling_pattern_blue_noise_table_deterministic() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut other_rng = StdRng::seed_from_u64(2);
+        for (col, row, sample) in [(0, 0, 0), (3, 7, 5), (12, 40, 63), (100, 9, 200)] {
+            let offset = SamplingPattern::BlueNoiseTable.offset(col, row, sample, 64, &mut rng);
+
+            assert_eq!(offset, SamplingPattern::BlueNoiseTable.offset(col, row, sample, 64, &mut other_rng));
+            assert_eq!(offset, SamplingPattern::BlueNoiseTable.offset(col + 8, row + 16, sample + 64, 64, &mut rng));
+        }
+    }
+
+    #[test]
+    fn sampling_pattern_blue_noise_table_within_pixel() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for col in 0..8 {
+            for row in 0..8 {
+                for sample in 0..64 {
+                    let (x, y) = SamplingPattern::BlueNoiseTable.offset(col, row, sample, 64, &mut rng);
+                    assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sampling_pattern_blue_noise_table_neighbours_differ() {
+        let mut rng = StdRng::seed_from_u64(1);
+
+        assert_ne!(SamplingPattern::BlueNoiseTable.offset(0, 0, 0, 1, &mut rng),
+                   SamplingPattern::BlueNoiseTable.offset(1, 0, 0, 1, &mut rng));
+    }
+
+    #[test]
+    fn sampling_pattern_stratified_one_per_cell() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let offsets: Vec<(f32, f32)> = (0..16).map(|sample| SamplingPattern::Stratified.offset(3, 5, sample, 16, &mut rng)).collect();
+
+        assert_eq!(offsets.len(), 16);
+        let mut cells: Vec<usize> = offsets.iter()
+            .map(|(x, y)| {
+                assert!((0.0..1.0).contains(x) && (0.0..1.0).contains(y));
+                (y * 4.0) as usize * 4 + (x * 4.0) as usize
+            })
+            .collect();
+        cells.sort();
+        assert_eq!(cells, (0..16).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn sampling_pattern_stratified_not_square() {
+        // 10 samples make a 3x3 grid and one random sample on top
+        let mut rng = StdRng::seed_from_u64(2);
+        let offsets: Vec<(f32, f32)> = (0..10).map(|sample| SamplingPattern::Stratified.offset(0, 0, sample, 10, &mut rng)).collect();
+
+        for (sample, (x, y)) in offsets.iter().enumerate() {
+            assert!((0.0..1.0).contains(x) && (0.0..1.0).contains(y));
+            if sample < 9 {
+                assert_eq!(((x * 3.0) as usize, (y * 3.0) as usize), (sample % 3, sample / 3));
+            }
+        }
+    }
+
+    #[test]
+    fn sampling_pattern_stratified_single_sample() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..100 {
+            let (x, y) = SamplingPattern::Stratified.offset(0, 0, 0, 1, &mut rng);
+            assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+        }
+        assert_ne!(SamplingPattern::Stratified.offset(0, 0, 0, 1, &mut rng), SamplingPattern::Stratified.offset(0, 0, 0, 1, &mut rng));
+    }
+
+    #[test]
+    fn split_mix64_spreads_neighbours() {
+        // Neighbouring inputs differ in about half of the output bits
+        for value in 0..100u64 {
+            let changed = (split_mix64(value) ^ split_mix64(value + 1)).count_ones();
+            assert!((12..=52).contains(&changed), "{} bits changed", changed);
+        }
+        assert_eq!(split_mix64(7), split_mix64(7));
+    }
+
+    #[test]
+    fn stream_seed_no_overlap() {
+        // With seeds simply added to the stream, row 1 of seed 0 would be row 0 of seed 1
+        let seeds: std::collections::HashSet<u64> = (0..32u64)
+            .flat_map(|seed| (0..32u64).map(move |stream| stream_seed(seed, stream)))
+            .collect();
+        assert_eq!(seeds.len(), 32 * 32);
+        assert_eq!(stream_seed(3, 4), stream_seed(3, 4));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::vector::*;
+
+use rand::Rng;
+use std::sync::OnceLock;
+
+/// ## MIN_WAVELENGTH
+/// Shortest wavelength in nanometers sampled in spectral mode
+pub const MIN_WAVELENGTH: f32 = 380.0;
+
+/// ## MAX_WAVELENGTH
+/// Longest wavelength in nanometers sampled in spectral mode
+pub const MAX_WAVELENGTH: f32 = 730.0;
+
+/// ## sample_wavelength
+/// Returns a wavelength picked uniformly between MIN_WAVELENGTH and MAX_WAVELENGTH
+pub fn sample_wavelength<R: Rng>(rng: &mut R) -> f32 {
+    rng.gen_range(MIN_WAVELENGTH..MAX_WAVELENGTH)
+}
+
+/// ## rgb_to_spectral
+/// Returns the value at the given wavelength of a smooth spectrum matching a linear rgb Color.
+/// The red, green and blue parts of the spectrum always add up to one, so white gives a flat spectrum of ones.
+pub fn rgb_to_spectral(color: Color, wavelength: f32) -> f32 {
+    let red: f32 = smoothstep(560.0, 610.0, wavelength);
+    let blue: f32 = 1.0 - smoothstep(470.0, 520.0, wavelength);
+    let green: f32 = 1.0 - red - blue;
+    color.x * red + color.y * green + color.z * blue
+}
+
+/// ## at_wavelength
+/// Returns the Color as it is seen by a ray of the given wavelength, a gray with the spectral value
+/// at the wavelength. Without a wavelength the Color is returned as it is.
+pub fn at_wavelength(color: Color, wavelength: Option<f32>) -> Color {
+    match wavelength {
+        Some(nm) => {
+            let value: f32 = rgb_to_spectral(color, nm);
+            Color::new(value, value, value)
+        }
+        None => color,
+    }
+}
+
+/// ## spectral_weight
+/// Returns the linear rgb Color a sample of the given wavelength with radiance one contributes.
+/// Averaged over uniformly sampled wavelengths the weights give white, so a flat spectrum looks
+/// the same as in rgb mode.
+pub fn spectral_weight(wavelength: f32) -> Color {
+    static WHITE: OnceLock<Color> = OnceLock::new();
+    let white: Color = *WHITE.get_or_init(|| {
+        let steps: usize = 1000;
+        let mut sum: Color = Color::new(0.0, 0.0, 0.0);
+        for i in 0..steps {
+            let nm: f32 = MIN_WAVELENGTH + (i as f32 + 0.5) / steps as f32 * (MAX_WAVELENGTH - MIN_WAVELENGTH);
+            sum += xyz_to_rgb(color_matching(nm));
+        }
+        sum / steps as f32
+    });
+    let rgb: Color = xyz_to_rgb(color_matching(wavelength));
+    Color::new(rgb.x / white.x, rgb.y / white.y, rgb.z / white.z)
+}
+
+/// ## color_matching
+/// Returns the CIE 1931 color matching functions (x, y, z) at the given wavelength,
+/// using the multi-lobe gaussian fit by Wyman, Sloan and Shirley
+fn color_matching(wavelength: f32) -> Vector3 {
+    let lobe = |mean: f32, below: f32, above: f32| {
+        let sigma: f32 = if wavelength < mean { below } else { above };
+        let t: f32 = (wavelength - mean) / sigma;
+        (-0.5 * t*t).exp()
+    };
+    Vector3::new(
+        1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7) - 0.065 * lobe(501.1, 20.4, 26.2),
+        0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1),
+        1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8),
+    )
+}
+
+/// ## xyz_to_rgb
+/// Converts a CIE XYZ color to linear sRGB
+fn xyz_to_rgb(xyz: Vector3) -> Color {
+    Color::new(
+        3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    )
+}
+
+/// ## smoothstep
+/// Returns 0 below edge0, 1 above edge1 and a smooth transition in between
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t: f32 = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Tests for spectral conversions
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgb_to_spectral_white_is_flat() {
+        for i in 0..=35 {
+            let nm = MIN_WAVELENGTH + i as f32 * 10.0;
+            assert!((rgb_to_spectral(Color::new(1.0, 1.0, 1.0), nm) - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn rgb_to_spectral_primaries() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+
+        assert!(rgb_to_spectral(red, 650.0) > 0.99);
+        assert!(rgb_to_spectral(red, 450.0) < 0.01);
+        assert!(rgb_to_spectral(blue, 450.0) > 0.99);
+        assert!(rgb_to_spectral(blue, 650.0) < 0.01);
+    }
+
+    #[test]
+    fn spectral_weight_averages_to_white() {
+        let steps = 700;
+        let mut sum = Color::new(0.0, 0.0, 0.0);
+        for i in 0..steps {
+            let nm = MIN_WAVELENGTH + (i as f32 + 0.5) / steps as f32 * (MAX_WAVELENGTH - MIN_WAVELENGTH);
+            sum += spectral_weight(nm);
+        }
+        let average = sum / steps as f32;
+
+        assert!((average - Color::new(1.0, 1.0, 1.0)).normal() < 0.01);
+    }
+
+    #[test]
+    fn spectral_weight_hue() {
+        let red = spectral_weight(650.0);
+        let blue = spectral_weight(450.0);
+
+        assert!(red.x > red.y && red.x > red.z);
+        assert!(blue.z > blue.x && blue.z > blue.y);
+    }
+
+    #[test]
+    fn at_wavelength_rgb_unchanged() {
+        let color = Color::new(0.2, 0.4, 0.8);
+
+        assert_eq!(at_wavelength(color, None), color);
+        let gray = at_wavelength(color, Some(450.0));
+        assert_eq!(gray.x, gray.y);
+        assert_eq!(gray.y, gray.z);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{vector::*, content_hash::ContentHasher, perlin::Perlin};
+
+use std::fmt::Debug;
+
+/// ## Texture
+/// Describes the color of a surface, which may change over it.
+pub trait Texture: Debug + Send + Sync {
+    /// Returns the color at the surface coordinates u and v, both between 0 and 1, of the point p
+    fn value(&self, u: f32, v: f32, p: Vector3) -> Color;
+    /// Adds the type and parameters of the texture to a hash describing the scene
+    fn hash_content(&self, hasher: &mut ContentHasher);
+}
+
+/// ## SolidColor
+/// The same color all over the surface
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SolidColor {
+    pub color: Color,
+}
+
+impl SolidColor {
+    /// ## new
+    /// Returns a SolidColor of the given color
+    pub fn new(color: Color) -> SolidColor {
+        SolidColor {
+            color,
+        }
+    }
+}
+
+impl Texture for SolidColor {
+    /// ## value
+    /// Returns the color wherever the surface is
+    fn value(&self, _u: f32, _v: f32, _p: Vector3) -> Color {
+        self.color
+    }
+
+    /// ## hash_content
+    /// Adds the color to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_vector(self.color);
+    }
+}
+
+/// ## CheckerTexture
+/// A checker pattern in space alternating between the odd and even textures.
+/// The pattern is made from the point and not the surface coordinates, so it fits any shape,
+/// with squares pi / scale wide.
+#[derive(Debug)]
+pub struct CheckerTexture {
+    pub odd: Box<dyn Texture>,
+    pub even: Box<dyn Texture>,
+    pub scale: f32,
+}
+
+impl CheckerTexture {
+    /// ## new
+    /// Returns a CheckerTexture alternating between the odd and even colors
+    pub fn new(odd: Color, even: Color, scale: f32) -> CheckerTexture {
+        CheckerTexture::with_textures(Box::new(SolidColor::new(odd)), Box::new(SolidColor::new(even)), scale)
+    }
+
+    /// ## with_textures
+    /// Returns a CheckerTexture alternating between the odd and even textures
+    pub fn with_textures(odd: Box<dyn Texture>, even: Box<dyn Texture>, scale: f32) -> CheckerTexture {
+        CheckerTexture {
+            odd,
+            even,
+            scale,
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    /// ## value
+    /// Returns the value of the odd texture where sin(scale*x)*sin(scale*y)*sin(scale*z) is negative, otherwise of the even one
+    fn value(&self, u: f32, v: f32, p: Vector3) -> Color {
+        let sines: f32 = (self.scale * p.x).sin() * (self.scale * p.y).sin() * (self.scale * p.z).sin();
+        if sines < 0.0 {
+            self.odd.value(u, v, p)
+        } else {
+            self.even.value(u, v, p)
+        }
+    }
+
+    /// ## hash_content
+    /// Adds both textures and the scale to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("CheckerTexture");
+        self.odd.hash_content(hasher);
+        self.even.hash_content(hasher);
+        hasher.write_f32(self.scale);
+    }
+}
+
+/// ## ImageTexture
+/// A picture wrapped around a surface using its (u, v) coordinates, with u = 0, v = 0 at the bottom left
+/// of the picture. A texture without a picture, like one that failed to load, is middle gray all over.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    /// Colors of the picture row by row from the top left, with channels between 0 and 1
+    pixels: Vec<Color>,
+}
+
+impl ImageTexture {
+    /// ## new
+    /// Loads a PNG or JPEG picture from the file at path
+    pub fn new(path: &str) -> Result<ImageTexture, image::ImageError> {
+        let picture: image::RgbImage = image::open(path)?.into_rgb8();
+        let pixels: Vec<Color> = picture.pixels()
+            .map(|pixel| Color::new(pixel[0] as f32, pixel[1] as f32, pixel[2] as f32) / 255.0)
+            .collect();
+        Ok(ImageTexture::from_pixels(picture.width() as usize, picture.height() as usize, pixels))
+    }
+
+    /// ## new_or_gray
+    /// Loads a picture like new, but prints a warning and returns a middle gray texture if it can't be loaded
+    pub fn new_or_gray(path: &str) -> ImageTexture {
+        ImageTexture::new(path).unwrap_or_else(|error| {
+            eprintln!("Warning: failed to load texture {}: {}", path, error);
+            ImageTexture::from_pixels(0, 0, Vec::new())
+        })
+    }
+
+    /// ## from_pixels
+    /// Returns a texture of a picture width by height pixels big, given row by row from the top left
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> ImageTexture {
+        assert_eq!(pixels.len(), width * height, "Expected {} pixels for a {}x{} picture", width * height, width, height);
+        ImageTexture {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    /// ## value
+    /// Returns the color of the pixel at (u, v), with u and v clamped to between 0 and 1.
+    /// v is flipped since pictures are stored from the top down.
+    fn value(&self, u: f32, v: f32, _p: Vector3) -> Color {
+        if self.pixels.is_empty() {
+            return Color::new(0.5, 0.5, 0.5);
+        }
+        let u: f32 = u.clamp(0.0, 1.0);
+        let v: f32 = 1.0 - v.clamp(0.0, 1.0);
+        let x: usize = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y: usize = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.pixels[y * self.width + x]
+    }
+
+    /// ## hash_content
+    /// Adds the size and every pixel of the picture to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("ImageTexture");
+        hasher.write_u64(self.width as u64);
+        hasher.write_u64(self.height as u64);
+        for pixel in &self.pixels {
+            hasher.write_vector(*pixel);
+        }
+    }
+}
+
+/// ## NoiseTexture
+/// A marble like pattern of light and dark bands along z, made wavy by Perlin turbulence.
+/// scale decides how close the bands are, and the same seed always gives the same pattern.
+#[derive(PartialEq, Debug, Clone)]
+pub struct NoiseTexture {
+    pub scale: f32,
+    noise: Perlin,
+}
+
+impl NoiseTexture {
+    /// ## new
+    /// Returns a NoiseTexture with the given scale, using Perlin noise made with seed
+    pub fn new(scale: f32, seed: u64) -> NoiseTexture {
+        NoiseTexture {
+            scale,
+            noise: Perlin::new(seed),
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    /// ## value
+    /// Returns a gray between black and white of 0.5 * (1 + sin(scale*z + 10*turbulence)) at p
+    fn value(&self, _u: f32, _v: f32, p: Vector3) -> Color {
+        let t: f32 = 0.5 * (1.0 + (self.scale * p.z + 10.0 * self.noise.turbulence(p, 7)).sin());
+        Color::new(t, t, t)
+    }
+
+    /// ## hash_content
+    /// Adds the scale and the seed of the noise to the hash
+    fn hash_content(&self, hasher: &mut ContentHasher) {
+        hasher.write_str("NoiseTexture");
+        hasher.write_f32(self.scale);
+        hasher.write_u64(self.noise.seed());
+    }
+}
+
+/// Tests for textures
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solid_color_value() {
+        let texture = SolidColor::new(Color::new(0.1, 0.2, 0.3));
+
+        assert_eq!(texture.value(0.0, 0.0, Vector3::new(0.0, 0.0, 0.0)), Color::new(0.1, 0.2, 0.3));
+        assert_eq!(texture.value(0.7, 0.2, Vector3::new(5.0, -3.0, 1.0)), Color::new(0.1, 0.2, 0.3));
+    }
+
+    #[test]
+    fn checker_texture_value() {
+        let odd = Color::new(0.0, 0.0, 0.0);
+        let even = Color::new(1.0, 1.0, 1.0);
+        let texture = CheckerTexture::new(odd, even, 1.0);
+        let corner = Vector3::new(1.0, 1.0, 1.0);
+
+        assert_eq!(texture.value(0.0, 0.0, corner), even);
+        // Crossing into the next square along any one axis switches color
+        assert_eq!(texture.value(0.0, 0.0, corner.with_x(-1.0)), odd);
+        assert_eq!(texture.value(0.0, 0.0, corner.with_y(4.0)), odd);
+        assert_eq!(texture.value(0.0, 0.0, Vector3::new(-1.0, -1.0, 1.0)), even);
+    }
+
+    #[test]
+    fn image_texture_load() {
+        let path = std::env::temp_dir().join(format!("image_texture_load_{}.png", std::process::id()));
+        let red = image::Rgb([255, 0, 0]);
+        let blue = image::Rgb([0, 0, 255]);
+        // Red in the top left and bottom right, blue in the other corners
+        let picture = image::RgbImage::from_fn(2, 2, |x, y| if x == y { red } else { blue });
+        picture.save(&path).unwrap();
+        let texture = ImageTexture::new(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        let texture = texture.unwrap();
+        let p = Vector3::new(0.0, 0.0, 0.0);
+
+        assert_eq!(texture.value(0.25, 0.25, p), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(texture.value(0.25, 0.75, p), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.value(0.75, 0.25, p), Color::new(1.0, 0.0, 0.0));
+        // Coordinates outside the picture are clamped to its edges
+        assert_eq!(texture.value(-3.0, 2.0, p), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.value(1.0, 0.0, p), Color::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn image_texture_missing_file() {
+        assert!(ImageTexture::new("no/such/texture.png").is_err());
+
+        let texture = ImageTexture::new_or_gray("no/such/texture.png");
+        assert_eq!(texture.value(0.3, 0.6, Vector3::new(0.0, 0.0, 0.0)), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn noise_texture_value() {
+        let texture = NoiseTexture::new(4.0, 42);
+        let points: Vec<Vector3> = (0..500)
+            .map(|i| Vector3::new(i as f32 * 0.137, (i % 17) as f32 * -0.61, (i % 29) as f32 * 0.29))
+            .collect();
+
+        for p in &points {
+            let value = texture.value(0.0, 0.0, *p);
+            assert_eq!(value, texture.value(0.0, 0.0, *p));
+            assert_eq!(value, NoiseTexture::new(4.0, 42).value(0.0, 0.0, *p));
+            assert!((0.0..=1.0).contains(&value.x) && value.x == value.y && value.y == value.z);
+        }
+        // The pattern isn't flat
+        let values: Vec<f32> = points.iter().map(|p| texture.value(0.0, 0.0, *p).x).collect();
+        assert!(values.iter().any(|value| *value < 0.2) && values.iter().any(|value| *value > 0.8));
+    }
+
+    #[test]
+    fn checker_texture_content_hash() {
+        let hash = |texture: &CheckerTexture| {
+            let mut hasher = ContentHasher::new();
+            texture.hash_content(&mut hasher);
+            hasher.finish()
+        };
+        let a = CheckerTexture::new(Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0), 1.0);
+        let swapped = CheckerTexture::new(Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0), 1.0);
+
+        assert_eq!(hash(&a), hash(&CheckerTexture::new(Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0), 1.0)));
+        assert_ne!(hash(&a), hash(&swapped));
+    }
+}
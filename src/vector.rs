@@ -15,18 +15,47 @@ impl Vector3 {
     /// Returns a Vector3 with x, y, z given as arguments
     pub fn new(x: f32, y: f32, z: f32) -> Vector3 {
         Vector3 {
-            x: x,
-            y: y,
-            z: z,
+            x,
+            y,
+            z,
         }
     }
 
+    /// ## with_x
+    /// Returns a copy of this Vector3 with x replaced by the given value
+    pub fn with_x(self, x: f32) -> Vector3 {
+        Vector3 { x, ..self }
+    }
+
+    /// ## with_y
+    /// Returns a copy of this Vector3 with y replaced by the given value
+    pub fn with_y(self, y: f32) -> Vector3 {
+        Vector3 { y, ..self }
+    }
+
+    /// ## with_z
+    /// Returns a copy of this Vector3 with z replaced by the given value
+    pub fn with_z(self, z: f32) -> Vector3 {
+        Vector3 { z, ..self }
+    }
+
     /// ## normal
     /// Returns the normal of this Vector3 as a f32 value
     pub fn normal(&self) -> f32 {
-        (self.x.powf(2.0) +
-         self.y.powf(2.0) +
-         self.z.powf(2.0)).sqrt()
+        self.length_squared().sqrt()
+    }
+
+    /// ## length_squared
+    /// Returns the square of the normal of this Vector3, without taking a square root
+    pub fn length_squared(&self) -> f32 {
+        self.x*self.x + self.y*self.y + self.z*self.z
+    }
+
+    /// ## near_zero
+    /// Returns true if x, y and z are all so close to zero that the Vector3 has no usable direction
+    pub fn near_zero(&self) -> bool {
+        const EPSILON: f32 = 1e-8;
+        self.x.abs() < EPSILON && self.y.abs() < EPSILON && self.z.abs() < EPSILON
     }
 
     /// ## unit_vec
@@ -43,6 +72,18 @@ impl Vector3 {
         }
     }
 
+    /// ## try_unit_vec
+    /// Returns a unit vector by normalizing this Vector3, or None if it has no length to normalize
+    pub fn try_unit_vec(&self) -> Option<Vector3> {
+        self.try_div(self.normal())
+    }
+
+    /// ## try_div
+    /// Returns this Vector3 divided by scalar, or None instead of panicking if scalar is zero
+    pub fn try_div(&self, scalar: f32) -> Option<Vector3> {
+        (scalar != 0.0).then(|| Vector3::new(self.x / scalar, self.y / scalar, self.z / scalar))
+    }
+
     /// ## cross
     /// Returns the cross product of this Vector3 and another given Vector3
     pub fn cross(&self, other: Vector3) -> Vector3 {
@@ -58,6 +99,12 @@ impl Vector3 {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
 
+    /// ## reflect
+    /// Returns this Vector3 mirrored in a surface with the given normal, which is assumed to be a unit vector
+    pub fn reflect(&self, normal: Vector3) -> Vector3 {
+        *self - 2.0 * self.dot(normal) * normal
+    }
+
     /// ## entrywise
     /// Returns the entrywise product of this Vector3 and another given Vector3
     pub fn entrywise(&self, other: Vector3) -> Vector3 {
@@ -68,20 +115,173 @@ impl Vector3 {
         }
     }
 
+    /// ## component_sum
+    /// Returns the sum of x, y and z of this Vector3
+    pub fn component_sum(&self) -> f32 {
+        self.x + self.y + self.z
+    }
+
+    /// ## average
+    /// Returns the average of x, y and z of this Vector3
+    pub fn average(&self) -> f32 {
+        self.component_sum() / 3.0
+    }
+
+    /// ## luminance
+    /// Returns the relative luminance of this Vector3 seen as a linear rgb Color
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
+    /// ## midpoint
+    /// Returns the point halfway between this Vector3 and another given Vector3
+    pub fn midpoint(&self, other: Vector3) -> Vector3 {
+        (*self + other) * 0.5
+    }
+
+    /// ## lerp
+    /// Returns the blend self * (1 - t) + other * t, which is self at t = 0 and other at t = 1.
+    /// t isn't clamped, so values outside 0 to 1 extrapolate along the line through both.
+    pub fn lerp(&self, other: Vector3, t: f32) -> Vector3 {
+        *self * (1.0 - t) + other * t
+    }
+
+    /// ## to_u32_rgba
+    /// Returns this Vector3 seen as a linear rgb Color packed into a u32 as 0xAABBGGRR,
+    /// so red is the lowest byte and alpha, which is always 255, the highest.
+    /// The channels are gamma corrected with gamma 2 and clamped to 0..=255.
+    pub fn to_u32_rgba(self) -> u32 {
+        let byte = |channel: f32| quantize(channel.max(0.0).sqrt()) as u32;
+        (255 << 24) | (byte(self.z) << 16) | (byte(self.y) << 8) | byte(self.x)
+    }
+
+    /// ## to_rgb_bytes
+    /// Returns this Vector3 seen as a linear rgb Color as three bytes, red first.
+    /// The channels are gamma corrected with gamma 2 and quantized like in every image writer,
+    /// so too bright channels become 255 and negative ones 0.
+    pub fn to_rgb_bytes(&self) -> [u8; 3] {
+        let byte = |channel: f32| quantize(channel.max(0.0).sqrt());
+        [byte(self.x), byte(self.y), byte(self.z)]
+    }
+
+    /// ## from_u32_rgba
+    /// Returns the linear rgb Color packed in a u32 as 0xAABBGGRR by to_u32_rgba. Alpha is ignored.
+    pub fn from_u32_rgba(rgba: u32) -> Vector3 {
+        let channel = |shift: u32| {
+            let gamma: f32 = ((rgba >> shift) & 0xff) as f32 / 255.0;
+            gamma * gamma
+        };
+        Vector3::new(channel(0), channel(8), channel(16))
+    }
+
+    /// ## heatmap
+    /// Returns the Color of t on a ramp from blue at 0 through green at 0.5 to red at 1,
+    /// shared by the debug views mapping a scalar to a Color. t outside 0..=1 is clamped.
+    pub fn heatmap(t: f32) -> Vector3 {
+        let t: f32 = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        if t < 0.5 {
+            let s: f32 = t * 2.0;
+            Vector3::new(0.0, s, 1.0 - s)
+        } else {
+            let s: f32 = (t - 0.5) * 2.0;
+            Vector3::new(s, 1.0 - s, 0.0)
+        }
+    }
+
+    /// ## move_towards
+    /// Returns this Vector3 moved towards target by at most max_delta,
+    /// or target itself if it is within max_delta
+    pub fn move_towards(&self, target: Vector3, max_delta: f32) -> Vector3 {
+        let difference: Vector3 = target - *self;
+        let distance: f32 = difference.normal();
+        if distance <= max_delta || distance == 0.0 {
+            return target;
+        }
+        *self + difference * (max_delta / distance)
+    }
+
     /// ## random_in_unit
     /// Returns a random vector withing a unit
     pub fn random_in_unit() -> Vector3 {
-        let mut rng = rand::thread_rng();
+        Vector3::random_in_unit_with(&mut rand::thread_rng())
+    }
+
+    /// ## random_in_unit_with
+    /// Returns a random vector within a unit sphere like random_in_unit, using the given random number generator
+    pub fn random_in_unit_with<R: Rng + ?Sized>(rng: &mut R) -> Vector3 {
         loop {
             let p: Vector3 = Vector3::new(rng.gen_range(-1.0..1.0),
                                           rng.gen_range(-1.0..1.0),
                                           rng.gen_range(-1.0..1.0)) * 2.0
                                  - Vector3::new(1.0, 1.0, 1.0);
-            if p.normal().powf(2.0) < 1.0 {
+            if p.length_squared() < 1.0 {
+                return p;
+            }
+        }
+    }
+
+    /// ## random_in_unit_disk
+    /// Returns a random vector within a unit disk in the xy plane
+    pub fn random_in_unit_disk() -> Vector3 {
+        Vector3::random_in_unit_disk_with(&mut rand::thread_rng())
+    }
+
+    /// ## random_in_unit_disk_with
+    /// Returns a random vector within a unit disk like random_in_unit_disk, using the given random number generator
+    pub fn random_in_unit_disk_with<R: Rng + ?Sized>(rng: &mut R) -> Vector3 {
+        loop {
+            let p: Vector3 = Vector3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), 0.0);
+            if p.length_squared() < 1.0 {
                 return p;
             }
         }
     }
+
+    /// ## random_unit_vector
+    /// Returns a random unit vector, uniformly distributed over all directions.
+    /// Added to a surface normal it gives directions following Lambert's cosine law, while a point
+    /// from random_in_unit is more often close to the center and bunches the directions up around the normal.
+    pub fn random_unit_vector() -> Vector3 {
+        Vector3::random_unit_vector_with(&mut rand::thread_rng())
+    }
+
+    /// ## random_unit_vector_with
+    /// Returns a random unit vector like random_unit_vector, using the given random number generator
+    pub fn random_unit_vector_with<R: Rng + ?Sized>(rng: &mut R) -> Vector3 {
+        Vector3::random_on_sphere_with(1.0, rng)
+    }
+
+    /// ## random_in_hemisphere
+    /// Returns a random unit vector uniformly distributed over the half of all directions on the side of normal
+    pub fn random_in_hemisphere(normal: Vector3) -> Vector3 {
+        Vector3::random_in_hemisphere_with(normal, &mut rand::thread_rng())
+    }
+
+    /// ## random_in_hemisphere_with
+    /// Returns a random unit vector like random_in_hemisphere, using the given random number generator
+    pub fn random_in_hemisphere_with<R: Rng + ?Sized>(normal: Vector3, rng: &mut R) -> Vector3 {
+        let p: Vector3 = Vector3::random_unit_vector_with(rng);
+        if p.dot(normal) >= 0.0 { p } else { -p }
+    }
+
+    /// ## random_on_sphere
+    /// Returns a random point uniformly distributed on the surface of a sphere
+    /// with the given radius centered at the origin
+    pub fn random_on_sphere(radius: f32) -> Vector3 {
+        Vector3::random_on_sphere_with(radius, &mut rand::thread_rng())
+    }
+
+    /// ## random_on_sphere_with
+    /// Returns a random point on a sphere like random_on_sphere, using the given random number generator
+    pub fn random_on_sphere_with<R: Rng + ?Sized>(radius: f32, rng: &mut R) -> Vector3 {
+        loop {
+            let p: Vector3 = Vector3::random_in_unit_with(rng);
+            let normal: f32 = p.normal();
+            if normal > 1e-4 { // Too short vectors lose their direction when normalized
+                return p * (radius / normal);
+            }
+        }
+    }
 }
 
 // Operator overloading for Vector3 math
@@ -129,6 +329,18 @@ impl ops::SubAssign for Vector3 {
     }
 }
 
+impl ops::Neg for Vector3 {
+    type Output = Vector3;
+
+    fn neg(self) -> Vector3 {
+        Vector3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
 impl ops::Mul<f32> for Vector3 {
     type Output = Vector3;
 
@@ -141,6 +353,23 @@ impl ops::Mul<f32> for Vector3 {
     }
 }
 
+impl ops::Mul<Vector3> for f32 {
+    type Output = Vector3;
+
+    fn mul(self, other: Vector3) -> Vector3 {
+        other * self
+    }
+}
+
+// Multiplying two Vector3 multiplies them entrywise, like colors are combined
+impl ops::Mul for Vector3 {
+    type Output = Vector3;
+
+    fn mul(self, other: Vector3) -> Vector3 {
+        self.entrywise(other)
+    }
+}
+
 impl ops::MulAssign<f32> for Vector3 {
     fn mul_assign(&mut self, other: f32) {
         *self = Vector3 {
@@ -151,6 +380,31 @@ impl ops::MulAssign<f32> for Vector3 {
     }
 }
 
+// Indexing a Vector3 gives x, y and z for the axes 0, 1 and 2
+impl ops::Index<usize> for Vector3 {
+    type Output = f32;
+
+    fn index(&self, axis: usize) -> &f32 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Axis {} is out of range for a Vector3", axis),
+        }
+    }
+}
+
+impl ops::IndexMut<usize> for Vector3 {
+    fn index_mut(&mut self, axis: usize) -> &mut f32 {
+        match axis {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("Axis {} is out of range for a Vector3", axis),
+        }
+    }
+}
+
 // To avoid unexpected issues IEEE 754 standard for floating point numbers will be ignored
 impl ops::Div<f32> for Vector3 {
     type Output = Vector3;
@@ -180,10 +434,41 @@ impl ops::DivAssign<f32> for Vector3 {
     }
 }
 
+impl From<(f32, f32, f32)> for Vector3 {
+    fn from((x, y, z): (f32, f32, f32)) -> Vector3 {
+        Vector3::new(x, y, z)
+    }
+}
+
+impl From<[f32; 3]> for Vector3 {
+    fn from([x, y, z]: [f32; 3]) -> Vector3 {
+        Vector3::new(x, y, z)
+    }
+}
+
+impl From<Vector3> for (f32, f32, f32) {
+    fn from(v: Vector3) -> (f32, f32, f32) {
+        (v.x, v.y, v.z)
+    }
+}
+
+impl From<Vector3> for [f32; 3] {
+    fn from(v: Vector3) -> [f32; 3] {
+        [v.x, v.y, v.z]
+    }
+}
+
 /// ## Color
 /// Special Vector3 where x, y, z, represent r, g, b, of a color
 pub type Color = Vector3;
 
+/// ## quantize
+/// Returns a color channel between 0 and 1 as a byte, the conversion shared by every image writer.
+/// Channels outside 0 to 1 are clamped to 0..=255 and NaN becomes 0.
+pub fn quantize(channel: f32) -> u8 {
+    (255.99 * channel).clamp(0.0, 255.0) as u8
+}
+
 /// Tests for Vector3 struct
 #[cfg(test)]
 mod tests {
@@ -273,6 +558,29 @@ mod tests {
         assert_eq!(c, a)
     }
 
+    #[test]
+    fn vector3_neg() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+
+        assert_eq!(Vector3::new(-1.0, -2.0, -3.0), -a)
+    }
+
+    #[test]
+    fn vector3_neg_mixed() {
+        let a = Vector3::new(-1.5, 0.5, -4.0);
+
+        assert_eq!(Vector3::new(1.5, -0.5, 4.0), -a)
+    }
+
+    #[test]
+    fn vector3_neg_zero() {
+        let a = Vector3::new(0.0, 0.0, 0.0);
+        let b = -a;
+
+        assert_eq!(a, b);
+        assert_eq!(b.normal(), 0.0)
+    }
+
     #[test]
     fn vector3_scalar_mul() {
         let a = Vector3::new(4.0, 6.0, 2.0);
@@ -309,6 +617,24 @@ mod tests {
         assert_eq!(c, a * b)
     }
 
+    #[test]
+    fn vector3_scalar_mul_left() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+
+        assert_eq!(Vector3::new(2.0, 4.0, 6.0), 2.0 * a);
+        assert_eq!(a * -0.5, -0.5 * a)
+    }
+
+    #[test]
+    fn vector3_mul() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+        let b = Vector3::new(4.0, 5.0, 6.0);
+
+        assert_eq!(Vector3::new(4.0, 10.0, 18.0), a * b);
+        assert_eq!(a.entrywise(b), a * b);
+        assert_eq!(Vector3::new(2.0, 4.0, 6.0), a * 2.0)
+    }
+
     #[test]
     fn vector3_scalar_mul_assign() {
         let mut a = Vector3::new(4.0, 6.0, 2.0);
@@ -365,6 +691,32 @@ mod tests {
         assert_eq!(c, a)
     }
 
+    #[test]
+    fn vector3_index() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+
+        assert_eq!(a[0], a.x);
+        assert_eq!(a[1], a.y);
+        assert_eq!(a[2], a.z)
+    }
+
+    #[test]
+    #[should_panic]
+    fn vector3_index_out_of_range() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+
+        let _ = a[3];
+    }
+
+    #[test]
+    fn vector3_index_mut() {
+        let mut a = Vector3::new(1.0, 2.0, 3.0);
+        a[1] = 5.0;
+        a[2] += 1.0;
+
+        assert_eq!(Vector3::new(1.0, 5.0, 4.0), a)
+    }
+
     #[test]
     fn vector3_dot() {
         let a = Vector3::new(1.0, 2.0, 3.0);
@@ -392,6 +744,16 @@ mod tests {
         assert_eq!(c, a.dot(b))
     }
 
+    #[test]
+    fn vector3_reflect() {
+        let normal = Vector3::new(0.0, 1.0, 0.0);
+
+        assert_eq!(Vector3::new(1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0).reflect(normal));
+        assert_eq!(Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0).reflect(normal));
+        // A grazing direction along the surface isn't changed
+        assert_eq!(Vector3::new(1.0, 0.0, -1.0), Vector3::new(1.0, 0.0, -1.0).reflect(normal))
+    }
+
     #[test]
     fn vector3_cross() {
         let a = Vector3::new(1.0, 2.0, 3.0);
@@ -464,6 +826,38 @@ mod tests {
         assert_eq!(a.normal(), 0.0);
     }
 
+    #[test]
+    fn vector3_length_squared() {
+        let a = Vector3::new(4.0, 4.0, 2.0);
+
+        assert_eq!(a.length_squared(), 36.0);
+        assert!((a.length_squared() - a.normal().powf(2.0)).abs() < 1e-5)
+    }
+
+    #[test]
+    fn vector3_try_unit_vec() {
+        let a = Vector3::new(0.0, 3.0, 4.0);
+
+        assert_eq!(a.try_unit_vec(), Some(Vector3::new(0.0, 0.6, 0.8)));
+        assert_eq!(Vector3::new(0.0, 0.0, 0.0).try_unit_vec(), None)
+    }
+
+    #[test]
+    fn vector3_try_div() {
+        let a = Vector3::new(4.0, 6.0, 2.0);
+
+        assert_eq!(a.try_div(2.0), Some(Vector3::new(2.0, 3.0, 1.0)));
+        assert_eq!(a.try_div(0.0), None);
+        assert_eq!(a.try_div(-0.0), None)
+    }
+
+    #[test]
+    fn vector3_near_zero() {
+        assert!(Vector3::new(1e-9, 1e-9, 1e-9).near_zero());
+        assert!(Vector3::new(-1e-9, 0.0, 1e-9).near_zero());
+        assert!(!Vector3::new(1e-9, 0.1, 1e-9).near_zero())
+    }
+
     #[test]
     fn vector3_normalized() {
         let a = Vector3::new(2.0, 0.0, 0.0);
@@ -487,4 +881,234 @@ mod tests {
         let result = std::panic::catch_unwind(|| a.unit_vec() );
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn vector3_random_in_unit_disk() {
+        for _ in 0..1000 {
+            let p = Vector3::random_in_unit_disk();
+            assert_eq!(p.z, 0.0);
+            assert!(p.length_squared() < 1.0);
+        }
+    }
+
+    #[test]
+    fn vector3_random_with_seeded() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let mut a = StdRng::seed_from_u64(4);
+        let mut b = StdRng::seed_from_u64(4);
+        for _ in 0..100 {
+            assert_eq!(Vector3::random_in_unit_with(&mut a), Vector3::random_in_unit_with(&mut b));
+            assert_eq!(Vector3::random_in_unit_disk_with(&mut a), Vector3::random_in_unit_disk_with(&mut b));
+            assert_eq!(Vector3::random_unit_vector_with(&mut a), Vector3::random_unit_vector_with(&mut b));
+        }
+    }
+
+    #[test]
+    fn vector3_random_unit_vector() {
+        for _ in 0..10000 {
+            assert!((Vector3::random_unit_vector().normal() - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn vector3_random_in_hemisphere() {
+        let normal = Vector3::new(0.0, 0.6, -0.8);
+        for _ in 0..1000 {
+            let p = Vector3::random_in_hemisphere(normal);
+            assert!((p.normal() - 1.0).abs() < 1e-5);
+            assert!(p.dot(normal) >= 0.0);
+        }
+    }
+
+    #[test]
+    fn vector3_random_on_sphere_radius() {
+        for _ in 0..1000 {
+            let p = Vector3::random_on_sphere(2.5);
+            assert!((p.normal() - 2.5).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn vector3_random_on_sphere_octants() {
+        let mut octants = [false; 8];
+        for _ in 0..1000 {
+            let p = Vector3::random_on_sphere(1.0);
+            let index = (p.x > 0.0) as usize + 2 * (p.y > 0.0) as usize + 4 * (p.z > 0.0) as usize;
+            octants[index] = true;
+        }
+        assert!(octants.iter().all(|&covered| covered));
+    }
+
+    #[test]
+    fn vector3_luminance() {
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        let black = Vector3::new(0.0, 0.0, 0.0);
+
+        assert!((white.luminance() - 1.0).abs() < 1e-6);
+        assert_eq!(black.luminance(), 0.0);
+        assert!(Vector3::new(0.0, 1.0, 0.0).luminance() > Vector3::new(1.0, 0.0, 0.0).luminance());
+    }
+
+    #[test]
+    fn vector3_component_sum() {
+        let a = Vector3::new(1.0, -2.0, 4.0);
+        assert_eq!(a.component_sum(), 3.0);
+    }
+
+    #[test]
+    fn vector3_component_sum_zero() {
+        let a = Vector3::new(0.0, 0.0, 0.0);
+        assert_eq!(a.component_sum(), 0.0);
+    }
+
+    #[test]
+    fn vector3_average() {
+        let a = Vector3::new(1.0, -2.0, 4.0);
+        assert_eq!(a.average(), 1.0);
+    }
+
+    #[test]
+    fn vector3_average_zero() {
+        let a = Vector3::new(0.0, 0.0, 0.0);
+        assert_eq!(a.average(), 0.0);
+    }
+
+    #[test]
+    fn vector3_move_towards_partial() {
+        let a = Vector3::new(0.0, 0.0, 0.0);
+        let b = Vector3::new(3.0, 4.0, 0.0);
+        assert_eq!(a.move_towards(b, 2.5), Vector3::new(1.5, 2.0, 0.0));
+    }
+
+    #[test]
+    fn vector3_move_towards_snaps() {
+        let a = Vector3::new(1.0, 1.0, 1.0);
+        let b = Vector3::new(1.0, 3.0, 1.0);
+        assert_eq!(a.move_towards(b, 5.0), b);
+    }
+
+    #[test]
+    fn vector3_move_towards_zero_distance() {
+        let a = Vector3::new(1.0, -2.0, 4.0);
+        assert_eq!(a.move_towards(a, 1.0), a);
+        assert_eq!(a.move_towards(a, 0.0), a);
+    }
+
+    #[test]
+    fn vector3_with_z() {
+        let a = Vector3::new(1.0, -2.0, 4.0);
+        assert_eq!(a.with_z(0.0), Vector3::new(1.0, -2.0, 0.0));
+    }
+
+    #[test]
+    fn vector3_with_x_y() {
+        let a = Vector3::new(1.0, -2.0, 4.0);
+        assert_eq!(a.with_x(3.0), Vector3::new(3.0, -2.0, 4.0));
+        assert_eq!(a.with_y(3.0), Vector3::new(1.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn vector3_midpoint() {
+        let a = Vector3::new(1.0, -2.0, 4.0);
+        let b = Vector3::new(3.0, 2.0, -4.0);
+        assert_eq!(a.midpoint(b), Vector3::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn vector3_midpoint_self() {
+        let a = Vector3::new(1.0, -2.0, 4.0);
+        assert_eq!(a.midpoint(a), a);
+    }
+
+    #[test]
+    fn vector3_lerp() {
+        let a = Vector3::new(1.0, -2.0, 4.0);
+        let b = Vector3::new(3.0, 2.0, -4.0);
+
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 1.0), b);
+        assert_eq!(a.lerp(b, 0.5), a.midpoint(b));
+        assert_eq!(a.lerp(b, 2.0), Vector3::new(5.0, 6.0, -12.0));
+    }
+
+    #[test]
+    fn vector3_from_tuple() {
+        let v: Vector3 = (1.0, -2.0, 3.5).into();
+        assert_eq!(v, Vector3::new(1.0, -2.0, 3.5));
+
+        let tuple: (f32, f32, f32) = v.into();
+        assert_eq!(tuple, (1.0, -2.0, 3.5));
+    }
+
+    #[test]
+    fn vector3_from_array() {
+        let v: Vector3 = [1.0, -2.0, 3.5].into();
+        assert_eq!(v, Vector3::new(1.0, -2.0, 3.5));
+
+        let array: [f32; 3] = v.into();
+        assert_eq!(array, [1.0, -2.0, 3.5]);
+        assert_eq!(Vector3::from(<[f32; 3]>::from(v)), v);
+    }
+
+    #[test]
+    fn vector3_to_u32_rgba() {
+        assert_eq!(Vector3::new(1.0, 1.0, 1.0).to_u32_rgba(), 0xFFFFFFFF);
+        assert_eq!(Vector3::new(0.0, 0.0, 0.0).to_u32_rgba(), 0xFF000000);
+        assert_eq!(Vector3::new(1.0, 0.0, 0.0).to_u32_rgba(), 0xFF0000FF);
+        assert_eq!(Vector3::new(0.0, 0.0, 4.0).to_u32_rgba(), 0xFFFF0000);
+    }
+
+    #[test]
+    fn vector3_heatmap() {
+        let cold = Vector3::heatmap(0.0);
+        let hot = Vector3::heatmap(1.0);
+        assert_eq!(cold, Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(hot, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(Vector3::heatmap(-3.0), cold);
+        assert_eq!(Vector3::heatmap(7.0), hot);
+        assert_eq!(Vector3::heatmap(f32::NAN), cold);
+
+        let warm = Vector3::heatmap(0.75);
+        assert!(warm.x > cold.x && warm.x < hot.x);
+        assert!(warm.z < cold.z && warm.z >= hot.z);
+        assert_eq!(Vector3::heatmap(0.5), Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn quantize_channels() {
+        assert_eq!(quantize(0.0), 0);
+        assert_eq!(quantize(0.25), 63);
+        assert_eq!(quantize(0.5), 127);
+        assert_eq!(quantize(1.0), 255);
+        assert_eq!(quantize(2.0), 255);
+        assert_eq!(quantize(-1.0), 0);
+        assert_eq!(quantize(f32::NAN), 0);
+    }
+
+    #[test]
+    fn vector3_rgb_bytes_match_u32_rgba() {
+        for color in [Vector3::new(0.0625, 0.25, 0.5), Vector3::new(0.9, 0.01, 1.5), Vector3::new(0.3, 0.6, 0.0)] {
+            let rgba = color.to_u32_rgba();
+            assert_eq!(color.to_rgb_bytes(), [rgba as u8, (rgba >> 8) as u8, (rgba >> 16) as u8]);
+        }
+    }
+
+    #[test]
+    fn vector3_to_rgb_bytes() {
+        assert_eq!(Vector3::new(2.5, -0.3, 0.25).to_rgb_bytes(), [255, 0, 127]);
+        assert_eq!(Vector3::new(1.0, 0.0, f32::NAN).to_rgb_bytes(), [255, 0, 0]);
+        assert_eq!(Vector3::new(1.0e30, -1.0e30, 0.0).to_rgb_bytes(), [255, 0, 0]);
+    }
+
+    #[test]
+    fn vector3_u32_rgba_round_trip() {
+        let a = Vector3::new(0.2, 0.5, 0.8);
+        let b = Vector3::from_u32_rgba(a.to_u32_rgba());
+
+        // One step of 8 bits in gamma space is at most 2/255 in linear space
+        assert!((a.x - b.x).abs() < 2.0 / 255.0);
+        assert!((a.y - b.y).abs() < 2.0 / 255.0);
+        assert!((a.z - b.z).abs() < 2.0 / 255.0);
+    }
+}